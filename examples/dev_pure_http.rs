@@ -1,10 +1,7 @@
 // cargo run --example credentials
 use dotenv::dotenv;
-use reqwest;
-use serde_json::{from_str, to_string, to_string_pretty, Value};
+use serde_json::{from_str, Value};
 use std::env;
-use tokio;
-use anyhow;
 use lazy_static::lazy_static;
 
 use rsclashapi::auth::credentials::*;
@@ -16,19 +13,13 @@ fn get_credentials() -> Credentials {
     let email = env::var("EMAIL").expect("EMAIL environment variable missing");
     let password = env::var("PASSWORD").expect("PASSWORD environment variable missing");
 
-    let credentials = Credentials::builder()
+    Credentials::builder()
         .add_credential(email, password)
-        .build();
-
-    credentials
+        .build()
 }
 
 fn format_tag(tag: &str) -> String {
-    if tag.starts_with('#') {
-        format!("%23{}", &tag[1..])
-    } else {
-        format!("%23{}", tag)
-    }
+    format!("%23{}", tag.strip_prefix('#').unwrap_or(tag))
 }
 
 lazy_static! {
@@ -74,7 +65,7 @@ async fn main() -> Result<(), anyhow::Error> {
 
     // let credential = credentials.0.first().unwrap().clone();
 
-    let api_account = APIAccount {
+    let _api_account = APIAccount {
         credentials,
         response: login_response.clone(),
         keys,
@@ -1,10 +1,6 @@
 // cargo run --example format_tag
 fn format_tag(tag: &str) -> String {
-    if tag.starts_with('#') {
-        format!("%23{}", &tag[1..])
-    } else {
-        format!("%23{}", tag)
-    }
+    format!("%23{}", tag.strip_prefix('#').unwrap_or(tag))
 }
 
 fn main() {
@@ -1,5 +1,4 @@
 // cargo run --example ip_addr
-use reqwest;
 use std::net::IpAddr;
 use std::str::FromStr;
 
@@ -0,0 +1,362 @@
+//! Tracks donations of a clan's members across polls of its member list.
+//! `ClanMember::donations` and `donations_received` are season totals that
+//! reset when the season ends, so the tracker accumulates the increase between
+//! polls instead of trusting the raw counters.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, SystemTime};
+
+use crate::models::clan::{ClanMember, ClanMemberList};
+use crate::utils::time::{season_end, season_of};
+
+/// How long after a season ends the API may still report the old counters
+const RESET_GRACE: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PlayerDonations {
+    pub tag: String,
+    pub name: String,
+    pub donated: u32,
+    pub received: u32,
+}
+
+impl PlayerDonations {
+    /// Troops donated per troop received. Players that have not received
+    /// anything get their donation count.
+    #[must_use]
+    pub fn ratio(&self) -> f32 {
+        if self.received == 0 {
+            self.donated as f32
+        } else {
+            self.donated as f32 / self.received as f32
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SeasonDonations {
+    /// Season id in "YYYY-MM" form
+    pub season: String,
+    pub players: HashMap<String, PlayerDonations>,
+}
+
+impl SeasonDonations {
+    #[must_use]
+    pub fn total_donated(&self) -> u32 {
+        self.players.values().map(|player| player.donated).sum()
+    }
+
+    #[must_use]
+    pub fn total_received(&self) -> u32 {
+        self.players.values().map(|player| player.received).sum()
+    }
+
+    /// Players ordered by troops donated, most first
+    #[must_use]
+    pub fn leaderboard(&self) -> Vec<&PlayerDonations> {
+        let mut players: Vec<&PlayerDonations> = self.players.values().collect();
+        players.sort_by(|a, b| b.donated.cmp(&a.donated).then_with(|| a.tag.cmp(&b.tag)));
+        players
+    }
+
+    /// Players ordered by donation ratio, highest first
+    #[must_use]
+    pub fn ratios(&self) -> Vec<&PlayerDonations> {
+        let mut players: Vec<&PlayerDonations> = self.players.values().collect();
+        players.sort_by(|a, b| {
+            b.ratio()
+                .total_cmp(&a.ratio())
+                .then_with(|| a.tag.cmp(&b.tag))
+        });
+        players
+    }
+}
+
+/// Donation counters of a member as of the last update
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct Counters {
+    donated: u32,
+    received: u32,
+}
+
+impl From<&ClanMember> for Counters {
+    fn from(member: &ClanMember) -> Self {
+        Self {
+            donated: member.donations,
+            received: member.donations_received,
+        }
+    }
+}
+
+/// Increase of a counter, a counter lower than before has been reset and
+/// counts up from zero again
+fn increase(before: u32, after: u32) -> u32 {
+    if after >= before {
+        after - before
+    } else {
+        after
+    }
+}
+
+/// Accumulates donations per season. The tracker is serializable so it can be
+/// persisted between restarts.
+///
+/// Counters start over some time after the season ends. Until the counters
+/// of the clan go down, or a day has passed, increases are still credited to
+/// the season that ended. Once they have been reset the counters of the
+/// previous update count as zero, so donations made between the reset and the
+/// next poll are credited to the new season even when a counter is higher than
+/// before.
+///
+/// Counters of members present in the first update are credited in full.
+/// Members that join later, or rejoin after leaving, only have donations
+/// made from then on credited, since the counters also include donations made
+/// in other clans.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DonationTracker {
+    /// Counters of the members present in the last update
+    counters: HashMap<String, Counters>,
+    seasons: BTreeMap<String, SeasonDonations>,
+    /// Season (year, month) the counters of the last update belong to,
+    /// `None` before the first update
+    counter_season: Option<(i64, u32)>,
+}
+
+impl DonationTracker {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the member list as fetched at `now`
+    pub fn update(&mut self, members: &ClanMemberList, now: SystemTime) {
+        let first_update = self.counter_season.is_none();
+        let now_season = season_of(now);
+        let counter_season = self.counter_season.unwrap_or(now_season);
+        let reset = counter_season != now_season
+            && (self.dropped(members)
+                || now >= season_end(counter_season.0, counter_season.1) + RESET_GRACE);
+        let (year, month) = if reset { now_season } else { counter_season };
+        self.counter_season = Some((year, month));
+
+        let season = format!("{year:04}-{month:02}");
+        let totals = self
+            .seasons
+            .entry(season.clone())
+            .or_insert_with(|| SeasonDonations {
+                season,
+                players: HashMap::new(),
+            });
+
+        let mut counters = HashMap::new();
+        for member in &members.0 {
+            let current = Counters::from(member);
+            let credit = match self.counters.get(&member.tag) {
+                Some(_) if reset => current,
+                Some(previous) => Counters {
+                    donated: increase(previous.donated, current.donated),
+                    received: increase(previous.received, current.received),
+                },
+                None if first_update => current,
+                None => Counters {
+                    donated: 0,
+                    received: 0,
+                },
+            };
+
+            let player = totals.players.entry(member.tag.clone()).or_default();
+            player.tag = member.tag.clone();
+            player.name = member.name.clone();
+            player.donated += credit.donated;
+            player.received += credit.received;
+
+            counters.insert(member.tag.clone(), current);
+        }
+
+        self.counters = counters;
+    }
+
+    /// Whether a counter of a member is lower than in the last update
+    fn dropped(&self, members: &ClanMemberList) -> bool {
+        members.0.iter().any(|member| {
+            self.counters.get(&member.tag).is_some_and(|previous| {
+                member.donations < previous.donated || member.donations_received < previous.received
+            })
+        })
+    }
+
+    #[must_use]
+    pub fn season(&self, season: &str) -> Option<&SeasonDonations> {
+        self.seasons.get(season)
+    }
+
+    /// Totals of the most recent season that has been tracked
+    #[must_use]
+    pub fn current_season(&self) -> Option<&SeasonDonations> {
+        self.seasons.values().next_back()
+    }
+
+    /// Every tracked season, oldest first
+    pub fn seasons(&self) -> impl Iterator<Item = &SeasonDonations> {
+        self.seasons.values()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::DonationTracker;
+    use crate::models::clan::{Clan, ClanMemberList};
+    use crate::utils::time::parse_api_time;
+
+    fn members() -> ClanMemberList {
+        let clan: Clan =
+            serde_json::from_str(include_str!("../../tests/fixtures/clan.json")).unwrap();
+        clan.member_list.unwrap()
+    }
+
+    #[test]
+    fn test_accumulate_and_reset() {
+        let mut tracker = DonationTracker::new();
+        let mut members = members();
+        let start = members.0[0].donations;
+
+        tracker.update(&members, parse_api_time("20240128T000000.000Z").unwrap());
+        members.0[0].donations += 100;
+        members.0[1].donations_received += 40;
+        tracker.update(&members, parse_api_time("20240129T000000.000Z").unwrap());
+
+        let january = tracker.season("2024-01").unwrap();
+        assert_eq!(january.players["#P0"].donated, start + 100);
+        assert_eq!(january.leaderboard()[0].tag, "#P0");
+
+        // The season ends and every counter starts over
+        for member in &mut members.0 {
+            member.donations = 0;
+            member.donations_received = 0;
+        }
+        members.0[0].donations = 25;
+        tracker.update(&members, parse_api_time("20240129T060000.000Z").unwrap());
+
+        let february = tracker.current_season().unwrap();
+        assert_eq!(february.season, "2024-02");
+        assert_eq!(february.players["#P0"].donated, 25);
+        assert_eq!(february.total_donated(), 25);
+        assert_eq!(
+            tracker.season("2024-01").unwrap().players["#P0"].donated,
+            start + 100
+        );
+    }
+
+    /// Members with every counter at zero but `#P0` at `p0` and `#P2` at
+    /// `p2` donations
+    fn with_donations(p0: u32, p2: u32) -> ClanMemberList {
+        let mut members = members();
+        for member in &mut members.0 {
+            member.donations = 0;
+            member.donations_received = 0;
+        }
+        members.0[0].donations = p0;
+        members.0[1].donations = p2;
+        members
+    }
+
+    fn donated(tracker: &DonationTracker, season: &str, tag: &str) -> u32 {
+        tracker.season(season).unwrap().players[tag].donated
+    }
+
+    #[test]
+    fn test_counter_higher_after_reset() {
+        let mut tracker = DonationTracker::new();
+        tracker.update(
+            &with_donations(500, 300),
+            parse_api_time("20240129T040000.000Z").unwrap(),
+        );
+
+        // The season ends at 05:00 and #P0 donates 600 troops before the next
+        // poll, #P2 shows the counters were reset
+        tracker.update(
+            &with_donations(600, 0),
+            parse_api_time("20240129T070000.000Z").unwrap(),
+        );
+
+        assert_eq!(donated(&tracker, "2024-01", "#P0"), 500);
+        assert_eq!(donated(&tracker, "2024-02", "#P0"), 600);
+    }
+
+    #[test]
+    fn test_late_reset() {
+        let mut tracker = DonationTracker::new();
+        tracker.update(
+            &with_donations(500, 300),
+            parse_api_time("20240129T040000.000Z").unwrap(),
+        );
+
+        // The season has ended but the API still reports the old counters
+        tracker.update(
+            &with_donations(550, 300),
+            parse_api_time("20240129T060000.000Z").unwrap(),
+        );
+        assert_eq!(donated(&tracker, "2024-01", "#P0"), 550);
+        assert!(tracker.season("2024-02").is_none());
+
+        tracker.update(
+            &with_donations(20, 0),
+            parse_api_time("20240129T080000.000Z").unwrap(),
+        );
+        tracker.update(
+            &with_donations(30, 5),
+            parse_api_time("20240129T090000.000Z").unwrap(),
+        );
+        assert_eq!(donated(&tracker, "2024-01", "#P0"), 550);
+        assert_eq!(donated(&tracker, "2024-02", "#P0"), 30);
+        assert_eq!(donated(&tracker, "2024-02", "#P2"), 5);
+    }
+
+    #[test]
+    fn test_reset_without_donations() {
+        let mut tracker = DonationTracker::new();
+        let none = with_donations(0, 0);
+        tracker.update(&none, parse_api_time("20240128T000000.000Z").unwrap());
+
+        // No counter can go down, the reset is assumed after a day
+        tracker.update(
+            &with_donations(40, 0),
+            parse_api_time("20240130T060000.000Z").unwrap(),
+        );
+        assert_eq!(donated(&tracker, "2024-01", "#P0"), 0);
+        assert_eq!(donated(&tracker, "2024-02", "#P0"), 40);
+    }
+
+    #[test]
+    fn test_leave_and_rejoin() {
+        let mut tracker = DonationTracker::new();
+        let full = members();
+        let now = parse_api_time("20240110T000000.000Z").unwrap();
+        tracker.update(&full, now);
+        let credited = tracker.current_season().unwrap().players["#P9"].donated;
+
+        let mut without = full.clone();
+        let mut away = without.0.remove(3);
+        tracker.update(&without, now);
+
+        // Donations made while in another clan are not credited
+        away.donations += 500;
+        away.donations_received += 10;
+        let mut back = without.clone();
+        back.0.push(away);
+        tracker.update(&back, now);
+        back.0[3].donations += 7;
+        tracker.update(&back, now);
+
+        let season = tracker.current_season().unwrap();
+        assert_eq!(season.players["#P9"].donated, credited + 7);
+
+        let json = serde_json::to_string(&tracker).unwrap();
+        let restored: DonationTracker = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            restored.current_season().unwrap().total_donated(),
+            season.total_donated()
+        );
+    }
+}
@@ -0,0 +1,510 @@
+//! Tracks legend league attacks and defenses by polling players. The API
+//! only exposes a player's trophies and win counters, so every change between
+//! two polls is inferred from the trophy difference together with the change
+//! of `attack_wins` and `defense_wins`.
+//!
+//! A legend league attack gains, and a defense loses, at most 40 trophies.
+//! When a player attacked and was attacked between the same two polls only the
+//! net change is known, such changes are recorded as [`LegendChange::Mixed`].
+//! Poll often, ideally every few minutes, to keep those rare.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::time::SystemTime;
+use tokio::task::JoinSet;
+
+use crate::calendar::legend_day;
+use crate::core::Client;
+use crate::error::APIError;
+use crate::models::player::Player;
+use crate::utils::normalize_tag;
+use crate::utils::time::season_id;
+
+/// Most trophies a single attack can gain or a single defense can lose
+pub const MAX_TROPHIES_PER_BATTLE: u32 = 40;
+/// Attacks, and defenses, a player gets per legend day
+pub const BATTLES_PER_DAY: u32 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LegendChange {
+    /// Trophies gained by `count` attacks
+    Attack { trophies: u32, count: u32 },
+    /// Trophies lost by `count` defenses
+    Defense { trophies: u32, count: u32 },
+    /// Attacks and defenses that happened between the same two polls, only
+    /// the net change of trophies is known
+    Mixed {
+        net: i32,
+        attacks: u32,
+        defenses: u32,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LegendEntry {
+    pub tag: String,
+    pub time: SystemTime,
+    /// Trophies after the change
+    pub trophies: u32,
+    pub change: LegendChange,
+}
+
+/// Summary of a player's legend league day
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LegendDay {
+    pub tag: String,
+    pub name: String,
+    /// Start of the day, days roll over at 05:00
+    pub start: SystemTime,
+    pub start_trophies: u32,
+    pub end_trophies: u32,
+    pub attacks: u32,
+    pub attack_trophies: u32,
+    pub defenses: u32,
+    pub defense_trophies: u32,
+    /// Net trophies of mixed changes, which can not be split into attacks and
+    /// defenses
+    pub unresolved_trophies: i32,
+    pub entries: Vec<LegendEntry>,
+}
+
+impl LegendDay {
+    #[must_use]
+    pub fn net(&self) -> i64 {
+        i64::from(self.end_trophies) - i64::from(self.start_trophies)
+    }
+
+    /// Whether every attack of the day has been used
+    #[must_use]
+    pub fn attacks_done(&self) -> bool {
+        self.attacks >= BATTLES_PER_DAY
+    }
+
+    #[must_use]
+    pub fn remaining_attacks(&self) -> u32 {
+        BATTLES_PER_DAY.saturating_sub(self.attacks)
+    }
+
+    fn record(&mut self, entry: LegendEntry) {
+        match entry.change {
+            LegendChange::Attack { trophies, count } => {
+                self.attacks += count;
+                self.attack_trophies += trophies;
+            }
+            LegendChange::Defense { trophies, count } => {
+                self.defenses += count;
+                self.defense_trophies += trophies;
+            }
+            LegendChange::Mixed {
+                net,
+                attacks,
+                defenses,
+            } => {
+                self.attacks += attacks;
+                self.defenses += defenses;
+                self.unresolved_trophies += net;
+            }
+        }
+        self.end_trophies = entry.trophies;
+        self.entries.push(entry);
+    }
+}
+
+/// Trophies and win counters of a player as of the last poll
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct Baseline {
+    time: SystemTime,
+    trophies: u32,
+    attack_wins: u32,
+    defense_wins: u32,
+}
+
+impl Baseline {
+    fn of(player: &Player, time: SystemTime) -> Self {
+        Self {
+            time,
+            trophies: player.trophies,
+            attack_wins: player.attack_wins,
+            defense_wins: player.defense_wins,
+        }
+    }
+}
+
+/// Increase of a win counter, a counter lower than before has been reset at
+/// the end of the season
+fn increase(before: u32, after: u32) -> u32 {
+    if after >= before {
+        after - before
+    } else {
+        after
+    }
+}
+
+/// Battles needed to move `trophies`
+fn battles(trophies: u32) -> u32 {
+    trophies.div_ceil(MAX_TROPHIES_PER_BATTLE).max(1)
+}
+
+/// Classify the change between two polls of a player, `None` if nothing
+/// happened
+fn classify(before: &Baseline, after: &Baseline) -> Option<LegendChange> {
+    let net = i64::from(after.trophies) - i64::from(before.trophies);
+    let attacks = increase(before.attack_wins, after.attack_wins);
+    let defenses = increase(before.defense_wins, after.defense_wins);
+    let trophies = net.unsigned_abs() as u32;
+
+    let change = match net {
+        // A gain is attacks only, unless a defense was won at the same time. A
+        // gain more than the won attacks can explain implies attacks that did
+        // not count as wins.
+        1.. if defenses == 0 => LegendChange::Attack {
+            trophies,
+            count: battles(trophies).max(attacks),
+        },
+        ..=-1 if attacks == 0 => LegendChange::Defense {
+            trophies,
+            count: battles(trophies).max(defenses),
+        },
+        0 if attacks == 0 && defenses == 0 => return None,
+        0 if attacks == 0 => LegendChange::Defense {
+            trophies: 0,
+            count: defenses,
+        },
+        _ => LegendChange::Mixed {
+            net: net as i32,
+            attacks: attacks.max(1),
+            defenses: defenses.max(1),
+        },
+    };
+    Some(change)
+}
+
+/// Records the legend league days of the tracked players. The tracker is
+/// serializable so it can be persisted between restarts.
+///
+/// The first poll of a player only sets a baseline. Changes are attributed to
+/// the legend day of the poll that saw them, and polls that span the end of a
+/// season, when trophies are reset, start over from a new baseline.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LegendTracker {
+    tags: BTreeSet<String>,
+    baselines: HashMap<String, Baseline>,
+    /// Days of every player, oldest first
+    days: HashMap<String, Vec<LegendDay>>,
+}
+
+impl LegendTracker {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn track(&mut self, tag: &str) {
+        self.tags.insert(normalize_tag(tag));
+    }
+
+    pub fn untrack(&mut self, tag: &str) {
+        let tag = normalize_tag(tag);
+        self.tags.remove(&tag);
+        self.baselines.remove(&tag);
+    }
+
+    pub fn tracked(&self) -> impl Iterator<Item = &str> {
+        self.tags.iter().map(String::as_str)
+    }
+
+    /// Fetch every tracked player and record what changed. Players that were
+    /// fetched are recorded even if fetching another one failed, the failures
+    /// are returned next to the entries with the tag they were for.
+    pub async fn poll(&mut self, client: &Client) -> (Vec<LegendEntry>, Vec<(String, APIError)>) {
+        let mut tasks = JoinSet::new();
+        let mut tags = HashMap::new();
+        for tag in &self.tags {
+            let client = client.clone();
+            let task = {
+                let tag = tag.clone();
+                tasks.spawn(async move { client.get_player(&tag).await })
+            };
+            tags.insert(task.id(), tag.clone());
+        }
+
+        let now = SystemTime::now();
+        let mut entries = Vec::new();
+        let mut errors = Vec::new();
+        while let Some(result) = tasks.join_next_with_id().await {
+            let (id, result) = match result {
+                Ok((id, result)) => (id, result),
+                Err(e) => (e.id(), Err(APIError::Unknown(e.to_string()))),
+            };
+            match result {
+                Ok(player) => entries.extend(self.update(&player, now)),
+                Err(e) => errors.extend(tags.remove(&id).map(|tag| (tag, e))),
+            }
+        }
+        (entries, errors)
+    }
+
+    /// Record a player as fetched at `now`. Players outside the legend league
+    /// are ignored and lose their baseline.
+    pub fn update(&mut self, player: &Player, now: SystemTime) -> Option<LegendEntry> {
+        if !player
+            .league
+            .as_ref()
+            .is_some_and(|league| league.is_legend())
+        {
+            self.baselines.remove(&player.tag);
+            return None;
+        }
+
+        let current = Baseline::of(player, now);
+        let previous = self.baselines.insert(player.tag.clone(), current)?;
+        if season_id(previous.time) != season_id(now) {
+            return None;
+        }
+
+        let start = legend_day(now).start;
+        let days = self.days.entry(player.tag.clone()).or_default();
+        if days.last().map(|day| day.start) != Some(start) {
+            days.push(LegendDay {
+                tag: player.tag.clone(),
+                name: player.name.clone(),
+                start,
+                start_trophies: previous.trophies,
+                end_trophies: previous.trophies,
+                attacks: 0,
+                attack_trophies: 0,
+                defenses: 0,
+                defense_trophies: 0,
+                unresolved_trophies: 0,
+                entries: Vec::new(),
+            });
+        }
+        let day = days.last_mut()?;
+        day.name = player.name.clone();
+
+        let entry = LegendEntry {
+            tag: player.tag.clone(),
+            time: now,
+            trophies: player.trophies,
+            change: classify(&previous, &current)?,
+        };
+        day.record(entry.clone());
+        Some(entry)
+    }
+
+    /// The player's legend day that `time` falls in
+    #[must_use]
+    pub fn day(&self, tag: &str, time: SystemTime) -> Option<&LegendDay> {
+        let start = legend_day(time).start;
+        self.days
+            .get(&normalize_tag(tag))?
+            .iter()
+            .find(|day| day.start == start)
+    }
+
+    /// Every recorded day of a player, oldest first
+    pub fn days(&self, tag: &str) -> impl Iterator<Item = &LegendDay> {
+        self.days.get(&normalize_tag(tag)).into_iter().flatten()
+    }
+
+    /// Summaries of every player for the legend day that `time` falls in,
+    /// ordered by trophies, most first
+    #[must_use]
+    pub fn summaries(&self, time: SystemTime) -> Vec<&LegendDay> {
+        let start = legend_day(time).start;
+        let mut days: Vec<&LegendDay> = self
+            .days
+            .values()
+            .filter_map(|days| days.iter().rev().find(|day| day.start == start))
+            .collect();
+        days.sort_by(|a, b| {
+            b.end_trophies
+                .cmp(&a.end_trophies)
+                .then_with(|| a.tag.cmp(&b.tag))
+        });
+        days
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{LegendChange, LegendTracker};
+    use crate::core::Client;
+    use crate::models::player::Player;
+    use crate::utils::time::parse_api_time;
+    use std::time::{Duration, SystemTime};
+
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn player() -> Player {
+        serde_json::from_str(include_str!("../../tests/fixtures/player.json")).unwrap()
+    }
+
+    fn time(value: &str) -> SystemTime {
+        parse_api_time(value).unwrap()
+    }
+
+    #[test]
+    fn test_legend_day() {
+        let mut tracker = LegendTracker::new();
+        let mut player = player();
+        let start = time("20240110T060000.000Z");
+        let minutes = |n: u64| start + Duration::from_secs(n * 60);
+
+        assert!(tracker.update(&player, start).is_none());
+
+        player.trophies += 32;
+        player.attack_wins += 1;
+        let entry = tracker.update(&player, minutes(5)).unwrap();
+        assert_eq!(
+            entry.change,
+            LegendChange::Attack {
+                trophies: 32,
+                count: 1
+            }
+        );
+
+        // Two defenses
+        player.trophies -= 50;
+        assert_eq!(
+            tracker.update(&player, minutes(10)).unwrap().change,
+            LegendChange::Defense {
+                trophies: 50,
+                count: 2
+            }
+        );
+
+        // An attack won while losing more on defense
+        player.trophies -= 4;
+        player.attack_wins += 1;
+        assert_eq!(
+            tracker.update(&player, minutes(15)).unwrap().change,
+            LegendChange::Mixed {
+                net: -4,
+                attacks: 1,
+                defenses: 1
+            }
+        );
+        assert!(tracker.update(&player, minutes(20)).is_none());
+
+        let day = tracker.day("#p0", minutes(20)).unwrap();
+        assert_eq!((day.start_trophies, day.end_trophies), (5300, 5278));
+        assert_eq!(day.net(), -22);
+        assert_eq!((day.attacks, day.attack_trophies), (2, 32));
+        assert_eq!((day.defenses, day.defense_trophies), (3, 50));
+        assert_eq!(day.unresolved_trophies, -4);
+        assert_eq!(day.remaining_attacks(), 6);
+        assert_eq!(day.entries.len(), 3);
+
+        // The next day starts at 05:00
+        player.trophies += 40;
+        player.attack_wins += 1;
+        tracker.update(&player, time("20240111T050100.000Z"));
+        let next = tracker.day("#P0", time("20240111T120000.000Z")).unwrap();
+        assert_eq!((next.start_trophies, next.attacks), (5278, 1));
+        assert_eq!(tracker.days("#P0").count(), 2);
+        assert_eq!(tracker.summaries(time("20240111T120000.000Z")).len(), 1);
+
+        let json = serde_json::to_string(&tracker).unwrap();
+        let restored: LegendTracker = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.days("#P0").count(), 2);
+    }
+
+    #[test]
+    fn test_gain_beyond_won_attacks() {
+        let mut tracker = LegendTracker::new();
+        let mut player = player();
+        let start = time("20240110T060000.000Z");
+        tracker.update(&player, start);
+
+        // One won attack can not gain 70, the other attack was not won
+        player.trophies += 70;
+        player.attack_wins += 1;
+        let entry = tracker
+            .update(&player, start + Duration::from_secs(300))
+            .unwrap();
+        assert_eq!(
+            entry.change,
+            LegendChange::Attack {
+                trophies: 70,
+                count: 2
+            }
+        );
+        let day = tracker.day("#P0", start).unwrap();
+        assert_eq!((day.attacks, day.defenses), (2, 0));
+    }
+
+    #[test]
+    fn test_season_reset_and_leaving() {
+        let mut tracker = LegendTracker::new();
+        let mut player = player();
+        tracker.update(&player, time("20240129T040000.000Z"));
+
+        // Trophies and win counters reset when the season ends
+        player.trophies = 5000;
+        player.attack_wins = 0;
+        player.defense_wins = 0;
+        assert!(tracker
+            .update(&player, time("20240129T051000.000Z"))
+            .is_none());
+        assert_eq!(tracker.days("#P0").count(), 0);
+
+        player.trophies = 4900;
+        player.league = None;
+        assert!(tracker
+            .update(&player, time("20240129T052000.000Z"))
+            .is_none());
+        assert!(tracker.baselines.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_poll() {
+        let mut body: serde_json::Value =
+            serde_json::from_str(include_str!("../../tests/fixtures/player.json")).unwrap();
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/players/%23P0"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&body))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        body["trophies"] = 5260.into();
+        Mock::given(method("GET"))
+            .and(path("/players/%23P0"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&body))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        body["trophies"] = 5300.into();
+        Mock::given(method("GET"))
+            .and(path("/players/%23P0"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&body))
+            .mount(&server)
+            .await;
+
+        let client = Client::new("token").with_base_url(server.uri());
+        let mut tracker = LegendTracker::new();
+        tracker.track("p0");
+        let (entries, errors) = tracker.poll(&client).await;
+        assert!(entries.is_empty() && errors.is_empty());
+
+        let (entries, errors) = tracker.poll(&client).await;
+        assert!(errors.is_empty());
+        assert_eq!(entries.len(), 1);
+        assert_eq!(
+            entries[0].change,
+            LegendChange::Defense {
+                trophies: 40,
+                count: 1
+            }
+        );
+
+        // A failed fetch does not lose the entries of the other players
+        tracker.track("#MISSING");
+        let (entries, errors) = tracker.poll(&client).await;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, "#MISSING");
+    }
+}
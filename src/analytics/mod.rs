@@ -0,0 +1,8 @@
+// Analytics computed over the API models
+pub mod donations;
+pub mod legends;
+pub mod participation;
+pub mod raid;
+pub mod raid_medals;
+pub mod war;
+pub mod war_log;
@@ -0,0 +1,206 @@
+//! Reports on who has not attacked in a war and how reliably members take part
+//! in wars over time
+
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+use super::war::member_stats;
+use crate::cwl::CwlSeason;
+use crate::models::clan::ClanWar;
+use crate::models::enums::clan::WarState;
+
+#[derive(Debug, Clone)]
+pub struct RemainingAttacks {
+    pub tag: String,
+    pub name: String,
+    pub map_position: u8,
+    pub attacks_used: u32,
+    pub attacks_remaining: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct MissedAttackReport {
+    pub clan_tag: String,
+    pub opponent_tag: String,
+    pub state: WarState,
+    /// Time until the war ends
+    pub time_left: Duration,
+    /// Members with attacks left, ordered by map position
+    pub members: Vec<RemainingAttacks>,
+}
+
+impl MissedAttackReport {
+    #[must_use]
+    pub fn attacks_remaining(&self) -> u32 {
+        self.members
+            .iter()
+            .map(|member| member.attacks_remaining)
+            .sum()
+    }
+}
+
+/// Members of the war's `clan` that still have attacks left as of `now`
+#[must_use]
+pub fn missed_attacks(war: &ClanWar, now: SystemTime) -> MissedAttackReport {
+    let mut members: Vec<RemainingAttacks> = member_stats(war)
+        .into_iter()
+        .filter(|member| member.attacks_missed() > 0)
+        .map(|member| RemainingAttacks {
+            attacks_remaining: member.attacks_missed(),
+            attacks_used: member.attacks_used,
+            tag: member.tag,
+            name: member.name,
+            map_position: member.map_position,
+        })
+        .collect();
+    members.sort_by_key(|member| member.map_position);
+
+    MissedAttackReport {
+        clan_tag: war.clan.tag.clone(),
+        opponent_tag: war.opponent.tag.clone(),
+        state: war.state.clone(),
+        time_left: war.remaining(now),
+        members,
+    }
+}
+
+/// Missed attack reports for every round of a clan war league season that is
+/// on battle day or has ended
+#[must_use]
+pub fn cwl_missed_attacks(season: &CwlSeason, now: SystemTime) -> Vec<(usize, MissedAttackReport)> {
+    season
+        .rounds
+        .iter()
+        .filter_map(|round| {
+            let war = round.war.as_ref()?;
+            match war.state {
+                WarState::InWar | WarState::War | WarState::Ended => {
+                    Some((round.number, missed_attacks(war, now)))
+                }
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Participation {
+    pub tag: String,
+    pub name: String,
+    /// Wars the member was in the lineup for
+    pub wars: u32,
+    pub attacks_used: u32,
+    pub attacks_available: u32,
+    /// Wars in which the member did not attack at all
+    pub wars_without_attacks: u32,
+}
+
+impl Participation {
+    /// Share of available attacks that were used, between 0 and 1
+    #[must_use]
+    pub fn participation_rate(&self) -> f32 {
+        if self.attacks_available == 0 {
+            0.0
+        } else {
+            self.attacks_used as f32 / self.attacks_available as f32
+        }
+    }
+}
+
+/// Participation per player tag for the `clan` side of every ended war.
+/// Wars that are still running are skipped since their attacks are not final.
+/// The result is ordered from the lowest participation rate to the highest.
+#[must_use]
+pub fn participation<'a>(wars: impl IntoIterator<Item = &'a ClanWar>) -> Vec<Participation> {
+    let mut players: HashMap<String, Participation> = HashMap::new();
+
+    for war in wars
+        .into_iter()
+        .filter(|war| matches!(war.state, WarState::Ended))
+    {
+        for member in member_stats(war) {
+            let entry = players.entry(member.tag.clone()).or_default();
+            entry.tag = member.tag.clone();
+            entry.name = member.name.clone();
+            entry.wars += 1;
+            entry.attacks_used += member.attacks_used;
+            entry.attacks_available += member.attacks_available;
+            if member.attacks_used == 0 {
+                entry.wars_without_attacks += 1;
+            }
+        }
+    }
+
+    let mut players: Vec<Participation> = players.into_values().collect();
+    players.sort_by(|a, b| {
+        a.participation_rate()
+            .total_cmp(&b.participation_rate())
+            .then_with(|| a.tag.cmp(&b.tag))
+    });
+    players
+}
+
+/// Participation for the clan over the ended rounds of a clan war league season
+#[must_use]
+pub fn cwl_participation(season: &CwlSeason) -> Vec<Participation> {
+    participation(season.rounds.iter().filter_map(|round| round.war.as_ref()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{cwl_missed_attacks, missed_attacks, participation};
+    use crate::cwl::CwlSeason;
+    use crate::models::clan::{ClanWar, ClanWarLeagueGroup};
+    use crate::models::enums::clan::WarState;
+    use crate::utils::time::parse_api_time;
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    fn war() -> ClanWar {
+        serde_json::from_str(include_str!("../../tests/fixtures/current_war.json")).unwrap()
+    }
+
+    #[test]
+    fn test_missed_attacks() {
+        let war = war();
+        let now = parse_api_time("20240112T060000.000Z").unwrap();
+        let report = missed_attacks(&war, now);
+
+        assert_eq!(report.time_left, Duration::from_secs(2 * 3600));
+        let tags: Vec<&str> = report.members.iter().map(|m| m.tag.as_str()).collect();
+        assert_eq!(tags, ["#P8", "#P9", "#PY"]);
+        assert_eq!(report.attacks_remaining(), 3);
+    }
+
+    #[test]
+    fn test_participation() {
+        let mut ended = war();
+        ended.state = WarState::Ended;
+        let running = war();
+
+        let players = participation([&ended, &ended, &running]);
+        assert_eq!(players.len(), 5);
+        assert_eq!(players[0].wars, 2);
+        assert_eq!(players[0].participation_rate(), 0.5);
+        assert_eq!(players[4].tag, "#P2");
+        assert_eq!(players[4].participation_rate(), 1.0);
+    }
+
+    #[test]
+    fn test_cwl_missed_attacks() {
+        let group: ClanWarLeagueGroup =
+            serde_json::from_str(include_str!("../../tests/fixtures/cwl_group.json")).unwrap();
+        let wars: HashMap<String, ClanWar> =
+            serde_json::from_str(include_str!("../../tests/fixtures/cwl_wars.json")).unwrap();
+        let season = CwlSeason::from_wars("#2P0", group, wars);
+
+        let now = parse_api_time("20240106T080000.000Z").unwrap();
+        let reports = cwl_missed_attacks(&season, now);
+        let rounds: Vec<usize> = reports.iter().map(|(round, _)| *round).collect();
+        assert_eq!(rounds, [1, 2, 3, 4]);
+
+        // Two members have not attacked yet on battle day
+        assert_eq!(reports[3].1.attacks_remaining(), 2);
+        assert_eq!(reports[3].1.time_left, Duration::from_secs(24 * 3600));
+    }
+}
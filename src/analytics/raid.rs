@@ -0,0 +1,306 @@
+//! Raid weekend analytics over `ClanCapitalRaidSeason`
+
+use crate::models::clan_capital::{
+    ClanCapitalRaidSeason, ClanCapitalRaidSeasonDistrict, ClanCapitalRaidSeasons,
+};
+use crate::models::timestamp::Timestamp;
+
+#[derive(Debug, Clone)]
+pub struct RaidMemberStats {
+    pub tag: String,
+    pub name: String,
+    pub attacks: u32,
+    /// Regular attacks plus bonus attacks
+    pub attack_limit: u32,
+    pub loot: u32,
+}
+
+impl RaidMemberStats {
+    #[must_use]
+    pub fn attacks_unused(&self) -> u32 {
+        self.attack_limit.saturating_sub(self.attacks)
+    }
+
+    #[must_use]
+    pub fn loot_per_attack(&self) -> f32 {
+        if self.attacks == 0 {
+            0.0
+        } else {
+            self.loot as f32 / self.attacks as f32
+        }
+    }
+}
+
+/// Stats for every member that raided, ordered by loot
+#[must_use]
+pub fn member_stats(season: &ClanCapitalRaidSeason) -> Vec<RaidMemberStats> {
+    let mut members: Vec<RaidMemberStats> = season
+        .members
+        .0
+        .iter()
+        .map(|member| RaidMemberStats {
+            tag: member.tag.clone(),
+            name: member.name.clone(),
+            attacks: u32::from(member.attacks),
+            attack_limit: u32::from(member.attack_limit) + u32::from(member.bonus_attack_limit),
+            loot: member.capital_resources_looted,
+        })
+        .collect();
+    members.sort_by(|a, b| b.loot.cmp(&a.loot).then_with(|| a.tag.cmp(&b.tag)));
+    members
+}
+
+#[derive(Debug, Clone)]
+pub struct DistrictStats {
+    /// Tag of the clan the district belongs to
+    pub clan_tag: String,
+    pub district_id: u32,
+    pub name: String,
+    pub district_hall_level: u8,
+    pub attacks: u32,
+    pub destruction_percent: u8,
+    pub destroyed: bool,
+    pub loot: u32,
+}
+
+impl DistrictStats {
+    fn new(clan_tag: &str, district: &ClanCapitalRaidSeasonDistrict) -> Self {
+        Self {
+            clan_tag: clan_tag.to_string(),
+            district_id: district.id,
+            name: district.name.clone(),
+            district_hall_level: district.district_hall_level,
+            attacks: u32::from(district.attack_count),
+            destruction_percent: district.destruction_percent,
+            destroyed: district.is_destroyed(),
+            loot: district.total_looted,
+        }
+    }
+
+    #[must_use]
+    pub fn loot_per_attack(&self) -> f32 {
+        if self.attacks == 0 {
+            0.0
+        } else {
+            self.loot as f32 / self.attacks as f32
+        }
+    }
+}
+
+/// Every district the clan attacked during the season
+#[must_use]
+pub fn district_stats(season: &ClanCapitalRaidSeason) -> Vec<DistrictStats> {
+    season
+        .attack_log
+        .0
+        .iter()
+        .flat_map(|raid| {
+            raid.districts
+                .0
+                .iter()
+                .map(|district| DistrictStats::new(&raid.defender.tag, district))
+        })
+        .collect()
+}
+
+/// Average attacks needed to destroy a district, grouped by district hall
+/// level. Districts that were not destroyed are left out.
+#[must_use]
+pub fn attacks_per_destroyed_district(districts: &[DistrictStats]) -> Vec<(u8, f32)> {
+    let mut levels: Vec<(u8, u32, u32)> = Vec::new();
+    for district in districts.iter().filter(|district| district.destroyed) {
+        match levels
+            .iter_mut()
+            .find(|(level, _, _)| *level == district.district_hall_level)
+        {
+            Some((_, count, attacks)) => {
+                *count += 1;
+                *attacks += district.attacks;
+            }
+            None => levels.push((district.district_hall_level, 1, district.attacks)),
+        }
+    }
+
+    levels.sort_by_key(|(level, _, _)| *level);
+    levels
+        .into_iter()
+        .map(|(level, count, attacks)| (level, attacks as f32 / count as f32))
+        .collect()
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DefenseSummary {
+    /// Number of clans that raided the capital
+    pub raids: u32,
+    pub attacks_received: u32,
+    pub districts_destroyed: u32,
+    /// Districts of the clan's capital as left by each raiding clan
+    pub districts: Vec<DistrictStats>,
+}
+
+impl DefenseSummary {
+    /// Average number of attacks each raiding clan needed
+    #[must_use]
+    pub fn attacks_per_raid(&self) -> f32 {
+        if self.raids == 0 {
+            0.0
+        } else {
+            self.attacks_received as f32 / self.raids as f32
+        }
+    }
+}
+
+/// How the clan's capital held up against the clans that raided it
+#[must_use]
+pub fn defense_summary(season: &ClanCapitalRaidSeason) -> DefenseSummary {
+    let mut summary = DefenseSummary::default();
+    for raid in &season.defense_log.0 {
+        summary.raids += 1;
+        summary.attacks_received += u32::from(raid.attack_count);
+        summary.districts_destroyed += u32::from(raid.districts_destroyed);
+        summary.districts.extend(
+            raid.districts
+                .0
+                .iter()
+                .map(|district| DistrictStats::new(&raid.attacker.tag, district)),
+        );
+    }
+    summary
+}
+
+#[derive(Debug, Clone)]
+pub struct SeasonSummary {
+    pub start_time: Timestamp,
+    pub total_loot: u32,
+    pub total_attacks: u32,
+    pub raids_completed: u32,
+    pub districts_destroyed: u32,
+    pub participants: u32,
+    pub offensive_reward: u32,
+    pub defensive_reward: u32,
+}
+
+impl SeasonSummary {
+    #[must_use]
+    pub fn new(season: &ClanCapitalRaidSeason) -> Self {
+        Self {
+            start_time: season.start_time,
+            total_loot: season.capital_total_loot,
+            total_attacks: u32::from(season.total_attacks),
+            raids_completed: u32::from(season.raids_completed),
+            districts_destroyed: u32::from(season.enemy_districts_destroyed),
+            participants: season.members.0.len() as u32,
+            offensive_reward: u32::from(season.offensive_reward),
+            defensive_reward: u32::from(season.defensive_reward),
+        }
+    }
+
+    #[must_use]
+    pub fn loot_per_attack(&self) -> f32 {
+        if self.total_attacks == 0 {
+            0.0
+        } else {
+            self.total_loot as f32 / self.total_attacks as f32
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SeasonComparison {
+    pub current: SeasonSummary,
+    pub previous: SeasonSummary,
+}
+
+impl SeasonComparison {
+    #[must_use]
+    pub fn loot_change(&self) -> i64 {
+        i64::from(self.current.total_loot) - i64::from(self.previous.total_loot)
+    }
+
+    #[must_use]
+    pub fn attacks_change(&self) -> i64 {
+        i64::from(self.current.total_attacks) - i64::from(self.previous.total_attacks)
+    }
+
+    #[must_use]
+    pub fn participants_change(&self) -> i64 {
+        i64::from(self.current.participants) - i64::from(self.previous.participants)
+    }
+
+    #[must_use]
+    pub fn loot_per_attack_change(&self) -> f32 {
+        self.current.loot_per_attack() - self.previous.loot_per_attack()
+    }
+}
+
+/// Compare each season with the one before it. Seasons are expected newest
+/// first, the order the API returns them in.
+#[must_use]
+pub fn compare_seasons(seasons: &ClanCapitalRaidSeasons) -> Vec<SeasonComparison> {
+    seasons
+        .0
+        .windows(2)
+        .map(|pair| SeasonComparison {
+            current: SeasonSummary::new(&pair[0]),
+            previous: SeasonSummary::new(&pair[1]),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        attacks_per_destroyed_district, compare_seasons, defense_summary, district_stats,
+        member_stats,
+    };
+    use crate::models::clan_capital::ClanCapitalRaidSeasons;
+
+    fn seasons() -> ClanCapitalRaidSeasons {
+        serde_json::from_str(include_str!("../../tests/fixtures/raid_seasons.json")).unwrap()
+    }
+
+    #[test]
+    fn test_member_stats() {
+        let seasons = seasons();
+        let members = member_stats(&seasons.0[0]);
+
+        assert_eq!(members[0].tag, "#P0");
+        assert_eq!(members[0].loot_per_attack(), 4000.0);
+        let charlie = members.iter().find(|m| m.tag == "#P8").unwrap();
+        assert_eq!(charlie.attacks_unused(), 2);
+    }
+
+    #[test]
+    fn test_district_stats() {
+        let seasons = seasons();
+        let districts = district_stats(&seasons.0[0]);
+
+        assert_eq!(districts.len(), 6);
+        assert_eq!(districts.iter().filter(|d| d.destroyed).count(), 5);
+        assert_eq!(districts[1].loot_per_attack(), 3000.0);
+
+        let per_level = attacks_per_destroyed_district(&districts);
+        assert_eq!(per_level, [(3, 3.0), (4, 2.5), (5, 4.0), (8, 5.0)]);
+    }
+
+    #[test]
+    fn test_defense_summary() {
+        let seasons = seasons();
+        let defense = defense_summary(&seasons.0[0]);
+
+        assert_eq!(defense.raids, 1);
+        assert_eq!(defense.attacks_received, 7);
+        assert_eq!(defense.districts_destroyed, 2);
+        assert_eq!(defense.attacks_per_raid(), 7.0);
+    }
+
+    #[test]
+    fn test_compare_seasons() {
+        let comparisons = compare_seasons(&seasons());
+
+        assert_eq!(comparisons.len(), 1);
+        assert_eq!(comparisons[0].loot_change(), 33_000);
+        assert_eq!(comparisons[0].attacks_change(), 8);
+        assert_eq!(comparisons[0].participants_change(), 1);
+    }
+}
@@ -0,0 +1,315 @@
+//! Per-member war performance computed from a `ClanWar`
+
+use std::collections::HashMap;
+
+use crate::models::clan::{ClanWar, ClanWarAttack, ClanWarMember, WarClan};
+
+/// An attack together with the members involved and how many stars it added
+/// to the war total
+#[derive(Debug, Clone, Copy)]
+pub struct AttackInfo<'a> {
+    pub attack: &'a ClanWarAttack,
+    pub attacker: &'a ClanWarMember,
+    pub defender: Option<&'a ClanWarMember>,
+    /// Stars that improved on the best result against the defender so far
+    pub new_stars: u8,
+}
+
+impl AttackInfo<'_> {
+    /// Map positions the attacker moved. Negative means the attacker hit a base
+    /// higher on the map than their own.
+    #[must_use]
+    pub fn map_position_diff(&self) -> Option<i16> {
+        self.defender.map(|defender| {
+            i16::from(defender.map_position) - i16::from(self.attacker.map_position)
+        })
+    }
+
+    /// Town hall levels the attacker moved. Positive means the defender has a
+    /// higher town hall than the attacker.
+    #[must_use]
+    pub fn townhall_diff(&self) -> Option<i16> {
+        self.defender.map(|defender| {
+            i16::from(defender.townhall_level) - i16::from(self.attacker.townhall_level)
+        })
+    }
+
+    #[must_use]
+    pub fn is_triple(&self) -> bool {
+        self.attack.stars == 3
+    }
+}
+
+/// Every attack made by `attackers` on `defenders`, in attack order
+#[must_use]
+pub fn attack_breakdown<'a>(attackers: &'a WarClan, defenders: &'a WarClan) -> Vec<AttackInfo<'a>> {
+    let defender_lookup: HashMap<&str, &ClanWarMember> = defenders
+        .members
+        .0
+        .iter()
+        .map(|member| (member.tag.as_str(), member))
+        .collect();
+
+    let mut attacks: Vec<(&ClanWarAttack, &ClanWarMember)> = attackers
+        .members
+        .0
+        .iter()
+        .flat_map(|member| member.attacks.0.iter().map(move |attack| (attack, member)))
+        .collect();
+    attacks.sort_by_key(|(attack, _)| attack.order);
+
+    let mut best_stars: HashMap<&str, u8> = HashMap::new();
+    attacks
+        .into_iter()
+        .map(|(attack, attacker)| {
+            let best = best_stars.entry(attack.defender_tag.as_str()).or_insert(0);
+            let new_stars = attack.stars.saturating_sub(*best);
+            *best = (*best).max(attack.stars);
+
+            AttackInfo {
+                attack,
+                attacker,
+                defender: defender_lookup.get(attack.defender_tag.as_str()).copied(),
+                new_stars,
+            }
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MemberWarStats {
+    pub tag: String,
+    pub name: String,
+    pub map_position: u8,
+    pub townhall_level: u8,
+    /// Number of wars these stats cover
+    pub wars: u32,
+    pub attacks_used: u32,
+    pub attacks_available: u32,
+    pub stars: u32,
+    /// Sum of destruction percentages over all attacks
+    pub destruction: u32,
+    pub triples: u32,
+    /// Stars that improved on the best result against a base at the time
+    pub new_stars: u32,
+    /// Attacks on bases higher on the map than the attacker's own
+    pub hit_ups: u32,
+    /// Attacks on bases lower on the map than the attacker's own
+    pub hit_downs: u32,
+    /// Attacks on higher town hall levels
+    pub townhall_hit_ups: u32,
+    /// Attacks on lower town hall levels
+    pub townhall_hit_downs: u32,
+    /// Number of times the member's base was attacked
+    pub defenses: u32,
+    /// Stars given up by the best attack on the member's base
+    pub stars_conceded: u32,
+    /// Destruction given up by the best attack on the member's base
+    pub destruction_conceded: u32,
+}
+
+impl MemberWarStats {
+    #[must_use]
+    pub fn attacks_missed(&self) -> u32 {
+        self.attacks_available.saturating_sub(self.attacks_used)
+    }
+
+    #[must_use]
+    pub fn triple_rate(&self) -> f32 {
+        ratio(self.triples, self.attacks_used)
+    }
+
+    #[must_use]
+    pub fn average_stars(&self) -> f32 {
+        ratio(self.stars, self.attacks_used)
+    }
+
+    #[must_use]
+    pub fn average_destruction(&self) -> f32 {
+        ratio(self.destruction, self.attacks_used)
+    }
+
+    /// Add the stats of another war for the same member
+    pub fn merge(&mut self, other: &MemberWarStats) {
+        self.wars += other.wars;
+        self.attacks_used += other.attacks_used;
+        self.attacks_available += other.attacks_available;
+        self.stars += other.stars;
+        self.destruction += other.destruction;
+        self.triples += other.triples;
+        self.new_stars += other.new_stars;
+        self.hit_ups += other.hit_ups;
+        self.hit_downs += other.hit_downs;
+        self.townhall_hit_ups += other.townhall_hit_ups;
+        self.townhall_hit_downs += other.townhall_hit_downs;
+        self.defenses += other.defenses;
+        self.stars_conceded += other.stars_conceded;
+        self.destruction_conceded += other.destruction_conceded;
+    }
+}
+
+fn ratio(value: u32, total: u32) -> f32 {
+    if total == 0 {
+        0.0
+    } else {
+        value as f32 / total as f32
+    }
+}
+
+/// Stats for every member of the war's `clan`
+#[must_use]
+pub fn member_stats(war: &ClanWar) -> Vec<MemberWarStats> {
+    side_stats(&war.clan, &war.opponent, war.attacks_per_member)
+}
+
+/// Stats for every member of the war's `opponent`
+#[must_use]
+pub fn opponent_member_stats(war: &ClanWar) -> Vec<MemberWarStats> {
+    side_stats(&war.opponent, &war.clan, war.attacks_per_member)
+}
+
+fn side_stats(
+    attackers: &WarClan,
+    defenders: &WarClan,
+    attacks_per_member: u8,
+) -> Vec<MemberWarStats> {
+    let mut stats: Vec<MemberWarStats> = attackers
+        .members
+        .0
+        .iter()
+        .map(|member| {
+            let best = member.best_opponent_attack.as_ref();
+            MemberWarStats {
+                tag: member.tag.clone(),
+                name: member.name.clone(),
+                map_position: member.map_position,
+                townhall_level: member.townhall_level,
+                wars: 1,
+                attacks_available: u32::from(attacks_per_member),
+                defenses: u32::from(member.opponent_attacks),
+                stars_conceded: best.map_or(0, |attack| u32::from(attack.stars)),
+                destruction_conceded: best
+                    .map_or(0, |attack| u32::from(attack.destruction_percentage)),
+                ..Default::default()
+            }
+        })
+        .collect();
+
+    let index: HashMap<String, usize> = stats
+        .iter()
+        .enumerate()
+        .map(|(i, member)| (member.tag.clone(), i))
+        .collect();
+
+    for info in attack_breakdown(attackers, defenders) {
+        let Some(&i) = index.get(&info.attacker.tag) else {
+            continue;
+        };
+        let member = &mut stats[i];
+
+        member.attacks_used += 1;
+        member.stars += u32::from(info.attack.stars);
+        member.destruction += u32::from(info.attack.destruction_percentage);
+        member.new_stars += u32::from(info.new_stars);
+        if info.is_triple() {
+            member.triples += 1;
+        }
+
+        match info.map_position_diff() {
+            Some(diff) if diff < 0 => member.hit_ups += 1,
+            Some(diff) if diff > 0 => member.hit_downs += 1,
+            _ => {}
+        }
+        match info.townhall_diff() {
+            Some(diff) if diff > 0 => member.townhall_hit_ups += 1,
+            Some(diff) if diff < 0 => member.townhall_hit_downs += 1,
+            _ => {}
+        }
+    }
+
+    stats
+}
+
+/// Combine stats from several wars into one entry per member tag
+#[must_use]
+pub fn combine<'a>(stats: impl IntoIterator<Item = &'a MemberWarStats>) -> Vec<MemberWarStats> {
+    let mut combined: Vec<MemberWarStats> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+
+    for member in stats {
+        match index.get(&member.tag) {
+            Some(&i) => combined[i].merge(member),
+            None => {
+                index.insert(member.tag.clone(), combined.len());
+                combined.push(member.clone());
+            }
+        }
+    }
+
+    combined
+}
+
+#[cfg(test)]
+mod test {
+    use super::{attack_breakdown, combine, member_stats, opponent_member_stats};
+    use crate::models::clan::ClanWar;
+
+    fn war() -> ClanWar {
+        serde_json::from_str(include_str!("../../tests/fixtures/current_war.json")).unwrap()
+    }
+
+    #[test]
+    fn test_new_stars_by_order() {
+        let war = war();
+        let new_stars: Vec<(u8, u8)> = attack_breakdown(&war.clan, &war.opponent)
+            .iter()
+            .map(|info| (info.attack.order, info.new_stars))
+            .collect();
+
+        assert_eq!(
+            new_stars,
+            [(1, 2), (3, 1), (4, 2), (5, 3), (7, 0), (9, 3), (11, 1)]
+        );
+        let total: u32 = new_stars.iter().map(|(_, stars)| u32::from(*stars)).sum();
+        assert_eq!(total, u32::from(war.clan.stars));
+    }
+
+    #[test]
+    fn test_member_stats() {
+        let war = war();
+        let stats = member_stats(&war);
+
+        let alpha = &stats[0];
+        assert_eq!(alpha.attacks_used, 2);
+        assert_eq!(alpha.stars, 5);
+        assert_eq!(alpha.destruction, 185);
+        assert_eq!(alpha.new_stars, 4);
+        assert_eq!(alpha.triples, 1);
+        assert_eq!(alpha.hit_downs, 1);
+        assert_eq!(alpha.stars_conceded, 3);
+
+        let bravo = &stats[1];
+        assert_eq!(bravo.hit_ups, 1);
+        assert_eq!(bravo.townhall_hit_ups, 2);
+        assert_eq!(bravo.triple_rate(), 0.5);
+
+        let echo = &stats[4];
+        assert_eq!(echo.attacks_missed(), 1);
+        assert_eq!(echo.defenses, 0);
+        assert_eq!(echo.stars_conceded, 0);
+
+        let opponents = opponent_member_stats(&war);
+        assert_eq!(opponents.iter().map(|m| m.attacks_used).sum::<u32>(), 4);
+    }
+
+    #[test]
+    fn test_combine() {
+        let war = war();
+        let stats = member_stats(&war);
+        let combined = combine(stats.iter().chain(stats.iter()));
+
+        assert_eq!(combined.len(), 5);
+        assert_eq!(combined[0].wars, 2);
+        assert_eq!(combined[0].stars, 10);
+    }
+}
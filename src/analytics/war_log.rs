@@ -0,0 +1,286 @@
+//! Aggregations over a clan's `ClanWarLog`. Clan war league entries carry no
+//! result and no opponent, so only regular wars are taken into account.
+
+use std::collections::BTreeMap;
+
+use crate::models::clan::{ClanWarLog, ClanWarLogEntry};
+use crate::models::enums::clan::{BattleModifier, WarResult};
+use crate::models::timestamp::Timestamp;
+
+/// Regular wars of the log, oldest first
+fn regular_wars(log: &ClanWarLog) -> Vec<(&ClanWarLogEntry, WarResult)> {
+    let mut wars: Vec<(&ClanWarLogEntry, WarResult)> = log
+        .0
+        .iter()
+        .filter_map(|entry| entry.result.map(|result| (entry, result)))
+        .collect();
+    // The API lists the newest war first
+    wars.sort_by_key(|(entry, _)| entry.end_time);
+    wars
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Streak {
+    pub result: WarResult,
+    pub length: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Streaks {
+    /// Streak the clan is currently on
+    pub current: Option<Streak>,
+    pub longest_win: u32,
+    pub longest_loss: u32,
+    pub longest_tie: u32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct WarRecord {
+    pub wars: u32,
+    pub wins: u32,
+    pub losses: u32,
+    pub ties: u32,
+    pub stars: u32,
+    pub destruction: f32,
+    pub opponent_stars: u32,
+    pub opponent_destruction: f32,
+}
+
+impl WarRecord {
+    fn add(&mut self, entry: &ClanWarLogEntry, result: WarResult) {
+        self.wars += 1;
+        match result {
+            WarResult::Win => self.wins += 1,
+            WarResult::Lose => self.losses += 1,
+            WarResult::Tie => self.ties += 1,
+        }
+        self.stars += u32::from(entry.clan.stars);
+        self.destruction += entry.clan.destruction_percentage;
+        self.opponent_stars += u32::from(entry.opponent.stars);
+        self.opponent_destruction += entry.opponent.destruction_percentage;
+    }
+
+    fn average(&self, total: f32) -> f32 {
+        if self.wars == 0 {
+            0.0
+        } else {
+            total / self.wars as f32
+        }
+    }
+
+    #[must_use]
+    pub fn win_rate(&self) -> f32 {
+        self.average(self.wins as f32)
+    }
+
+    #[must_use]
+    pub fn average_stars(&self) -> f32 {
+        self.average(self.stars as f32)
+    }
+
+    #[must_use]
+    pub fn average_destruction(&self) -> f32 {
+        self.average(self.destruction)
+    }
+
+    #[must_use]
+    pub fn average_opponent_stars(&self) -> f32 {
+        self.average(self.opponent_stars as f32)
+    }
+
+    #[must_use]
+    pub fn average_opponent_destruction(&self) -> f32 {
+        self.average(self.opponent_destruction)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct WarLogSummary {
+    pub record: WarRecord,
+    pub streaks: Streaks,
+}
+
+/// Overall record and streaks of the war log
+#[must_use]
+pub fn summary(log: &ClanWarLog) -> WarLogSummary {
+    let mut summary = WarLogSummary::default();
+    let mut current: Option<Streak> = None;
+
+    for (entry, result) in regular_wars(log) {
+        summary.record.add(entry, result);
+
+        current = match current {
+            Some(streak) if streak.result == result => Some(Streak {
+                result,
+                length: streak.length + 1,
+            }),
+            _ => Some(Streak { result, length: 1 }),
+        };
+
+        if let Some(streak) = current {
+            let longest = match result {
+                WarResult::Win => &mut summary.streaks.longest_win,
+                WarResult::Lose => &mut summary.streaks.longest_loss,
+                WarResult::Tie => &mut summary.streaks.longest_tie,
+            };
+            *longest = (*longest).max(streak.length);
+        }
+    }
+
+    summary.streaks.current = current;
+    summary
+}
+
+/// Record per team size, ordered by team size
+#[must_use]
+pub fn by_team_size(log: &ClanWarLog) -> Vec<(u8, WarRecord)> {
+    let mut groups: BTreeMap<u8, WarRecord> = BTreeMap::new();
+    for (entry, result) in regular_wars(log) {
+        groups
+            .entry(entry.team_size)
+            .or_default()
+            .add(entry, result);
+    }
+    groups.into_iter().collect()
+}
+
+/// Record per battle modifier
+#[must_use]
+pub fn by_battle_modifier(log: &ClanWarLog) -> Vec<(BattleModifier, WarRecord)> {
+    let mut groups: Vec<(BattleModifier, WarRecord)> = Vec::new();
+    for (entry, result) in regular_wars(log) {
+        let index = match groups
+            .iter()
+            .position(|(modifier, _)| *modifier == entry.battle_modifier)
+        {
+            Some(index) => index,
+            None => {
+                groups.push((entry.battle_modifier, WarRecord::default()));
+                groups.len() - 1
+            }
+        };
+        groups[index].1.add(entry, result);
+    }
+    groups
+}
+
+/// Number of wars against opponents of each clan level, ordered by level
+#[must_use]
+pub fn opponent_levels(log: &ClanWarLog) -> Vec<(u8, u32)> {
+    let mut levels: BTreeMap<u8, u32> = BTreeMap::new();
+    for (entry, _) in regular_wars(log) {
+        *levels.entry(entry.opponent.clan_level).or_default() += 1;
+    }
+    levels.into_iter().collect()
+}
+
+#[derive(Debug, Clone)]
+pub struct TrendPoint {
+    pub end_time: Timestamp,
+    pub result: WarResult,
+    pub stars: u16,
+    pub destruction: f32,
+    /// Win rate over the last `window` wars up to and including this one
+    pub rolling_win_rate: f32,
+    /// Average stars over the last `window` wars up to and including this one
+    pub rolling_average_stars: f32,
+    /// Average destruction over the last `window` wars up to and including
+    /// this one
+    pub rolling_average_destruction: f32,
+}
+
+/// Per war data points, oldest first, with rolling averages over `window` wars
+#[must_use]
+pub fn trend(log: &ClanWarLog, window: usize) -> Vec<TrendPoint> {
+    let wars = regular_wars(log);
+    let window = window.max(1);
+
+    (0..wars.len())
+        .map(|i| {
+            let mut record = WarRecord::default();
+            for (entry, result) in &wars[(i + 1).saturating_sub(window)..=i] {
+                record.add(entry, *result);
+            }
+
+            let (entry, result) = wars[i];
+            TrendPoint {
+                end_time: entry.end_time,
+                result,
+                stars: entry.clan.stars,
+                destruction: entry.clan.destruction_percentage,
+                rolling_win_rate: record.win_rate(),
+                rolling_average_stars: record.average_stars(),
+                rolling_average_destruction: record.average_destruction(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{by_battle_modifier, by_team_size, opponent_levels, summary, trend, Streak};
+    use crate::models::clan::ClanWarLog;
+    use crate::models::enums::clan::{BattleModifier, WarResult};
+
+    fn log() -> ClanWarLog {
+        serde_json::from_str(include_str!("../../tests/fixtures/war_log.json")).unwrap()
+    }
+
+    #[test]
+    fn test_summary() {
+        let summary = summary(&log());
+
+        assert_eq!(summary.record.wars, 7);
+        assert_eq!(
+            (
+                summary.record.wins,
+                summary.record.losses,
+                summary.record.ties
+            ),
+            (4, 2, 1)
+        );
+        assert_eq!(summary.record.average_stars(), 32.285_713);
+        assert_eq!(
+            summary.streaks.current,
+            Some(Streak {
+                result: WarResult::Win,
+                length: 3
+            })
+        );
+        assert_eq!(summary.streaks.longest_win, 3);
+        assert_eq!(summary.streaks.longest_loss, 2);
+    }
+
+    #[test]
+    fn test_groups() {
+        let log = log();
+
+        let sizes = by_team_size(&log);
+        assert_eq!(sizes[0].0, 10);
+        assert_eq!(sizes[0].1.wars, 3);
+        assert_eq!(sizes[1].1.wars, 4);
+
+        let modifiers = by_battle_modifier(&log);
+        let hard = modifiers
+            .iter()
+            .find(|(modifier, _)| *modifier == BattleModifier::HardMode)
+            .unwrap();
+        assert_eq!(hard.1.win_rate(), 1.0);
+
+        assert_eq!(
+            opponent_levels(&log),
+            [(11, 1), (12, 3), (13, 1), (14, 1), (15, 1)]
+        );
+    }
+
+    #[test]
+    fn test_trend() {
+        let points = trend(&log(), 3);
+
+        assert_eq!(points.len(), 7);
+        assert_eq!(points[0].end_time.to_string(), "20240112T080000.000Z");
+        assert_eq!(points[0].rolling_win_rate, 1.0);
+        assert_eq!(points[6].rolling_win_rate, 1.0);
+        assert_eq!(points[3].rolling_win_rate, 0.0);
+    }
+}
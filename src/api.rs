@@ -1,17 +1,18 @@
 // This file should contain stuff regarding the actual api
+#[allow(clippy::module_inception)]
 pub mod api {
-    const BASE_URL: &'static str = "https://api.clashofclans.com/v1";
+    pub const BASE_URL: &str = "https://api.clashofclans.com/v1";
 
     // ----- CLANS -----
     // Retrieve information about clan's current war league group
     // Return: ClanWarLeagueGroup
-    fn league_group_endpoint(clantag: &str) -> String {
+    pub fn league_group_endpoint(clantag: &str) -> String {
         format!("/clans/{}/currentwar/leaguegroup", clantag)
     }
 
     // Retrieve information about individual clan war league war
     // Return: ClanWarLeagueGroup
-    fn warleague_war_endpoint(war_tag: &str) -> String {
+    pub fn warleague_war_endpoint(war_tag: &str) -> String {
         format!("/clanwarleagues/wars/{}", war_tag)
     }
 
@@ -22,7 +23,7 @@ pub mod api {
     //  after(String): return only items that occur after this marker,
     //  before(String): return only items that occur before this marker,
     // Return: ClanWarLog
-    fn warlog_endpoint(clantag: &str) -> String {
+    pub fn warlog_endpoint(clantag: &str) -> String {
         format!("/clans/{}/warlog", clantag)
     }
 
@@ -41,19 +42,19 @@ pub mod api {
     //  before(String): return only items that occur before this marker,
     //  labelIds(String): comma separated list of label ids used for filtering results,
     // Return: ClanList
-    fn search_clans_endpoint() -> &'static str {
+    pub fn search_clans_endpoint() -> &'static str {
         "/clans"
     }
 
     // Retrieve information about clan's current clan war
     // Return: ClanWar
-    fn curr_war_endpoint(clantag: &str) -> String {
+    pub fn curr_war_endpoint(clantag: &str) -> String {
         format!("/clans/{}/currentwar", clantag)
     }
 
     // Clan Information
     // Return: Clan
-    fn clan_endpoint(clantag: &str) -> String {
+    pub fn clan_endpoint(clantag: &str) -> String {
         format!("/clans/{}", clantag)
     }
 
@@ -64,7 +65,7 @@ pub mod api {
     //  after(String): return only items that occur after this marker,
     //  before(String): return only items that occur before this marker,
     // Return: ClanMemberList
-    fn clan_members_endpoint(clantag: &str) -> String {
+    pub fn clan_members_endpoint(clantag: &str) -> String {
         format!("/clans/{}/members", clantag)
     }
 
@@ -75,7 +76,7 @@ pub mod api {
     //  after(String): return only items that occur after this marker,
     //  before(String): return only items that occur before this marker,
     // Return: ClanCapitalRaidSeasons
-    fn clan_capital_raid_seasons_endpoint(clantag: &str) -> String {
+    pub fn clan_capital_raid_seasons_endpoint(clantag: &str) -> String {
         format!("/clans/{}/capitalraidseasons", clantag)
     }
 
@@ -83,13 +84,13 @@ pub mod api {
     // ----- PLAYERS -----
     // Get player information
     // Return: Player
-    fn player_endpoint(player_tag: &str) -> String {
+    pub fn player_endpoint(player_tag: &str) -> String {
         format!("/players/{}", player_tag)
     }
 
     // POST: Verify player API token that can be found from the game settings
     // Return: VerifyTokenResponse
-    fn verify_token_endpoint(player_tag: &str) -> String {
+    pub fn verify_token_endpoint(player_tag: &str) -> String {
         format!("/players/{}/verifytoken", player_tag)
     }
 
@@ -101,7 +102,7 @@ pub mod api {
     //  after(String): return only items that occur after marker,
     //  before(String): return only items that occur before marker,
     // Returns: LeagueList
-    fn capital_leagues_endpoint() -> &'static str {
+    pub fn capital_leagues_endpoint() -> &'static str {
         "/capitalleagues"
     }
 
@@ -111,7 +112,7 @@ pub mod api {
     //  after(String): return only items that occur after marker,
     //  before(String): return only items that occur before marker,
     // Returns: LeagueList
-    fn leagues_endpoint() -> &'static str {
+    pub fn leagues_endpoint() -> &'static str {
         "/leagues"
     }
 
@@ -123,19 +124,19 @@ pub mod api {
     //  after(String): return only items that occur after marker,
     //  before(String): return only items that occur before marker,
     // Return: PlayerRankingList
-    fn league_season_rankings_endpoint(league_id: &str, season_id: &str) -> String {
+    pub fn league_season_rankings_endpoint(league_id: &str, season_id: &str) -> String {
         format!("/leagues/{}/seasons/{}", league_id, season_id)
     }
 
     // Get capital league information
     // Return: League
-    fn capital_leagues_info_endpoint(league_id: &str) -> String {
+    pub fn capital_leagues_info_endpoint(league_id: &str) -> String {
         format!("/capitalleagues/{}", league_id)
     }
 
     // Get builder base league information
     // Return: League
-    fn builder_base_leagues_info_endpoint(league_id: &str) -> String {
+    pub fn builder_base_leagues_info_endpoint(league_id: &str) -> String {
         format!("/builderbaseleagues/{}", league_id)
     }
 
@@ -145,13 +146,13 @@ pub mod api {
     //  after(String): return only items that occur after marker,
     //  before(String): return only items that occur before marker,
     // Return: LeagueList
-    fn builder_base_leagues_endpoint() -> &'static str {
+    pub fn builder_base_leagues_endpoint() -> &'static str {
         "/builderbaseleagues"
     }
 
     // Get league information
     // Return: League
-    fn league_info_endpoint(league_id: &str) -> String {
+    pub fn league_info_endpoint(league_id: &str) -> String {
         format!("/leagues/{}", league_id)
     }
 
@@ -162,13 +163,13 @@ pub mod api {
     //  after(String): return only items that occur after marker,
     //  before(String): return only items that occur before marker,
     // Return: LeagueSeasonList
-    fn league_seasons_endpoint(league_id: &str) -> String {
+    pub fn league_seasons_endpoint(league_id: &str) -> String {
         format!("/leagues/{}/seasons", league_id)
     }
 
     // Get war league information
     // Return: League
-    fn war_league_info_endpoint(league_id: &str) -> String {
+    pub fn war_league_info_endpoint(league_id: &str) -> String {
         format!("/warleagues/{}", league_id)
     }
 
@@ -178,7 +179,7 @@ pub mod api {
     //  after(String): return only items that occur after marker,
     //  before(String): return only items that occur before marker,
     // Return: LeagueList
-    fn war_leagues_endpoint() -> &'static str {
+    pub fn war_leagues_endpoint() -> &'static str {
         "/warleagues"
     }
 
@@ -190,7 +191,7 @@ pub mod api {
     //  after(String): return only items that occur after marker,
     //  before(String): return only items that occur before marker,
     // Return: ClanRankingList
-    fn local_clan_rankings_endpoint(location_id: &str) -> String {
+    pub fn local_clan_rankings_endpoint(location_id: &str) -> String {
         format!("/locations/{}/rankings/clans", location_id)
     }
 
@@ -200,7 +201,7 @@ pub mod api {
     //  after(String): return only items that occur after marker,
     //  before(String): return only items that occur before marker,
     // Return: PlayerRankingList
-    fn local_player_rankings_endpoint(location_id: &str) -> String {
+    pub fn local_player_rankings_endpoint(location_id: &str) -> String {
         format!("/locations/{}/rankings/players", location_id)
     }
 
@@ -210,7 +211,7 @@ pub mod api {
     //  after(String): return only items that occur after marker,
     //  before(String): return only items that occur before marker,
     // Return: PlayerBuilderBaseRankingList
-    fn local_player_builder_rankings_endpoint(location_id: &str) -> String {
+    pub fn local_player_builder_rankings_endpoint(location_id: &str) -> String {
         format!("/locations/{}/rankings/players-builder-base", location_id)
     }
 
@@ -220,7 +221,7 @@ pub mod api {
     //  after(String): return only items that occur after marker,
     //  before(String): return only items that occur before marker,
     // Return: ClanBuilderBaseRankingList
-    fn local_clan_builder_rankings_endpoint(location_id: &str) -> String {
+    pub fn local_clan_builder_rankings_endpoint(location_id: &str) -> String {
         format!("/locations/{}/rankings/clans-builder-base", location_id)
     }
 
@@ -230,7 +231,7 @@ pub mod api {
     //  after(String): return only items that occur after marker,
     //  before(String): return only items that occur before marker,
     // Return: LocationList
-    fn locations_endpoint() -> &'static str {
+    pub fn locations_endpoint() -> &'static str {
         "/locations"
     }
 
@@ -240,13 +241,13 @@ pub mod api {
     //  after(String): return only items that occur after marker,
     //  before(String): return only items that occur before marker,
     // Return: ClanCapitalRankingList
-    fn local_capital_rankings_endpoint(location_id: &str) -> String {
+    pub fn local_capital_rankings_endpoint(location_id: &str) -> String {
         format!("/locations/{}/rankings/capitals", location_id)
     }
 
     // Get location information
     // Return: Location
-    fn locations_info_endpoint(location_id: &str) -> String {
+    pub fn locations_info_endpoint(location_id: &str) -> String {
         format!("/locations/{}", location_id)
     }
 
@@ -254,19 +255,19 @@ pub mod api {
     // ----- GOLDPASS -----
     // Get information about the current gold pass season
     // Returns: GoldPassSeason
-    fn goldpass_endpoint() -> &'static str {
+    pub fn goldpass_endpoint() -> &'static str {
         "/goldpass/seasons/current"
     }
 
 
     // ----- LABELS -----
     // List player labels
-    fn player_labels_endpoint() -> &'static str {
+    pub fn player_labels_endpoint() -> &'static str {
         "/labels/players"
     }
 
     // List clan labels
-    fn clan_labels_endpoint() -> &'static str {
+    pub fn clan_labels_endpoint() -> &'static str {
         "/labels/clans"
     }
 }
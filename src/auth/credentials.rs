@@ -1,7 +1,7 @@
-/// A `Credential` stores a users email and password linked to their supercell
-/// API account. `Credentials` is a simple wrapper around a list of `Credential`
-/// objects. It comes with helper functions `add_credential` and `build` to
-/// add Credential objects into the list of credentials and build the object.
+//! A `Credential` stores a users email and password linked to their supercell
+//! API account. `Credentials` is a simple wrapper around a list of `Credential`
+//! objects. It comes with helper functions `add_credential` and `build` to
+//! add Credential objects into the list of credentials and build the object.
 
 use serde::{Deserialize, Serialize};
 
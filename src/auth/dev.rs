@@ -1,5 +1,6 @@
+//! This file contains functionality regarding login/out
+
 use lazy_static::lazy_static;
-/// This file contains functionality regarding login/out
 use reqwest;
 use serde::{Deserialize, Serialize};
 use serde_json::from_str;
@@ -103,7 +104,7 @@ impl APIAccount {
         )
         .map_err(|e| APIError::SerializationFailed(e.to_string()))?; // Ensure error is converted to APIError
 
-        let base_url = DEV_API_URL.lock().unwrap();
+        let base_url = DEV_API_URL.lock().unwrap().clone();
 
         // Send login request
         let res = client
@@ -132,7 +133,7 @@ impl APIAccount {
 
     /// Lists all keys tied to a supercell API account
     pub async fn list_keys() -> anyhow::Result<Keys, APIError> {
-        let base_url = DEV_API_URL.lock().unwrap();
+        let base_url = DEV_API_URL.lock().unwrap().clone();
 
         let client = reqwest::Client::builder().cookie_store(true).build().unwrap();
 
@@ -174,7 +175,7 @@ impl APIAccount {
         let body = serde_json::to_string(&key_body)
             .map_err(|e| APIError::SerializationFailed(e.to_string()))?;
 
-        let base_url = DEV_API_URL.lock().unwrap();
+        let base_url = DEV_API_URL.lock().unwrap().clone();
 
         // Send the request to create the key
         let res = CLIENT
@@ -207,7 +208,7 @@ impl APIAccount {
         key_id: &str,
         account: &mut APIAccount,
     ) -> anyhow::Result<LogoutResponse, APIError> {
-        let base_url = DEV_API_URL.lock().unwrap();
+        let base_url = DEV_API_URL.lock().unwrap().clone();
 
         // Build the URL for the revocation request
         let url = format!("{}{}", base_url, Self::KEY_REVOKE_ENDPOINT);
@@ -36,5 +36,10 @@ impl Keys {
     pub fn len(&self) -> usize {
         self.keys.len()
     }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
 }
 
@@ -0,0 +1,21 @@
+//! Runs the API proxy. The keys and consumers are read from the environment,
+//! see `ProxyConfig::from_env`, and the proxy listens on `PROXY_BIND`
+//! (default `0.0.0.0:8080`).
+
+use rsclashapi::proxy::{serve, Proxy, ProxyConfig};
+use std::env;
+
+#[actix_web::main]
+async fn main() -> anyhow::Result<()> {
+    let config = ProxyConfig::from_env()?;
+    let bind = env::var("PROXY_BIND").unwrap_or_else(|_| "0.0.0.0:8080".to_string());
+
+    println!(
+        "Proxying {} with {} keys for {} consumers on {bind}",
+        config.upstream,
+        config.keys.len(),
+        config.consumers.len()
+    );
+    serve(Proxy::new(config), bind.as_str())?.await?;
+    Ok(())
+}
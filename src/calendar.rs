@@ -0,0 +1,467 @@
+//! The game's recurring schedule: seasons, raid weekends, clan war leagues,
+//! clan games and gold pass seasons. All times are UTC.
+//!
+//! Clan war league rounds are computed from the usual schedule. The exact
+//! times of a league depend on matchmaking, so use the wars of the league group
+//! when they are available.
+
+use std::time::{Duration, SystemTime};
+
+use crate::models::gold_pass::GoldPassSeason;
+use crate::utils::time::{
+    civil_from_days, days_from_civil, from_unix, season_end, season_of, time_until, to_unix,
+    weekday, RESET_HOUR, SECONDS_PER_DAY,
+};
+
+pub use crate::utils::time::season_id;
+
+const HOUR: u64 = 3600;
+const DAY: u64 = 24 * HOUR;
+
+/// Raid weekends run from Friday to Monday, starting and ending at 07:00
+pub const RAID_WEEKEND_HOUR: i64 = 7;
+/// Clan war league sign-up, clan games and the gold pass start at 08:00
+pub const MONTHLY_EVENT_HOUR: i64 = 8;
+/// Length of the clan war league sign-up
+pub const CWL_SIGNUP_DAYS: u64 = 2;
+pub const CWL_ROUNDS: usize = 7;
+/// Clan games run from the 22nd to the 28th of the month
+pub const CLAN_GAMES_START_DAY: u32 = 22;
+pub const CLAN_GAMES_END_DAY: u32 = 28;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Window {
+    pub start: SystemTime,
+    pub end: SystemTime,
+}
+
+impl Window {
+    #[must_use]
+    pub fn contains(&self, time: SystemTime) -> bool {
+        self.start <= time && time < self.end
+    }
+
+    #[must_use]
+    pub fn duration(&self) -> Duration {
+        time_until(self.end, self.start)
+    }
+
+    /// Zero once the window has started
+    #[must_use]
+    pub fn time_until_start(&self, now: SystemTime) -> Duration {
+        time_until(self.start, now)
+    }
+
+    /// Zero once the window has ended
+    #[must_use]
+    pub fn time_until_end(&self, now: SystemTime) -> Duration {
+        time_until(self.end, now)
+    }
+}
+
+impl From<&GoldPassSeason> for Window {
+    fn from(season: &GoldPassSeason) -> Self {
+        Self {
+            start: season.start_time.into(),
+            end: season.end_time.into(),
+        }
+    }
+}
+
+/// `hour` o'clock on a day of a month
+fn at(year: i64, month: u32, day: u32, hour: i64) -> SystemTime {
+    let seconds = days_from_civil(year, month, day) * SECONDS_PER_DAY + hour * HOUR as i64;
+    from_unix(seconds, 0).unwrap_or(SystemTime::UNIX_EPOCH)
+}
+
+fn month_of(time: SystemTime) -> (i64, u32) {
+    let (seconds, _) = to_unix(time);
+    let (year, month, _) = civil_from_days(seconds.div_euclid(SECONDS_PER_DAY));
+    (year, month)
+}
+
+fn next_month(year: i64, month: u32) -> (i64, u32) {
+    if month == 12 {
+        (year + 1, 1)
+    } else {
+        (year, month + 1)
+    }
+}
+
+fn previous_month(year: i64, month: u32) -> (i64, u32) {
+    if month == 1 {
+        (year - 1, 12)
+    } else {
+        (year, month - 1)
+    }
+}
+
+/// A window that happens once a month, the one in progress at `now` or the
+/// next one
+fn monthly(now: SystemTime, window: impl Fn(i64, u32) -> Window) -> Window {
+    let (year, month) = month_of(now);
+    let current = window(year, month);
+    if now < current.end {
+        current
+    } else {
+        let (year, month) = next_month(year, month);
+        window(year, month)
+    }
+}
+
+/// The season `time` falls in. Seasons, and the legend league, end on the
+/// last Monday of the month at 05:00.
+#[must_use]
+pub fn season(time: SystemTime) -> Window {
+    let (year, month) = season_of(time);
+    let (previous_year, previous) = previous_month(year, month);
+    Window {
+        start: season_end(previous_year, previous),
+        end: season_end(year, month),
+    }
+}
+
+/// The legend league day `time` falls in, days start at 05:00
+#[must_use]
+pub fn legend_day(time: SystemTime) -> Window {
+    let (seconds, _) = to_unix(time);
+    let day = (seconds - RESET_HOUR * HOUR as i64).div_euclid(SECONDS_PER_DAY);
+    let start = from_unix(day * SECONDS_PER_DAY + RESET_HOUR * HOUR as i64, 0)
+        .unwrap_or(SystemTime::UNIX_EPOCH);
+    Window {
+        start,
+        end: start + Duration::from_secs(DAY),
+    }
+}
+
+/// The raid weekend in progress at `now`, or the next one
+#[must_use]
+pub fn raid_weekend(now: SystemTime) -> Window {
+    let (seconds, _) = to_unix(now);
+    let days = seconds.div_euclid(SECONDS_PER_DAY);
+    // Friday is day 4 of the week
+    let friday = days - i64::from((weekday(days) + 3) % 7);
+    let mut start = from_unix(
+        friday * SECONDS_PER_DAY + RAID_WEEKEND_HOUR * HOUR as i64,
+        0,
+    )
+    .unwrap_or(SystemTime::UNIX_EPOCH);
+    if start > now {
+        start -= Duration::from_secs(7 * DAY);
+    }
+    if start + Duration::from_secs(3 * DAY) <= now {
+        start += Duration::from_secs(7 * DAY);
+    }
+
+    Window {
+        start,
+        end: start + Duration::from_secs(3 * DAY),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CwlRound {
+    pub preparation: Window,
+    pub battle: Window,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CwlSchedule {
+    pub signup: Window,
+    pub rounds: [CwlRound; CWL_ROUNDS],
+}
+
+impl CwlSchedule {
+    /// The schedule of the league held in `month`
+    #[must_use]
+    pub fn for_month(year: i64, month: u32) -> Self {
+        let start = at(year, month, 1, MONTHLY_EVENT_HOUR);
+        let signup_end = start + Duration::from_secs(CWL_SIGNUP_DAYS * DAY);
+
+        let rounds = std::array::from_fn(|i| {
+            let preparation_start = signup_end + Duration::from_secs(i as u64 * DAY);
+            let battle_start = preparation_start + Duration::from_secs(DAY);
+            CwlRound {
+                preparation: Window {
+                    start: preparation_start,
+                    end: battle_start,
+                },
+                battle: Window {
+                    start: battle_start,
+                    end: battle_start + Duration::from_secs(DAY),
+                },
+            }
+        });
+
+        Self {
+            signup: Window {
+                start,
+                end: signup_end,
+            },
+            rounds,
+        }
+    }
+
+    /// From the start of the sign-up to the end of the last battle day
+    #[must_use]
+    pub fn window(&self) -> Window {
+        Window {
+            start: self.signup.start,
+            end: self.rounds[CWL_ROUNDS - 1].battle.end,
+        }
+    }
+
+    /// Round number, starting at 1, whose battle day is in progress at `now`
+    #[must_use]
+    pub fn battle_day(&self, now: SystemTime) -> Option<usize> {
+        self.rounds
+            .iter()
+            .position(|round| round.battle.contains(now))
+            .map(|i| i + 1)
+    }
+}
+
+/// The clan war league in progress at `now`, or the next one
+#[must_use]
+pub fn cwl(now: SystemTime) -> CwlSchedule {
+    let (year, month) = month_of(now);
+    let current = CwlSchedule::for_month(year, month);
+    if now < current.window().end {
+        current
+    } else {
+        let (year, month) = next_month(year, month);
+        CwlSchedule::for_month(year, month)
+    }
+}
+
+/// The clan games in progress at `now`, or the next ones
+#[must_use]
+pub fn clan_games(now: SystemTime) -> Window {
+    monthly(now, |year, month| Window {
+        start: at(year, month, CLAN_GAMES_START_DAY, MONTHLY_EVENT_HOUR),
+        end: at(year, month, CLAN_GAMES_END_DAY, MONTHLY_EVENT_HOUR),
+    })
+}
+
+/// The gold pass season `time` falls in. Prefer the window of the
+/// `GoldPassSeason` returned by the API when it is available.
+#[must_use]
+pub fn gold_pass(time: SystemTime) -> Window {
+    let (year, month) = month_of(time);
+    let window = |year, month| {
+        let (next_year, next) = next_month(year, month);
+        Window {
+            start: at(year, month, 1, MONTHLY_EVENT_HOUR),
+            end: at(next_year, next, 1, MONTHLY_EVENT_HOUR),
+        }
+    };
+
+    let current = window(year, month);
+    if time < current.start {
+        let (year, month) = previous_month(year, month);
+        window(year, month)
+    } else {
+        current
+    }
+}
+
+/// Something on the schedule to count down to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Milestone {
+    SeasonEnd,
+    RaidWeekendStart,
+    RaidWeekendEnd,
+    CwlSignupStart,
+    /// Start of the next battle day of any round
+    CwlBattleDayStart,
+    ClanGamesStart,
+    ClanGamesEnd,
+    GoldPassEnd,
+}
+
+/// When `milestone` next happens after `now`
+#[must_use]
+pub fn next(milestone: Milestone, now: SystemTime) -> SystemTime {
+    let following_league = |league: &CwlSchedule| {
+        let (year, month) = month_of(league.signup.start);
+        let (year, month) = next_month(year, month);
+        CwlSchedule::for_month(year, month)
+    };
+
+    match milestone {
+        Milestone::SeasonEnd => season(now).end,
+        Milestone::RaidWeekendStart => {
+            let weekend = raid_weekend(now);
+            if weekend.start > now {
+                weekend.start
+            } else {
+                weekend.start + Duration::from_secs(7 * DAY)
+            }
+        }
+        Milestone::RaidWeekendEnd => raid_weekend(now).end,
+        Milestone::CwlSignupStart => {
+            let league = cwl(now);
+            if league.signup.start > now {
+                league.signup.start
+            } else {
+                following_league(&league).signup.start
+            }
+        }
+        Milestone::CwlBattleDayStart => {
+            let league = cwl(now);
+            league
+                .rounds
+                .iter()
+                .map(|round| round.battle.start)
+                .find(|start| *start > now)
+                .unwrap_or_else(|| following_league(&league).rounds[0].battle.start)
+        }
+        Milestone::ClanGamesStart => {
+            let games = clan_games(now);
+            if games.start > now {
+                games.start
+            } else {
+                clan_games(games.end).start
+            }
+        }
+        Milestone::ClanGamesEnd => clan_games(now).end,
+        Milestone::GoldPassEnd => gold_pass(now).end,
+    }
+}
+
+/// Time from `now` until `milestone` next happens
+#[must_use]
+pub fn time_until_next(milestone: Milestone, now: SystemTime) -> Duration {
+    time_until(next(milestone, now), now)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        clan_games, cwl, gold_pass, legend_day, next, raid_weekend, season, season_id,
+        time_until_next, Milestone, Window,
+    };
+    use crate::models::gold_pass::GoldPassSeason;
+    use crate::utils::time::{format_api_time, parse_api_time};
+    use std::time::Duration;
+
+    fn time(value: &str) -> std::time::SystemTime {
+        parse_api_time(value).unwrap()
+    }
+
+    fn window(window: Window) -> (String, String) {
+        (format_api_time(window.start), format_api_time(window.end))
+    }
+
+    #[test]
+    fn test_season() {
+        let now = time("20240210T120000.000Z");
+        assert_eq!(season_id(now), "2024-02");
+        assert_eq!(
+            window(season(now)),
+            (
+                "20240129T050000.000Z".to_string(),
+                "20240226T050000.000Z".to_string()
+            )
+        );
+        assert_eq!(season_id(time("20240226T050000.000Z")), "2024-03");
+    }
+
+    #[test]
+    fn test_legend_day() {
+        assert_eq!(
+            window(legend_day(time("20240110T045959.000Z"))),
+            (
+                "20240109T050000.000Z".to_string(),
+                "20240110T050000.000Z".to_string()
+            )
+        );
+        assert!(legend_day(time("20240110T050000.000Z")).contains(time("20240111T045959.000Z")));
+    }
+
+    #[test]
+    fn test_raid_weekend() {
+        // Wednesday, the next weekend starts on Friday the 12th
+        let weekend = raid_weekend(time("20240110T120000.000Z"));
+        assert_eq!(
+            window(weekend),
+            (
+                "20240112T070000.000Z".to_string(),
+                "20240115T070000.000Z".to_string()
+            )
+        );
+        // Sunday, the weekend is in progress
+        assert_eq!(raid_weekend(time("20240114T230000.000Z")), weekend);
+        // Friday morning before the start
+        assert_eq!(raid_weekend(time("20240112T060000.000Z")), weekend);
+        // Monday after the end
+        assert_eq!(
+            format_api_time(raid_weekend(time("20240115T080000.000Z")).start),
+            "20240119T070000.000Z"
+        );
+    }
+
+    #[test]
+    fn test_cwl() {
+        let league = cwl(time("20240105T120000.000Z"));
+        assert_eq!(format_api_time(league.signup.start), "20240101T080000.000Z");
+        assert_eq!(league.battle_day(time("20240105T120000.000Z")), Some(2));
+        assert_eq!(format_api_time(league.window().end), "20240111T080000.000Z");
+
+        // Once the last round is over the next league is returned
+        let league = cwl(time("20240120T000000.000Z"));
+        assert_eq!(format_api_time(league.signup.start), "20240201T080000.000Z");
+    }
+
+    #[test]
+    fn test_clan_games_and_gold_pass() {
+        assert_eq!(
+            window(clan_games(time("20241229T000000.000Z"))),
+            (
+                "20250122T080000.000Z".to_string(),
+                "20250128T080000.000Z".to_string()
+            )
+        );
+        assert_eq!(
+            window(gold_pass(time("20240301T070000.000Z"))),
+            (
+                "20240201T080000.000Z".to_string(),
+                "20240301T080000.000Z".to_string()
+            )
+        );
+
+        let season = GoldPassSeason {
+            start_time: "20240201T080000.000Z".parse().unwrap(),
+            end_time: "20240301T080000.000Z".parse().unwrap(),
+        };
+        assert_eq!(
+            Window::from(&season),
+            gold_pass(time("20240215T000000.000Z"))
+        );
+    }
+
+    #[test]
+    fn test_next() {
+        let now = time("20240113T070000.000Z");
+        assert_eq!(
+            format_api_time(next(Milestone::RaidWeekendStart, now)),
+            "20240119T070000.000Z"
+        );
+        assert_eq!(
+            time_until_next(Milestone::RaidWeekendEnd, now),
+            Duration::from_secs(2 * 24 * 3600)
+        );
+        assert_eq!(
+            format_api_time(next(Milestone::CwlSignupStart, now)),
+            "20240201T080000.000Z"
+        );
+        assert_eq!(
+            format_api_time(next(Milestone::ClanGamesStart, now)),
+            "20240122T080000.000Z"
+        );
+        assert_eq!(
+            format_api_time(next(Milestone::SeasonEnd, now)),
+            "20240129T050000.000Z"
+        );
+    }
+}
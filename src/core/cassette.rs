@@ -0,0 +1,351 @@
+//! Record/replay cassettes for the client. While recording, every request
+//! the client sends and the response it gets are written to a JSON file.
+//! While replaying, responses are served from the file without touching the
+//! network, and a request that was not recorded fails. Tests of code built on
+//! the client are then deterministic and run offline.
+//!
+//! Cassettes never contain the API token: the `Authorization` header is not
+//! recorded and the token, and every `token` field of a body (e.g. player
+//! tokens sent to `verifytoken`), are replaced with `SCRUBBED`.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::error::CassetteError;
+
+/// What tokens are replaced with
+pub const SCRUBBED: &str = "[scrubbed]";
+
+/// Response headers kept in a cassette
+const RECORDED_HEADERS: [&str; 2] = ["content-type", "cache-control"];
+
+/// Replace `token` and every `token` field in `value`
+fn scrub(value: &mut Value, token: &str) {
+    match value {
+        Value::String(text) if !token.is_empty() && text.contains(token) => {
+            *text = text.replace(token, SCRUBBED);
+        }
+        Value::Array(items) => items.iter_mut().for_each(|item| scrub(item, token)),
+        Value::Object(fields) => {
+            for (key, field) in fields.iter_mut() {
+                if key == "token" && field.is_string() {
+                    *field = Value::String(SCRUBBED.to_string());
+                } else {
+                    scrub(field, token);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// A body as JSON if it is JSON, as a string otherwise
+fn body_value(body: &str) -> Value {
+    serde_json::from_str(body).unwrap_or_else(|_| Value::String(body.to_string()))
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    pub method: String,
+    /// Path and query relative to the base url, e.g. `/clans/%232PP`
+    pub endpoint: String,
+    pub body: Option<Value>,
+}
+
+impl RecordedRequest {
+    /// A request as it is recorded, without `token`
+    #[must_use]
+    pub fn new(method: &str, endpoint: &str, body: Option<Value>, token: &str) -> Self {
+        let body = body.map(|mut body| {
+            scrub(&mut body, token);
+            body
+        });
+        Self {
+            method: method.to_uppercase(),
+            endpoint: endpoint.to_string(),
+            body,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    pub status: u16,
+    /// Lowercase header names
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    pub body: Value,
+}
+
+impl RecordedResponse {
+    /// Read a response, returning it as received and as it is recorded,
+    /// without `token`
+    pub(crate) async fn read(
+        response: reqwest::Response,
+        token: &str,
+    ) -> Result<(Self, Self), reqwest::Error> {
+        let status = response.status().as_u16();
+        let headers = RECORDED_HEADERS
+            .iter()
+            .filter_map(|&name| {
+                let value = response.headers().get(name)?.to_str().ok()?;
+                Some((name.to_string(), value.to_string()))
+            })
+            .collect();
+        let body = body_value(&response.text().await?);
+
+        let received = Self {
+            status,
+            headers,
+            body,
+        };
+        let mut recorded = received.clone();
+        scrub(&mut recorded.body, token);
+        Ok((received, recorded))
+    }
+
+    /// The body as it was sent
+    #[must_use]
+    pub fn body_text(&self) -> String {
+        match &self.body {
+            Value::String(text) => text.clone(),
+            body => body.to_string(),
+        }
+    }
+
+    /// Turn the recording back into a response
+    pub(crate) fn into_response(self) -> reqwest::Response {
+        let mut builder = http::Response::builder().status(self.status);
+        for (name, value) in &self.headers {
+            builder = builder.header(name.as_str(), value.as_str());
+        }
+        let response = builder
+            .body(self.body_text())
+            .unwrap_or_else(|_| http::Response::new(String::new()));
+        reqwest::Response::from(response)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Interaction {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CassetteMode {
+    Record,
+    Replay,
+}
+
+#[derive(Debug, Default)]
+struct Tape {
+    interactions: Vec<Interaction>,
+    /// Whether each interaction was replayed
+    played: Vec<bool>,
+}
+
+#[derive(Debug)]
+pub struct Cassette {
+    path: PathBuf,
+    mode: CassetteMode,
+    tape: Mutex<Tape>,
+}
+
+impl Cassette {
+    /// Record to `path`, the file is overwritten with every interaction
+    #[must_use]
+    pub fn record(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            mode: CassetteMode::Record,
+            tape: Mutex::new(Tape::default()),
+        }
+    }
+
+    /// Replay the interactions recorded in `path`
+    pub fn replay(path: impl Into<PathBuf>) -> Result<Self, CassetteError> {
+        let path = path.into();
+        let interactions: Vec<Interaction> = serde_json::from_str(&fs::read_to_string(&path)?)?;
+        Ok(Self {
+            path,
+            mode: CassetteMode::Replay,
+            tape: Mutex::new(Tape {
+                played: vec![false; interactions.len()],
+                interactions,
+            }),
+        })
+    }
+
+    /// Replay `path` if it exists, record it otherwise
+    pub fn once(path: impl Into<PathBuf>) -> Result<Self, CassetteError> {
+        let path = path.into();
+        if path.exists() {
+            Self::replay(path)
+        } else {
+            Ok(Self::record(path))
+        }
+    }
+
+    #[must_use]
+    pub fn mode(&self) -> CassetteMode {
+        self.mode
+    }
+
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    #[must_use]
+    pub fn interactions(&self) -> Vec<Interaction> {
+        self.tape
+            .lock()
+            .expect("cassette poisoned")
+            .interactions
+            .clone()
+    }
+
+    /// Add an interaction and write the cassette
+    pub fn push(&self, interaction: Interaction) -> Result<(), CassetteError> {
+        let mut tape = self.tape.lock().expect("cassette poisoned");
+        tape.interactions.push(interaction);
+        tape.played.push(false);
+        Self::write(&self.path, &tape.interactions)
+    }
+
+    fn write(path: &Path, interactions: &[Interaction]) -> Result<(), CassetteError> {
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(interactions)?)?;
+        Ok(())
+    }
+
+    /// The response recorded for `request`. Identical requests are answered
+    /// in the order they were recorded, the last answer is repeated once they
+    /// are used up.
+    #[must_use]
+    pub fn play(&self, request: &RecordedRequest) -> Option<RecordedResponse> {
+        let mut tape = self.tape.lock().expect("cassette poisoned");
+        let Tape {
+            interactions,
+            played,
+        } = &mut *tape;
+        let matching: Vec<usize> = interactions
+            .iter()
+            .enumerate()
+            .filter(|(_, interaction)| interaction.request == *request)
+            .map(|(index, _)| index)
+            .collect();
+        let index = matching
+            .iter()
+            .copied()
+            .find(|&index| !played[index])
+            .or_else(|| matching.last().copied())?;
+        played[index] = true;
+        Some(interactions[index].response.clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Cassette, CassetteMode, SCRUBBED};
+    use crate::api::api;
+    use crate::core::client::Client;
+    use crate::error::APIError;
+    use crate::models::clan::Clan;
+    use serde_json::json;
+    use std::env;
+    use std::fs;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_record_and_replay() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/clans/%232PP"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("Cache-Control", "max-age=120")
+                    .set_body_string(include_str!("../../tests/fixtures/clan.json")),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/players/%23P0/verifytoken"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "tag": "#P0",
+                "token": "player-token",
+                "status": "ok"
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/players/%23P2"))
+            .respond_with(ResponseTemplate::new(404).set_body_json(json!({
+                "reason": "notFound"
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let file = env::temp_dir().join(format!("rsclashapi-cassette-{}.json", std::process::id()));
+        let client = Client::new("secret-api-key")
+            .with_base_url(server.uri())
+            .with_cassette(Cassette::record(&file));
+        let endpoint = api::clan_endpoint("%232PP");
+        let clan = client.get_cached::<Clan>(&endpoint).await.unwrap();
+        let verified = client
+            .verify_player_token("#P0", "player-token")
+            .await
+            .unwrap();
+        assert_eq!(verified.token, "player-token");
+        assert!(matches!(
+            client.get_player("#P2").await,
+            Err(APIError::NotFound)
+        ));
+
+        let recorded = fs::read_to_string(&file).unwrap();
+        assert!(!recorded.contains("secret-api-key"));
+        assert!(!recorded.contains("player-token"));
+        assert!(recorded.contains(SCRUBBED));
+
+        // Nothing listens on the discard port, every response is replayed
+        let cassette = Cassette::once(&file).unwrap();
+        assert_eq!(cassette.mode(), CassetteMode::Replay);
+        let replay = Client::new("another-key")
+            .with_base_url("http://127.0.0.1:9")
+            .with_cassette(cassette);
+        for _ in 0..2 {
+            let replayed = replay.get_cached::<Clan>(&endpoint).await.unwrap();
+            assert_eq!(replayed.data.tag, clan.data.tag);
+            assert_eq!(replayed.max_age, clan.max_age);
+        }
+        let verified = replay
+            .verify_player_token("#P0", "a-new-token")
+            .await
+            .unwrap();
+        assert!(verified.is_ok());
+        assert!(matches!(
+            replay.get_player("#P2").await,
+            Err(APIError::NotFound)
+        ));
+        assert!(matches!(
+            replay.get_player("#P8").await,
+            Err(APIError::UnrecordedRequest(_))
+        ));
+
+        fs::remove_file(&file).unwrap();
+    }
+}
@@ -0,0 +1,353 @@
+//! A thin client around the Clash of Clans game API. Every request is
+//! authenticated with a single API token (see `auth::dev` for creating keys).
+
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, CACHE_CONTROL};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::cassette::{Cassette, CassetteMode, Interaction, RecordedRequest, RecordedResponse};
+use crate::api::api;
+use crate::error::APIError;
+use crate::models::clan::{
+    Clan, ClanMemberList, ClanWar, ClanWarLeagueGroup, ClanWarLog, CurrentWar,
+};
+use crate::models::clan_capital::ClanCapitalRaidSeasons;
+use crate::models::error::SupercellAPIError;
+use crate::models::player::{Player, VerifyTokenRequest, VerifyTokenResponse};
+use crate::utils::{format_tag, normalize_tag};
+
+/// Normalize a tag and url encode it for use in an endpoint
+pub(crate) fn encode_tag(tag: &str) -> String {
+    format_tag(&normalize_tag(tag))
+}
+
+/// How long the API caches a response for, read from the `Cache-Control`
+/// header. Polling the same endpoint again before it expires returns the same
+/// data.
+fn max_age(res: &reqwest::Response) -> Option<Duration> {
+    parse_max_age(res.headers().get(CACHE_CONTROL)?.to_str().ok()?)
+}
+
+/// The `max-age` directive of a `Cache-Control` header value
+pub(crate) fn parse_max_age(header: &str) -> Option<Duration> {
+    header
+        .split([',', ' '])
+        .find_map(|directive| directive.trim().strip_prefix("max-age="))
+        .and_then(|seconds| seconds.parse().ok())
+        .map(Duration::from_secs)
+}
+
+/// Paginated endpoints wrap their results in an `items` field
+#[derive(Deserialize)]
+struct ItemList<T> {
+    items: T,
+}
+
+/// A response together with how long the API caches it for
+#[derive(Debug, Clone)]
+pub struct Cached<T> {
+    pub data: T,
+    pub max_age: Option<Duration>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    token: String,
+    base_url: String,
+    cassette: Option<Arc<Cassette>>,
+}
+
+impl Client {
+    /// Create a client that authenticates with the given API token
+    #[must_use]
+    pub fn new(token: impl Into<String>) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            token: token.into(),
+            base_url: api::BASE_URL.to_string(),
+            cassette: None,
+        }
+    }
+
+    /// Point the client at a different base url (e.g. a proxy or a mock server)
+    #[must_use]
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Record requests to, or replay them from, a cassette. Clones of the
+    /// client share it.
+    #[must_use]
+    pub fn with_cassette(mut self, cassette: Cassette) -> Self {
+        self.cassette = Some(Arc::new(cassette));
+        self
+    }
+
+    #[must_use]
+    pub fn cassette(&self) -> Option<&Cassette> {
+        self.cassette.as_deref()
+    }
+
+    #[must_use]
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    #[must_use]
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    fn headers(&self) -> Result<HeaderMap, APIError> {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", self.token))?,
+        );
+        Ok(headers)
+    }
+
+    /// Send a GET request to `endpoint` and deserialize the response body
+    pub async fn get<T: DeserializeOwned>(&self, endpoint: &str) -> Result<T, APIError> {
+        let res = self.send_get(endpoint).await?;
+        Self::parse(res).await
+    }
+
+    /// Like `get`, but also returns how long the API caches the response for
+    pub async fn get_cached<T: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+    ) -> Result<Cached<T>, APIError> {
+        let res = self.send_get(endpoint).await?;
+        let max_age = max_age(&res);
+        Ok(Cached {
+            data: Self::parse(res).await?,
+            max_age,
+        })
+    }
+
+    /// GET a paginated endpoint and return its items
+    pub async fn get_items<T: DeserializeOwned>(&self, endpoint: &str) -> Result<T, APIError> {
+        Ok(self.get::<ItemList<T>>(endpoint).await?.items)
+    }
+
+    /// Like `get_items`, but also returns how long the API caches the
+    /// response for
+    pub async fn get_items_cached<T: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+    ) -> Result<Cached<T>, APIError> {
+        let cached = self.get_cached::<ItemList<T>>(endpoint).await?;
+        Ok(Cached {
+            data: cached.data.items,
+            max_age: cached.max_age,
+        })
+    }
+
+    async fn send_get(&self, endpoint: &str) -> Result<reqwest::Response, APIError> {
+        self.send(reqwest::Method::GET, endpoint, None).await
+    }
+
+    /// Send a request, through the cassette if there is one
+    async fn send(
+        &self,
+        method: reqwest::Method,
+        endpoint: &str,
+        body: Option<serde_json::Value>,
+    ) -> Result<reqwest::Response, APIError> {
+        let Some(cassette) = &self.cassette else {
+            return self.send_request(method, endpoint, body).await;
+        };
+
+        let request = RecordedRequest::new(method.as_str(), endpoint, body.clone(), &self.token);
+        match cassette.mode() {
+            CassetteMode::Replay => cassette
+                .play(&request)
+                .map(RecordedResponse::into_response)
+                .ok_or_else(|| {
+                    APIError::UnrecordedRequest(format!("{} {}", request.method, request.endpoint))
+                }),
+            CassetteMode::Record => {
+                let res = self.send_request(method, endpoint, body).await?;
+                let (received, recorded) = RecordedResponse::read(res, &self.token).await?;
+                cassette.push(Interaction {
+                    request,
+                    response: recorded,
+                })?;
+                Ok(received.into_response())
+            }
+        }
+    }
+
+    async fn send_request(
+        &self,
+        method: reqwest::Method,
+        endpoint: &str,
+        body: Option<serde_json::Value>,
+    ) -> Result<reqwest::Response, APIError> {
+        let mut request = self
+            .http
+            .request(method, format!("{}{}", self.base_url, endpoint))
+            .headers(self.headers()?);
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+        Ok(request.send().await?)
+    }
+
+    /// Send a POST request with a JSON body to `endpoint` and deserialize the
+    /// response body
+    pub async fn post<B: Serialize, T: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        body: &B,
+    ) -> Result<T, APIError> {
+        let body =
+            serde_json::to_value(body).map_err(|e| APIError::SerializationFailed(e.to_string()))?;
+        let res = self
+            .send(reqwest::Method::POST, endpoint, Some(body))
+            .await?;
+
+        Self::parse(res).await
+    }
+
+    async fn parse<T: DeserializeOwned>(res: reqwest::Response) -> Result<T, APIError> {
+        if !res.status().is_success() {
+            return Err(APIError::from_response(res).await);
+        }
+
+        let body = res.text().await?;
+        serde_json::from_str(&body).map_err(|e| APIError::SerializationFailed(e.to_string()))
+    }
+
+    // ----- CLANS -----
+    /// Clan information
+    pub async fn get_clan(&self, clantag: &str) -> Result<Clan, APIError> {
+        self.get(&api::clan_endpoint(&encode_tag(clantag))).await
+    }
+
+    /// Retrieve information about the clan's current clan war. A private war
+    /// log is reported as `CurrentWar::PrivateWarLog` rather than an error.
+    pub async fn get_current_war(&self, clantag: &str) -> Result<CurrentWar, APIError> {
+        Ok(self.get_current_war_cached(clantag).await?.data)
+    }
+
+    /// Like `get_current_war`, but also returns how long the API caches the
+    /// response for
+    pub async fn get_current_war_cached(
+        &self,
+        clantag: &str,
+    ) -> Result<Cached<CurrentWar>, APIError> {
+        let res = self
+            .send_get(&api::curr_war_endpoint(&encode_tag(clantag)))
+            .await?;
+        let max_age = max_age(&res);
+
+        if res.status() == reqwest::StatusCode::FORBIDDEN {
+            let body = res.text().await?;
+            // An invalid ip is also a 403, only "accessDenied" means a private war log
+            return match serde_json::from_str::<SupercellAPIError>(&body) {
+                Ok(error) if error.reason.as_deref() == Some("accessDenied") => Ok(Cached {
+                    data: CurrentWar::PrivateWarLog,
+                    max_age,
+                }),
+                _ => Err(APIError::AccessDenied),
+            };
+        }
+
+        Ok(Cached {
+            data: Self::parse(res).await?,
+            max_age,
+        })
+    }
+
+    /// List clan members
+    pub async fn get_clan_members(&self, clantag: &str) -> Result<ClanMemberList, APIError> {
+        self.get_items(&api::clan_members_endpoint(&encode_tag(clantag)))
+            .await
+    }
+
+    /// Retrieve the clan's clan war log, newest war first
+    pub async fn get_war_log(&self, clantag: &str) -> Result<ClanWarLog, APIError> {
+        self.get_items(&api::warlog_endpoint(&encode_tag(clantag)))
+            .await
+    }
+
+    /// Retrieve the clan's capital raid seasons, newest season first
+    pub async fn get_capital_raid_seasons(
+        &self,
+        clantag: &str,
+        limit: Option<u32>,
+    ) -> Result<ClanCapitalRaidSeasons, APIError> {
+        let mut endpoint = api::clan_capital_raid_seasons_endpoint(&encode_tag(clantag));
+        if let Some(limit) = limit {
+            endpoint = format!("{}?limit={}", endpoint, limit);
+        }
+        self.get_items(&endpoint).await
+    }
+
+    /// Retrieve information about the clan's current clan war league group
+    pub async fn get_league_group(&self, clantag: &str) -> Result<ClanWarLeagueGroup, APIError> {
+        self.get(&api::league_group_endpoint(&encode_tag(clantag)))
+            .await
+    }
+
+    /// Retrieve information about an individual clan war league war
+    pub async fn get_league_war(&self, war_tag: &str) -> Result<ClanWar, APIError> {
+        self.get(&api::warleague_war_endpoint(&encode_tag(war_tag)))
+            .await
+    }
+
+    // ----- PLAYERS -----
+    /// Get player information
+    pub async fn get_player(&self, player_tag: &str) -> Result<Player, APIError> {
+        self.get(&api::player_endpoint(&encode_tag(player_tag)))
+            .await
+    }
+
+    /// Verify a player API token that can be found in the game settings.
+    /// Tokens are single use and only valid for a couple of minutes.
+    pub async fn verify_player_token(
+        &self,
+        player_tag: &str,
+        token: &str,
+    ) -> Result<VerifyTokenResponse, APIError> {
+        let body = VerifyTokenRequest {
+            token: token.to_string(),
+        };
+
+        self.post(&api::verify_token_endpoint(&encode_tag(player_tag)), &body)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Client;
+    use crate::models::clan::CurrentWar;
+
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_private_war_log() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/clans/%232PP/currentwar"))
+            .respond_with(ResponseTemplate::new(403).set_body_json(json!({
+                "reason": "accessDenied",
+                "message": "Access denied, clan war log is private."
+            })))
+            .mount(&server)
+            .await;
+
+        let client = Client::new("token").with_base_url(server.uri());
+        let war = client.get_current_war("#2PP").await.unwrap();
+        assert!(matches!(war, CurrentWar::PrivateWarLog));
+    }
+}
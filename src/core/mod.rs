@@ -0,0 +1,6 @@
+// Core functionality for talking to the Clash of Clans game API
+pub mod cassette;
+pub mod client;
+
+pub use cassette::{Cassette, CassetteMode};
+pub use client::{Cached, Client};
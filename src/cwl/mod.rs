@@ -0,0 +1,9 @@
+// Helpers for working with clan war league seasons
+pub mod season;
+pub mod standings;
+
+pub use season::{CwlRound, CwlSeason};
+pub use standings::{standings, CwlStanding, Projection};
+
+/// War tag the API uses for wars in rounds that have not started yet
+pub const PLACEHOLDER_WAR_TAG: &str = "#0";
@@ -0,0 +1,345 @@
+//! Resolves a clan's clan war league season. The league group only lists war
+//! tags per round, so every war in the group is fetched and the clan's own
+//! war is picked out of each round.
+
+use std::collections::HashMap;
+use tokio::task::JoinSet;
+
+use super::PLACEHOLDER_WAR_TAG;
+use crate::core::Client;
+use crate::error::APIError;
+use crate::models::clan::{ClanWar, ClanWarLeagueGroup, WarClan};
+use crate::models::enums::clan::WarState;
+use crate::utils::normalize_tag;
+
+#[derive(Debug, Clone)]
+pub struct CwlRound {
+    /// Round number, starting at 1
+    pub number: usize,
+    /// Tag of the clan's war this round, [`PLACEHOLDER_WAR_TAG`] until the
+    /// round is scheduled. `None` if the clan is in none of the round's wars,
+    /// or if its war failed to fetch together with others of the round and
+    /// can not be told apart from them.
+    pub war_tag: Option<String>,
+    /// The clan's war this round from the clan's perspective
+    pub war: Option<ClanWar>,
+}
+
+impl CwlRound {
+    /// Whether the round has not been scheduled yet
+    #[must_use]
+    pub fn is_placeholder(&self) -> bool {
+        self.war_tag.as_deref() == Some(PLACEHOLDER_WAR_TAG)
+    }
+
+    /// Whether the round is scheduled but the clan's war failed to fetch
+    #[must_use]
+    pub fn is_failed(&self) -> bool {
+        self.war.is_none() && self.war_tag.is_some() && !self.is_placeholder()
+    }
+
+    #[must_use]
+    pub fn opponent(&self) -> Option<&WarClan> {
+        self.war.as_ref().map(|war| &war.opponent)
+    }
+
+    #[must_use]
+    pub fn state(&self) -> Option<&WarState> {
+        self.war.as_ref().map(|war| &war.state)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CwlSeason {
+    pub clan_tag: String,
+    pub group: ClanWarLeagueGroup,
+    pub rounds: Vec<CwlRound>,
+    /// Every war of the group that has been scheduled, keyed by war tag
+    pub wars: HashMap<String, ClanWar>,
+    /// Tags of scheduled wars that could not be fetched, sorted
+    pub failed_wars: Vec<String>,
+}
+
+impl CwlSeason {
+    /// Fetch the clan's league group and every scheduled war in it. Wars that
+    /// fail to fetch don't fail the season, they are listed in `failed_wars`.
+    pub async fn fetch(client: &Client, clantag: &str) -> Result<Self, APIError> {
+        let group = client.get_league_group(clantag).await?;
+
+        let mut tasks = JoinSet::new();
+        for war_tag in group
+            .rounds
+            .0
+            .iter()
+            .flat_map(|round| round.war_tags.iter())
+            .filter(|war_tag| war_tag.as_str() != PLACEHOLDER_WAR_TAG)
+        {
+            let client = client.clone();
+            let war_tag = war_tag.clone();
+            tasks.spawn(async move {
+                let war = client.get_league_war(&war_tag).await;
+                (war_tag, war)
+            });
+        }
+
+        let mut wars = HashMap::new();
+        let mut failed_wars = Vec::new();
+        while let Some(result) = tasks.join_next().await {
+            let (war_tag, war) = result.map_err(|e| APIError::Unknown(e.to_string()))?;
+            match war {
+                Ok(war) => {
+                    wars.insert(war_tag, war);
+                }
+                Err(_) => failed_wars.push(war_tag),
+            }
+        }
+        failed_wars.sort();
+
+        let mut season = Self::from_wars(clantag, group, wars);
+        season.failed_wars = failed_wars;
+        Ok(season)
+    }
+
+    /// Build the season view from a group and wars that have already been
+    /// fetched. Scheduled wars missing from `wars` are taken as failed.
+    #[must_use]
+    pub fn from_wars(
+        clantag: &str,
+        group: ClanWarLeagueGroup,
+        wars: HashMap<String, ClanWar>,
+    ) -> Self {
+        let clan_tag = normalize_tag(clantag);
+
+        let rounds = group
+            .rounds
+            .0
+            .iter()
+            .enumerate()
+            .map(|(i, round)| {
+                let found = round.war_tags.iter().find_map(|war_tag| {
+                    let war = wars.get(war_tag)?.clone().for_clan(&clan_tag)?;
+                    Some((war_tag.clone(), war))
+                });
+
+                let (war_tag, war) = match found {
+                    Some((war_tag, war)) => (Some(war_tag), Some(war)),
+                    None => (Self::missing_war_tag(&round.war_tags, &wars), None),
+                };
+                CwlRound {
+                    number: i + 1,
+                    war_tag,
+                    war,
+                }
+            })
+            .collect();
+
+        Self {
+            clan_tag,
+            group,
+            rounds,
+            wars,
+            failed_wars: Vec::new(),
+        }
+    }
+
+    /// Tag of the clan's war in a round none of whose fetched wars has the
+    /// clan: the placeholder if the round is not scheduled, else the only war
+    /// of the round that was not fetched
+    fn missing_war_tag(war_tags: &[String], wars: &HashMap<String, ClanWar>) -> Option<String> {
+        if war_tags
+            .iter()
+            .all(|war_tag| war_tag == PLACEHOLDER_WAR_TAG)
+        {
+            return Some(PLACEHOLDER_WAR_TAG.to_string());
+        }
+
+        let mut missing = war_tags
+            .iter()
+            .filter(|war_tag| war_tag.as_str() != PLACEHOLDER_WAR_TAG)
+            .filter(|war_tag| !wars.contains_key(war_tag.as_str()));
+        match (missing.next(), missing.next()) {
+            (Some(war_tag), None) => Some(war_tag.clone()),
+            _ => None,
+        }
+    }
+
+    /// Whether every scheduled war was fetched
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        self.failed_wars.is_empty()
+    }
+
+    /// The season id in "YYYY-MM" form
+    #[must_use]
+    pub fn season(&self) -> &str {
+        &self.group.season
+    }
+
+    /// The clan's opponent for every round that has been scheduled
+    pub fn opponents(&self) -> impl Iterator<Item = &WarClan> {
+        self.rounds.iter().filter_map(CwlRound::opponent)
+    }
+
+    /// The most recent round that has been scheduled
+    #[must_use]
+    pub fn latest_round(&self) -> Option<&CwlRound> {
+        self.rounds
+            .iter()
+            .rev()
+            .find(|round| !round.is_placeholder())
+    }
+
+    /// The round currently on battle day, if any. A round whose war failed
+    /// to fetch is on battle day when the next round is in preparation.
+    #[must_use]
+    pub fn current_round(&self) -> Option<&CwlRound> {
+        self.rounds
+            .iter()
+            .find(|round| matches!(round.state(), Some(WarState::InWar | WarState::War)))
+            .or_else(|| {
+                self.rounds
+                    .windows(2)
+                    .find(|pair| {
+                        pair[0].is_failed()
+                            && matches!(pair[1].state(), Some(WarState::Preparation))
+                    })
+                    .map(|pair| &pair[0])
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::CwlSeason;
+    use crate::core::Client;
+    use crate::models::clan::{ClanWar, ClanWarLeagueGroup};
+    use std::collections::HashMap;
+
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_fetch_season() {
+        let group: serde_json::Value =
+            serde_json::from_str(include_str!("../../tests/fixtures/cwl_group.json")).unwrap();
+        let wars: HashMap<String, serde_json::Value> =
+            serde_json::from_str(include_str!("../../tests/fixtures/cwl_wars.json")).unwrap();
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/clans/%232PG/currentwar/leaguegroup"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(group))
+            .mount(&server)
+            .await;
+        for (war_tag, war) in wars {
+            Mock::given(method("GET"))
+                .and(path(format!("/clanwarleagues/wars/%23{}", &war_tag[1..])))
+                .respond_with(ResponseTemplate::new(200).set_body_json(war))
+                .mount(&server)
+                .await;
+        }
+
+        let client = Client::new("token").with_base_url(server.uri());
+        let season = CwlSeason::fetch(&client, "2pg").await.unwrap();
+
+        assert_eq!(season.season(), "2024-01");
+        assert_eq!(season.wars.len(), 20);
+        assert_eq!(season.rounds.len(), 7);
+        assert_eq!(season.opponents().count(), 5);
+        assert!(season.rounds[5].is_placeholder());
+        assert_eq!(season.latest_round().unwrap().number, 5);
+        assert_eq!(season.current_round().unwrap().number, 4);
+
+        // The clan is the opponent in its first round war
+        let first = season.rounds[0].war.as_ref().unwrap();
+        assert_eq!(first.clan.tag, "#2PG");
+        assert_eq!(first.opponent.tag, "#2P0");
+        assert!(season.is_complete());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_season_partial() {
+        let group: serde_json::Value =
+            serde_json::from_str(include_str!("../../tests/fixtures/cwl_group.json")).unwrap();
+        let wars: HashMap<String, serde_json::Value> =
+            serde_json::from_str(include_str!("../../tests/fixtures/cwl_wars.json")).unwrap();
+        let failing = season_war_tag(&group, 0);
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/clans/%232PG/currentwar/leaguegroup"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(group))
+            .mount(&server)
+            .await;
+        for (war_tag, war) in wars {
+            let response = if war_tag == failing {
+                ResponseTemplate::new(503).set_body_json(serde_json::json!({
+                    "reason": "inMaintenance"
+                }))
+            } else {
+                ResponseTemplate::new(200).set_body_json(war)
+            };
+            Mock::given(method("GET"))
+                .and(path(format!("/clanwarleagues/wars/%23{}", &war_tag[1..])))
+                .respond_with(response)
+                .mount(&server)
+                .await;
+        }
+
+        let client = Client::new("token").with_base_url(server.uri());
+        let season = CwlSeason::fetch(&client, "2pg").await.unwrap();
+
+        assert!(!season.is_complete());
+        assert_eq!(season.failed_wars, [failing.as_str()]);
+        assert_eq!(season.wars.len(), 19);
+        assert_eq!(season.rounds.len(), 7);
+
+        // The clan's first war failed, the round is still scheduled
+        let first = &season.rounds[0];
+        assert!(first.is_failed() && !first.is_placeholder());
+        assert_eq!(first.war_tag.as_deref(), Some(failing.as_str()));
+        assert!(season.rounds[5].is_placeholder());
+    }
+
+    /// The first war tag of round `round` in a league group
+    fn season_war_tag(group: &serde_json::Value, round: usize) -> String {
+        group["rounds"][round]["warTags"][0]
+            .as_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn test_failed_current_round() {
+        let group: ClanWarLeagueGroup =
+            serde_json::from_str(include_str!("../../tests/fixtures/cwl_group.json")).unwrap();
+        let mut wars: HashMap<String, ClanWar> =
+            serde_json::from_str(include_str!("../../tests/fixtures/cwl_wars.json")).unwrap();
+        // The clan's war on battle day
+        wars.remove("#828Q");
+
+        let season = CwlSeason::from_wars("#2PG", group, wars);
+        let current = season.current_round().unwrap();
+        assert_eq!(current.number, 4);
+        assert!(current.is_failed());
+        assert_eq!(current.war_tag.as_deref(), Some("#828Q"));
+        assert_eq!(season.latest_round().unwrap().number, 5);
+    }
+
+    #[test]
+    fn test_unknown_clan() {
+        let group: ClanWarLeagueGroup =
+            serde_json::from_str(include_str!("../../tests/fixtures/cwl_group.json")).unwrap();
+        let wars: HashMap<String, ClanWar> =
+            serde_json::from_str(include_str!("../../tests/fixtures/cwl_wars.json")).unwrap();
+
+        let season = CwlSeason::from_wars("#PPPP", group, wars);
+        assert!(season.rounds.iter().all(|round| round.war.is_none()));
+        assert!(season.rounds[..5]
+            .iter()
+            .all(|round| round.war_tag.is_none()));
+        assert!(season.rounds[5..]
+            .iter()
+            .all(|round| round.is_placeholder()));
+    }
+}
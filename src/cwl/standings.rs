@@ -0,0 +1,174 @@
+//! Clan war league standings table. The API only returns individual wars, so
+//! the table is computed the same way the game does: stars (including the
+//! win bonus) first, then total destruction.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use super::CwlSeason;
+use crate::models::clan::{ClanWar, ClanWarLeagueGroup, WarClan};
+use crate::models::enums::clan::WarState;
+use crate::models::league::WarLeagueId;
+
+/// Bonus stars awarded for winning a clan war league war
+pub const WAR_WIN_BONUS_STARS: u32 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Projection {
+    Promotion,
+    Stay,
+    Demotion,
+}
+
+#[derive(Debug, Clone)]
+pub struct CwlStanding {
+    /// Position in the group, starting at 1
+    pub rank: usize,
+    pub tag: String,
+    pub name: String,
+    /// Stars including the win bonus
+    pub stars: u32,
+    /// Total destruction over all wars, i.e. the sum of each base's destruction
+    pub destruction: f32,
+    pub wins: u8,
+    pub losses: u8,
+    pub ties: u8,
+    /// Number of wars that have ended
+    pub rounds_played: u8,
+    pub projection: Projection,
+}
+
+/// Compute the standings for every clan in `group`. Wars on battle day count
+/// towards stars and destruction, only ended wars count as played.
+pub fn standings<'a>(
+    group: &ClanWarLeagueGroup,
+    wars: impl IntoIterator<Item = &'a ClanWar>,
+    league: WarLeagueId,
+) -> Vec<CwlStanding> {
+    let mut table: HashMap<&str, CwlStanding> = group
+        .clans
+        .0
+        .iter()
+        .map(|clan| {
+            let standing = CwlStanding {
+                rank: 0,
+                tag: clan.tag.clone(),
+                name: clan.name.clone(),
+                stars: 0,
+                destruction: 0.0,
+                wins: 0,
+                losses: 0,
+                ties: 0,
+                rounds_played: 0,
+                projection: Projection::Stay,
+            };
+            (clan.tag.as_str(), standing)
+        })
+        .collect();
+
+    for war in wars {
+        let ended = match war.state {
+            WarState::Ended => true,
+            WarState::InWar | WarState::War => false,
+            _ => continue,
+        };
+
+        for (side, other) in [(&war.clan, &war.opponent), (&war.opponent, &war.clan)] {
+            let Some(standing) = table.get_mut(side.tag.as_str()) else {
+                continue;
+            };
+
+            standing.stars += u32::from(side.stars);
+            standing.destruction += side.destruction_percentage * f32::from(war.team_size);
+
+            if ended {
+                standing.rounds_played += 1;
+                match compare(side, other) {
+                    Ordering::Greater => {
+                        standing.wins += 1;
+                        standing.stars += WAR_WIN_BONUS_STARS;
+                    }
+                    Ordering::Less => standing.losses += 1,
+                    Ordering::Equal => standing.ties += 1,
+                }
+            }
+        }
+    }
+
+    let mut table: Vec<CwlStanding> = table.into_values().collect();
+    table.sort_by(|a, b| {
+        b.stars
+            .cmp(&a.stars)
+            .then(b.destruction.total_cmp(&a.destruction))
+            .then_with(|| a.tag.cmp(&b.tag))
+    });
+
+    let clans = table.len();
+    let promoted = league.promotion_spots();
+    let demoted = league.demotion_spots();
+    for (i, standing) in table.iter_mut().enumerate() {
+        standing.rank = i + 1;
+        standing.projection = if i < promoted {
+            Projection::Promotion
+        } else if i + demoted >= clans {
+            Projection::Demotion
+        } else {
+            Projection::Stay
+        };
+    }
+
+    table
+}
+
+/// Stars decide a war, destruction breaks ties
+fn compare(clan: &WarClan, opponent: &WarClan) -> Ordering {
+    clan.stars.cmp(&opponent.stars).then(
+        clan.destruction_percentage
+            .total_cmp(&opponent.destruction_percentage),
+    )
+}
+
+impl CwlSeason {
+    /// The standings table for the season's group
+    #[must_use]
+    pub fn standings(&self, league: WarLeagueId) -> Vec<CwlStanding> {
+        standings(&self.group, self.wars.values(), league)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{standings, Projection};
+    use crate::models::clan::{ClanWar, ClanWarLeagueGroup};
+    use crate::models::league::WarLeagueId;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_standings() {
+        let group: ClanWarLeagueGroup =
+            serde_json::from_str(include_str!("../../tests/fixtures/cwl_group.json")).unwrap();
+        let wars: HashMap<String, ClanWar> =
+            serde_json::from_str(include_str!("../../tests/fixtures/cwl_wars.json")).unwrap();
+
+        let table = standings(&group, wars.values(), WarLeagueId::GoldII);
+        assert_eq!(table.len(), 8);
+
+        let first = &table[0];
+        assert_eq!(first.tag, "#2PG");
+        assert_eq!(first.stars, 71);
+        assert_eq!(first.destruction, 1603.0);
+        assert_eq!((first.wins, first.losses, first.ties), (3, 0, 0));
+        assert_eq!(first.rounds_played, 3);
+
+        // Equal stars are ranked by destruction
+        assert_eq!(table[1].tag, "#2PY");
+        assert_eq!(table[2].tag, "#2P8");
+        assert_eq!(table[1].stars, table[2].stars);
+
+        let projections: Vec<Projection> = table.iter().map(|s| s.projection).collect();
+        assert_eq!(projections[..2], [Projection::Promotion; 2]);
+        assert_eq!(projections[2..6], [Projection::Stay; 4]);
+        assert_eq!(projections[6..], [Projection::Demotion; 2]);
+        assert_eq!(table[7].tag, "#2P9");
+    }
+}
@@ -1,4 +1,5 @@
-/// Errors regarding the rsclashapi wrapper
+//! Errors regarding the rsclashapi wrapper
+
 use reqwest::header::InvalidHeaderValue;
 use thiserror::Error;
 
@@ -49,9 +50,18 @@ pub enum APIError {
     /// Invalid tag (player, clan, war, league, etc.)
     #[error("Invalid tag: {0}")]
     InvalidTag(String),
+    /// Timestamp not in the API's `20240101T000000.000Z` format
+    #[error("Invalid timestamp: {0}")]
+    InvalidTimestamp(String),
     /// Failed to serialize or deserialize data
     #[error("Serialization or deserialization failed: {0}")]
     SerializationFailed(String),
+    /// Replaying a cassette that has no response for the request
+    #[error("No recorded response for {0}")]
+    UnrecordedRequest(String),
+    /// Failed to write a cassette
+    #[error("Cassette error: {0}")]
+    Cassette(CassetteError),
 }
 
 impl APIError {
@@ -88,3 +98,83 @@ impl From<InvalidHeaderValue> for APIError {
         Self::InvalidHeader(e)
     }
 }
+
+impl From<CassetteError> for APIError {
+    fn from(e: CassetteError) -> Self {
+        Self::Cassette(e)
+    }
+}
+
+/// Errors regarding linking external accounts to players
+#[derive(Error, Debug)]
+pub enum LinkError {
+    /// The player API token has already been used for a link
+    #[error("Token has already been used")]
+    TokenReused,
+    /// The API rejected the player API token
+    #[error("Invalid token for player {0}")]
+    InvalidToken(String),
+    /// The player is already linked to a different user
+    #[error("Player {tag} is already linked to user {user_id}")]
+    AlreadyLinked { tag: String, user_id: String },
+    /// Request to verify the token failed
+    #[error("Verification failed: {0}")]
+    Api(APIError),
+}
+
+impl From<APIError> for LinkError {
+    fn from(e: APIError) -> Self {
+        Self::Api(e)
+    }
+}
+
+/// Errors regarding storing snapshots
+#[derive(Error, Debug)]
+pub enum StoreError {
+    /// Failed to serialize or deserialize a snapshot
+    #[error("Serialization or deserialization failed: {0}")]
+    SerializationFailed(serde_json::Error),
+    /// A stored time is out of range
+    #[error("Invalid time: {0}")]
+    InvalidTime(i64),
+    /// Query against the database failed
+    #[cfg(feature = "sqlite")]
+    #[error("Database error: {0}")]
+    Database(rusqlite::Error),
+}
+
+impl From<serde_json::Error> for StoreError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::SerializationFailed(e)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl From<rusqlite::Error> for StoreError {
+    fn from(e: rusqlite::Error) -> Self {
+        Self::Database(e)
+    }
+}
+
+/// Errors regarding reading and writing cassettes
+#[derive(Error, Debug)]
+pub enum CassetteError {
+    /// Failed to read or write the cassette file
+    #[error("Cassette file error: {0}")]
+    Io(std::io::Error),
+    /// The cassette file is not valid
+    #[error("Serialization or deserialization failed: {0}")]
+    SerializationFailed(serde_json::Error),
+}
+
+impl From<std::io::Error> for CassetteError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for CassetteError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::SerializationFailed(e)
+    }
+}
@@ -0,0 +1,73 @@
+//! Turns two successive snapshots of the same resource into events
+
+use serde::Serialize;
+
+use super::Event;
+use crate::models::clan::{Clan, CurrentWar};
+use crate::models::clan_capital::ClanCapitalRaidSeason;
+use crate::models::player::Player;
+
+/// Whether two snapshots differ. The models do not implement `PartialEq`, so
+/// they are compared through their JSON representation.
+pub fn changed<T: Serialize>(old: &T, new: &T) -> bool {
+    serde_json::to_value(old).ok() != serde_json::to_value(new).ok()
+}
+
+#[must_use]
+pub fn diff_clan(old: &Clan, new: &Clan) -> Vec<Event> {
+    let mut events = Vec::new();
+    if changed(old, new) {
+        events.push(Event::ClanUpdated {
+            old: Box::new(old.clone()),
+            new: Box::new(new.clone()),
+        });
+    }
+    events
+}
+
+#[must_use]
+pub fn diff_war(clan_tag: &str, old: &CurrentWar, new: &CurrentWar) -> Vec<Event> {
+    let mut events = Vec::new();
+    if changed(old, new) {
+        events.push(Event::WarUpdated {
+            clan_tag: clan_tag.to_string(),
+            old: Box::new(old.clone()),
+            new: Box::new(new.clone()),
+        });
+    }
+    events
+}
+
+#[must_use]
+pub fn diff_player(old: &Player, new: &Player) -> Vec<Event> {
+    let mut events = Vec::new();
+    if changed(old, new) {
+        events.push(Event::PlayerUpdated {
+            old: Box::new(old.clone()),
+            new: Box::new(new.clone()),
+        });
+    }
+    events
+}
+
+#[must_use]
+pub fn diff_raid_season(
+    clan_tag: &str,
+    old: &ClanCapitalRaidSeason,
+    new: &ClanCapitalRaidSeason,
+) -> Vec<Event> {
+    let mut events = Vec::new();
+    if old.start_time != new.start_time {
+        events.push(Event::RaidSeasonStarted {
+            clan_tag: clan_tag.to_string(),
+            season: Box::new(new.clone()),
+        });
+    } else if changed(old, new) {
+        events.push(Event::RaidSeasonUpdated {
+            clan_tag: clan_tag.to_string(),
+            old: Box::new(old.clone()),
+            new: Box::new(new.clone()),
+        });
+    }
+    events
+}
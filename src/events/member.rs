@@ -0,0 +1,229 @@
+//! Membership events found by diffing successive member lists of a clan
+
+use serde::Serialize;
+use std::collections::HashMap;
+
+use super::Event;
+use crate::models::clan::{ClanMember, ClanMemberList};
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MemberEvent {
+    /// A player joined the clan for the first time since it has been watched
+    Joined { member: Box<ClanMember> },
+    /// A player left or was kicked from the clan
+    Left { member: Box<ClanMember> },
+    /// A player that left earlier joined again. `before` is the player as they
+    /// were last seen in the clan.
+    Rejoined {
+        before: Box<ClanMember>,
+        after: Box<ClanMember>,
+    },
+    Promoted {
+        before: Box<ClanMember>,
+        after: Box<ClanMember>,
+    },
+    Demoted {
+        before: Box<ClanMember>,
+        after: Box<ClanMember>,
+    },
+    ClanRankChanged {
+        before: Box<ClanMember>,
+        after: Box<ClanMember>,
+    },
+    TownHallUpgraded {
+        before: Box<ClanMember>,
+        after: Box<ClanMember>,
+    },
+}
+
+impl MemberEvent {
+    /// Tag of the player the event is about
+    #[must_use]
+    pub fn tag(&self) -> &str {
+        match self {
+            MemberEvent::Joined { member } | MemberEvent::Left { member } => &member.tag,
+            MemberEvent::Rejoined { after, .. }
+            | MemberEvent::Promoted { after, .. }
+            | MemberEvent::Demoted { after, .. }
+            | MemberEvent::ClanRankChanged { after, .. }
+            | MemberEvent::TownHallUpgraded { after, .. } => &after.tag,
+        }
+    }
+
+    /// Name of the event, e.g. "member.joined"
+    #[must_use]
+    pub fn kind(&self) -> &'static str {
+        match self {
+            MemberEvent::Joined { .. } => "member.joined",
+            MemberEvent::Left { .. } => "member.left",
+            MemberEvent::Rejoined { .. } => "member.rejoined",
+            MemberEvent::Promoted { .. } => "member.promoted",
+            MemberEvent::Demoted { .. } => "member.demoted",
+            MemberEvent::ClanRankChanged { .. } => "member.clan_rank_changed",
+            MemberEvent::TownHallUpgraded { .. } => "member.town_hall_upgraded",
+        }
+    }
+}
+
+/// Membership events between two member lists of `clan_tag`.
+///
+/// `former` holds the members that have left the clan so far, keyed by tag.
+/// Members leaving are added to it and members joining are looked up in it to
+/// tell rejoins from first time joins.
+#[must_use]
+pub fn diff_members(
+    clan_tag: &str,
+    old: &ClanMemberList,
+    new: &ClanMemberList,
+    former: &mut HashMap<String, ClanMember>,
+) -> Vec<Event> {
+    let before: HashMap<&str, &ClanMember> = old
+        .0
+        .iter()
+        .map(|member| (member.tag.as_str(), member))
+        .collect();
+    let after: HashMap<&str, &ClanMember> = new
+        .0
+        .iter()
+        .map(|member| (member.tag.as_str(), member))
+        .collect();
+
+    let mut events = Vec::new();
+    for member in &new.0 {
+        let Some(&previous) = before.get(member.tag.as_str()) else {
+            events.push(match former.remove(&member.tag) {
+                Some(previous) => MemberEvent::Rejoined {
+                    before: Box::new(previous),
+                    after: Box::new(member.clone()),
+                },
+                None => MemberEvent::Joined {
+                    member: Box::new(member.clone()),
+                },
+            });
+            continue;
+        };
+
+        let pair = || (Box::new(previous.clone()), Box::new(member.clone()));
+        if member.role > previous.role {
+            let (before, after) = pair();
+            events.push(MemberEvent::Promoted { before, after });
+        } else if member.role < previous.role {
+            let (before, after) = pair();
+            events.push(MemberEvent::Demoted { before, after });
+        }
+        if member.clan_rank != previous.clan_rank {
+            let (before, after) = pair();
+            events.push(MemberEvent::ClanRankChanged { before, after });
+        }
+        if member.town_hall_level > previous.town_hall_level {
+            let (before, after) = pair();
+            events.push(MemberEvent::TownHallUpgraded { before, after });
+        }
+    }
+
+    for member in &old.0 {
+        if !after.contains_key(member.tag.as_str()) {
+            former.insert(member.tag.clone(), member.clone());
+            events.push(MemberEvent::Left {
+                member: Box::new(member.clone()),
+            });
+        }
+    }
+
+    events
+        .into_iter()
+        .map(|event| Event::Member {
+            clan_tag: clan_tag.to_string(),
+            event,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{diff_members, MemberEvent};
+    use crate::events::Event;
+    use crate::models::clan::{Clan, ClanMemberList};
+    use crate::models::enums::clan::Role;
+    use std::collections::HashMap;
+
+    fn members() -> ClanMemberList {
+        let clan: Clan =
+            serde_json::from_str(include_str!("../../tests/fixtures/clan.json")).unwrap();
+        clan.member_list.unwrap()
+    }
+
+    fn member_events(events: Vec<Event>) -> Vec<MemberEvent> {
+        events
+            .into_iter()
+            .map(|event| match event {
+                Event::Member { event, .. } => event,
+                other => panic!("unexpected event {other:?}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_join_leave_rejoin() {
+        let mut former = HashMap::new();
+        let full = members();
+        let mut without = full.clone();
+        let left = without.0.remove(3);
+
+        let events = member_events(diff_members("#2PP", &full, &without, &mut former));
+        assert!(matches!(&events[..], [MemberEvent::Left { member }] if member.tag == left.tag));
+        assert!(former.contains_key(&left.tag));
+
+        let events = member_events(diff_members("#2PP", &without, &full, &mut former));
+        assert!(
+            matches!(&events[..], [MemberEvent::Rejoined { before, .. }] if before.tag == left.tag)
+        );
+        assert!(former.is_empty());
+
+        let mut newcomer = full.clone();
+        newcomer.0[0].tag = "#PY".to_string();
+        let events = member_events(diff_members("#2PP", &full, &newcomer, &mut former));
+        assert_eq!(events.len(), 2);
+        assert!(matches!(&events[0], MemberEvent::Joined { member } if member.tag == "#PY"));
+        assert!(matches!(&events[1], MemberEvent::Left { member } if member.tag == "#P0"));
+    }
+
+    #[test]
+    fn test_role_rank_and_town_hall() {
+        let mut former = HashMap::new();
+        let old = members();
+        let mut new = old.clone();
+        new.0[2].role = Role::Coleader;
+        new.0[1].role = Role::Member;
+        new.0[3].town_hall_level += 1;
+        new.0.swap(2, 3);
+        (new.0[2].clan_rank, new.0[3].clan_rank) = (new.0[3].clan_rank, new.0[2].clan_rank);
+
+        let events = member_events(diff_members("#2PP", &old, &new, &mut former));
+        let summary: Vec<(&str, &str)> = events
+            .iter()
+            .map(|event| {
+                let kind = match event {
+                    MemberEvent::Promoted { .. } => "promoted",
+                    MemberEvent::Demoted { .. } => "demoted",
+                    MemberEvent::ClanRankChanged { .. } => "rank",
+                    MemberEvent::TownHallUpgraded { .. } => "townhall",
+                    _ => "other",
+                };
+                (event.tag(), kind)
+            })
+            .collect();
+
+        assert_eq!(
+            summary,
+            [
+                ("#P2", "demoted"),
+                ("#P9", "rank"),
+                ("#P9", "townhall"),
+                ("#P8", "promoted"),
+                ("#P8", "rank"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,113 @@
+// Event subsystem: polls watched clans and players and turns the differences
+// between successive snapshots into typed events
+pub mod diff;
+pub mod member;
+pub mod player;
+pub mod poller;
+pub mod war;
+
+pub use member::MemberEvent;
+pub use player::{LevelChange, PlayerEvent};
+pub use poller::Poller;
+pub use war::{WarAttackEvent, WarEvent};
+
+use serde::Serialize;
+
+use crate::models::clan::{Clan, CurrentWar};
+use crate::models::clan_capital::ClanCapitalRaidSeason;
+use crate::models::player::Player;
+
+/// Something the poller fetches on a schedule
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Target {
+    Clan(String),
+    Members(String),
+    CurrentWar(String),
+    RaidSeason(String),
+    Player(String),
+}
+
+impl Target {
+    #[must_use]
+    pub fn tag(&self) -> &str {
+        match self {
+            Target::Clan(tag)
+            | Target::Members(tag)
+            | Target::CurrentWar(tag)
+            | Target::RaidSeason(tag)
+            | Target::Player(tag) => tag,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    /// Any field of a watched clan changed
+    ClanUpdated { old: Box<Clan>, new: Box<Clan> },
+    /// A member of a watched clan joined, left or changed
+    Member {
+        clan_tag: String,
+        event: MemberEvent,
+    },
+    /// The current war of a watched clan changed
+    WarUpdated {
+        clan_tag: String,
+        old: Box<CurrentWar>,
+        new: Box<CurrentWar>,
+    },
+    /// An attack was made or the state of a watched clan's war changed
+    War { clan_tag: String, event: WarEvent },
+    /// A watched player progressed or changed clans, labels or war preference
+    Player { tag: String, event: PlayerEvent },
+    /// Any field of a watched player changed
+    PlayerUpdated { old: Box<Player>, new: Box<Player> },
+    /// A new raid weekend started for a watched clan
+    RaidSeasonStarted {
+        clan_tag: String,
+        season: Box<ClanCapitalRaidSeason>,
+    },
+    /// The current raid weekend of a watched clan changed
+    RaidSeasonUpdated {
+        clan_tag: String,
+        old: Box<ClanCapitalRaidSeason>,
+        new: Box<ClanCapitalRaidSeason>,
+    },
+    /// Fetching a target failed, it will be retried on the next poll
+    PollFailed { target: Target, message: String },
+}
+
+impl Event {
+    /// Name of the event, e.g. "clan.updated" or "member.joined"
+    #[must_use]
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Event::ClanUpdated { .. } => "clan.updated",
+            Event::Member { event, .. } => event.kind(),
+            Event::WarUpdated { .. } => "war.updated",
+            Event::War { event, .. } => event.kind(),
+            Event::Player { event, .. } => event.kind(),
+            Event::PlayerUpdated { .. } => "player.updated",
+            Event::RaidSeasonStarted { .. } => "raid_season.started",
+            Event::RaidSeasonUpdated { .. } => "raid_season.updated",
+            Event::PollFailed { .. } => "poll.failed",
+        }
+    }
+
+    /// Tag of the clan or player the event belongs to
+    #[must_use]
+    pub fn tag(&self) -> &str {
+        match self {
+            Event::ClanUpdated { new, .. } => &new.tag,
+            Event::PlayerUpdated { new, .. } => &new.tag,
+            Event::Member { clan_tag, .. }
+            | Event::WarUpdated { clan_tag, .. }
+            | Event::War { clan_tag, .. }
+            | Event::RaidSeasonStarted { clan_tag, .. }
+            | Event::RaidSeasonUpdated { clan_tag, .. } => clan_tag,
+            Event::Player { tag, .. } => tag,
+            Event::PollFailed { target, .. } => target.tag(),
+        }
+    }
+}
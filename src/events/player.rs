@@ -0,0 +1,368 @@
+//! Progression events found by diffing successive snapshots of a player
+
+use serde::Serialize;
+use std::collections::HashMap;
+
+use super::Event;
+use crate::models::enums::player::{VillageType, WarPreference};
+use crate::models::labels::{PlayerLabelId, PlayerLabels};
+use crate::models::player::{Hero, HeroEquipment, Player, PlayerClan, Spell, Troop};
+
+/// A troop, spell, hero or hero equipment that gained levels. Units that were
+/// unlocked since the last snapshot have an `old` level of zero.
+#[derive(Debug, Clone, Serialize)]
+pub struct LevelChange {
+    pub name: String,
+    pub village: VillageType,
+    pub old: u32,
+    pub new: u32,
+    pub max_level: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PlayerEvent {
+    TrophiesChanged {
+        old: u32,
+        new: u32,
+    },
+    BuilderBaseTrophiesChanged {
+        old: u32,
+        new: u32,
+    },
+    TownHallUpgraded {
+        old: u8,
+        new: u8,
+    },
+    TownHallWeaponUpgraded {
+        old: Option<u8>,
+        new: u8,
+    },
+    TroopUpgraded(LevelChange),
+    SpellUpgraded(LevelChange),
+    HeroUpgraded(LevelChange),
+    HeroEquipmentUpgraded(LevelChange),
+    AchievementStarsIncreased {
+        name: String,
+        village: VillageType,
+        old: u8,
+        new: u8,
+    },
+    /// The player joined, left or switched clans
+    ClanChanged {
+        old: Option<Box<PlayerClan>>,
+        new: Option<Box<PlayerClan>>,
+    },
+    LabelsChanged {
+        old: Vec<PlayerLabels>,
+        new: Vec<PlayerLabels>,
+    },
+    WarPreferenceChanged {
+        old: Option<WarPreference>,
+        new: Option<WarPreference>,
+    },
+}
+
+impl PlayerEvent {
+    /// Name of the event, e.g. "player.hero_upgraded"
+    #[must_use]
+    pub fn kind(&self) -> &'static str {
+        match self {
+            PlayerEvent::TrophiesChanged { .. } => "player.trophies_changed",
+            PlayerEvent::BuilderBaseTrophiesChanged { .. } => {
+                "player.builder_base_trophies_changed"
+            }
+            PlayerEvent::TownHallUpgraded { .. } => "player.town_hall_upgraded",
+            PlayerEvent::TownHallWeaponUpgraded { .. } => "player.town_hall_weapon_upgraded",
+            PlayerEvent::TroopUpgraded(_) => "player.troop_upgraded",
+            PlayerEvent::SpellUpgraded(_) => "player.spell_upgraded",
+            PlayerEvent::HeroUpgraded(_) => "player.hero_upgraded",
+            PlayerEvent::HeroEquipmentUpgraded(_) => "player.hero_equipment_upgraded",
+            PlayerEvent::AchievementStarsIncreased { .. } => "player.achievement_stars_increased",
+            PlayerEvent::ClanChanged { .. } => "player.clan_changed",
+            PlayerEvent::LabelsChanged { .. } => "player.labels_changed",
+            PlayerEvent::WarPreferenceChanged { .. } => "player.war_preference_changed",
+        }
+    }
+}
+
+/// Name, village, level and max level of every unit in a list
+type Levels<'a> = Vec<(&'a str, VillageType, u32, u32)>;
+
+/// Anything in a player's army that has a level
+trait Unit {
+    fn level(&self) -> (&str, VillageType, u32, u32);
+}
+
+macro_rules! impl_unit {
+    ($($unit:ty),*) => {
+        $(impl Unit for $unit {
+            fn level(&self) -> (&str, VillageType, u32, u32) {
+                (self.name.as_str(), self.village, self.level, self.max_level)
+            }
+        })*
+    };
+}
+
+impl_unit!(Troop, Spell, Hero, HeroEquipment);
+
+fn levels<T: Unit>(units: &[T]) -> Levels<'_> {
+    units.iter().map(Unit::level).collect()
+}
+
+fn levels_of<T: Unit>(units: &Option<Vec<T>>) -> Levels<'_> {
+    units.as_deref().map(levels).unwrap_or_default()
+}
+
+fn level_changes(old: Levels<'_>, new: Levels<'_>) -> Vec<LevelChange> {
+    let before: HashMap<(&str, VillageType), u32> = old
+        .into_iter()
+        .map(|(name, village, level, _)| ((name, village), level))
+        .collect();
+
+    new.into_iter()
+        .filter_map(|(name, village, level, max_level)| {
+            let previous = before.get(&(name, village)).copied().unwrap_or(0);
+            (level > previous).then(|| LevelChange {
+                name: name.to_string(),
+                village,
+                old: previous,
+                new: level,
+                max_level,
+            })
+        })
+        .collect()
+}
+
+fn label_ids(labels: &Option<Vec<PlayerLabels>>) -> Vec<PlayerLabelId> {
+    labels.iter().flatten().map(|label| label.id).collect()
+}
+
+/// One event per change between two snapshots of the same player
+#[must_use]
+pub fn diff_player_events(old: &Player, new: &Player) -> Vec<Event> {
+    let mut events = Vec::new();
+
+    if old.trophies != new.trophies {
+        events.push(PlayerEvent::TrophiesChanged {
+            old: old.trophies,
+            new: new.trophies,
+        });
+    }
+    if old.builder_base_trophies != new.builder_base_trophies {
+        events.push(PlayerEvent::BuilderBaseTrophiesChanged {
+            old: old.builder_base_trophies,
+            new: new.builder_base_trophies,
+        });
+    }
+    if new.town_hall_level > old.town_hall_level {
+        events.push(PlayerEvent::TownHallUpgraded {
+            old: old.town_hall_level,
+            new: new.town_hall_level,
+        });
+    } else if let Some(weapon) = new.town_hall_weapon_level {
+        // The weapon starts over with every town hall upgrade
+        if new.town_hall_weapon_level > old.town_hall_weapon_level {
+            events.push(PlayerEvent::TownHallWeaponUpgraded {
+                old: old.town_hall_weapon_level,
+                new: weapon,
+            });
+        }
+    }
+
+    events.extend(
+        level_changes(levels(&old.troops), levels(&new.troops))
+            .into_iter()
+            .map(PlayerEvent::TroopUpgraded),
+    );
+    events.extend(
+        level_changes(levels_of(&old.spells), levels_of(&new.spells))
+            .into_iter()
+            .map(PlayerEvent::SpellUpgraded),
+    );
+    events.extend(
+        level_changes(levels_of(&old.heroes), levels_of(&new.heroes))
+            .into_iter()
+            .map(PlayerEvent::HeroUpgraded),
+    );
+    events.extend(
+        level_changes(
+            levels_of(&old.hero_equipment),
+            levels_of(&new.hero_equipment),
+        )
+        .into_iter()
+        .map(PlayerEvent::HeroEquipmentUpgraded),
+    );
+
+    let stars: HashMap<(&str, VillageType), u8> = old
+        .achievements
+        .iter()
+        .map(|achievement| {
+            (
+                (achievement.name.as_str(), achievement.village),
+                achievement.stars,
+            )
+        })
+        .collect();
+    for achievement in &new.achievements {
+        let previous = stars
+            .get(&(achievement.name.as_str(), achievement.village))
+            .copied()
+            .unwrap_or(0);
+        if achievement.stars > previous {
+            events.push(PlayerEvent::AchievementStarsIncreased {
+                name: achievement.name.clone(),
+                village: achievement.village,
+                old: previous,
+                new: achievement.stars,
+            });
+        }
+    }
+
+    let clan_tag = |player: &Player| player.clan.as_ref().map(|clan| clan.tag.clone());
+    if clan_tag(old) != clan_tag(new) {
+        events.push(PlayerEvent::ClanChanged {
+            old: old.clan.clone().map(Box::new),
+            new: new.clan.clone().map(Box::new),
+        });
+    }
+
+    if label_ids(&old.labels) != label_ids(&new.labels) {
+        events.push(PlayerEvent::LabelsChanged {
+            old: old.labels.clone().unwrap_or_default(),
+            new: new.labels.clone().unwrap_or_default(),
+        });
+    }
+
+    if old.war_preference != new.war_preference {
+        events.push(PlayerEvent::WarPreferenceChanged {
+            old: old.war_preference,
+            new: new.war_preference,
+        });
+    }
+
+    events
+        .into_iter()
+        .map(|event| Event::Player {
+            tag: new.tag.clone(),
+            event,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{diff_player_events, PlayerEvent};
+    use crate::events::Event;
+    use crate::models::enums::player::{VillageType, WarPreference};
+    use crate::models::player::Player;
+
+    fn player() -> Player {
+        serde_json::from_str(include_str!("../../tests/fixtures/player.json")).unwrap()
+    }
+
+    fn player_events(old: &Player, new: &Player) -> Vec<PlayerEvent> {
+        diff_player_events(old, new)
+            .into_iter()
+            .map(|event| match event {
+                Event::Player { event, .. } => event,
+                other => panic!("unexpected event {other:?}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_no_changes() {
+        let player = player();
+        assert!(player_events(&player, &player).is_empty());
+    }
+
+    #[test]
+    fn test_progression() {
+        let old = player();
+        let mut new = player();
+        new.trophies += 32;
+        new.town_hall_weapon_level = Some(4);
+        new.troops[1].level += 1;
+        new.spells.as_mut().unwrap()[0].level += 1;
+        new.hero_equipment.as_mut().unwrap()[1].level += 2;
+        new.achievements[1].stars = 3;
+        new.war_preference = Some(WarPreference::Out);
+
+        let events = player_events(&old, &new);
+        assert_eq!(events.len(), 7);
+        assert!(matches!(
+            events[0],
+            PlayerEvent::TrophiesChanged {
+                old: 5300,
+                new: 5332
+            }
+        ));
+        assert!(matches!(
+            events[1],
+            PlayerEvent::TownHallWeaponUpgraded {
+                old: Some(3),
+                new: 4
+            }
+        ));
+        assert!(matches!(
+            &events[2],
+            PlayerEvent::TroopUpgraded(change)
+                if change.name == "Barbarian"
+                    && change.village == VillageType::BuilderBase
+                    && (change.old, change.new) == (18, 19)
+        ));
+        assert!(matches!(&events[3], PlayerEvent::SpellUpgraded(_)));
+        assert!(matches!(
+            &events[4],
+            PlayerEvent::HeroEquipmentUpgraded(change) if (change.old, change.new) == (10, 12)
+        ));
+        assert!(matches!(
+            &events[5],
+            PlayerEvent::AchievementStarsIncreased { old: 2, new: 3, .. }
+        ));
+        assert!(matches!(
+            events[6],
+            PlayerEvent::WarPreferenceChanged {
+                old: Some(WarPreference::In),
+                new: Some(WarPreference::Out)
+            }
+        ));
+    }
+
+    #[test]
+    fn test_town_hall_and_clan() {
+        let old = player();
+        let mut new = player();
+        new.town_hall_level = 16;
+        new.town_hall_weapon_level = None;
+        new.clan = None;
+        new.labels = None;
+        new.heroes
+            .as_mut()
+            .unwrap()
+            .push(crate::models::player::Hero {
+                name: "Minion Prince".to_string(),
+                level: 1,
+                max_level: 80,
+                equipment: None,
+                village: VillageType::HomeVillage,
+            });
+
+        let events = player_events(&old, &new);
+        assert_eq!(events.len(), 4);
+        assert!(matches!(
+            events[0],
+            PlayerEvent::TownHallUpgraded { old: 15, new: 16 }
+        ));
+        assert!(matches!(&events[1], PlayerEvent::HeroUpgraded(change) if change.old == 0));
+        assert!(matches!(
+            &events[2],
+            PlayerEvent::ClanChanged {
+                old: Some(_),
+                new: None
+            }
+        ));
+        assert!(matches!(&events[3], PlayerEvent::LabelsChanged { new, .. } if new.is_empty()));
+    }
+}
@@ -0,0 +1,460 @@
+//! Polls watched clans and players on a schedule and delivers the events found
+//! by diffing successive snapshots. Every target is polled again once the
+//! API's cache for it expires (`Cache-Control: max-age`), but never more
+//! often than the configured minimum interval.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+use tokio::time::{sleep_until, Instant};
+
+use super::diff::{diff_clan, diff_player, diff_raid_season, diff_war};
+use super::member::diff_members;
+use super::player::diff_player_events;
+use super::war::{diff_war_events, war_reminders};
+use super::{Event, Target};
+use crate::api::api;
+use crate::core::client::encode_tag;
+use crate::core::{Cached, Client};
+use crate::error::APIError;
+use crate::models::clan::{Clan, ClanMember, ClanMemberList, CurrentWar};
+use crate::models::clan_capital::{ClanCapitalRaidSeason, ClanCapitalRaidSeasons};
+use crate::models::player::Player;
+use crate::utils::normalize_tag;
+
+/// Async callback invoked for every event
+pub type EventHandler =
+    Arc<dyn Fn(Event) -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync>;
+
+/// Default minimum time between two polls of the same target
+pub const DEFAULT_MIN_INTERVAL: Duration = Duration::from_secs(60);
+/// Default time a member that left is remembered to tell a rejoin
+pub const DEFAULT_FORMER_MEMBER_RETENTION: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+#[derive(Debug, Clone)]
+enum Snapshot {
+    Clan(Box<Clan>),
+    Members(ClanMemberList),
+    CurrentWar(Box<CurrentWar>),
+    RaidSeason(Box<ClanCapitalRaidSeason>),
+    Player(Box<Player>),
+}
+
+/// Members that left a watched clan, used to detect rejoins
+#[derive(Debug, Default)]
+struct FormerMembers {
+    members: HashMap<String, ClanMember>,
+    /// When each member was first seen gone
+    left_at: HashMap<String, SystemTime>,
+}
+
+impl FormerMembers {
+    /// Note when members that left since the last call were seen gone, and
+    /// forget the ones that left more than `retention` before `now`
+    fn expire(&mut self, now: SystemTime, retention: Duration) {
+        let members = &self.members;
+        self.left_at.retain(|tag, _| members.contains_key(tag));
+        for tag in self.members.keys() {
+            self.left_at.entry(tag.clone()).or_insert(now);
+        }
+        self.left_at.retain(|_, left_at| {
+            now.duration_since(*left_at)
+                .map_or(true, |gone| gone < retention)
+        });
+        let left_at = &self.left_at;
+        self.members.retain(|tag, _| left_at.contains_key(tag));
+    }
+}
+
+pub struct Poller {
+    client: Client,
+    min_interval: Duration,
+    /// When each watched target is due to be polled next
+    schedule: HashMap<Target, Instant>,
+    /// Last snapshot of each target and when it was fetched
+    snapshots: HashMap<Target, (Snapshot, SystemTime)>,
+    /// Members that left each watched clan, used to detect rejoins
+    former_members: HashMap<String, FormerMembers>,
+    former_member_retention: Duration,
+    /// Time left in a war at which to send a reminder
+    war_reminders: Vec<Duration>,
+    handlers: Vec<EventHandler>,
+    senders: Vec<mpsc::UnboundedSender<Event>>,
+}
+
+impl Poller {
+    #[must_use]
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            min_interval: DEFAULT_MIN_INTERVAL,
+            schedule: HashMap::new(),
+            snapshots: HashMap::new(),
+            former_members: HashMap::new(),
+            former_member_retention: DEFAULT_FORMER_MEMBER_RETENTION,
+            war_reminders: Vec::new(),
+            handlers: Vec::new(),
+            senders: Vec::new(),
+        }
+    }
+
+    /// Never poll the same target more often than `interval`
+    #[must_use]
+    pub fn with_min_interval(mut self, interval: Duration) -> Self {
+        self.min_interval = interval;
+        self
+    }
+
+    /// Remember members that left a watched clan for `retention`, a member
+    /// that comes back later is reported as joined rather than rejoined
+    #[must_use]
+    pub fn with_former_member_retention(mut self, retention: Duration) -> Self {
+        self.former_member_retention = retention;
+        self
+    }
+
+    /// Send a `WarEvent::Reminder` when the time left in a watched clan's war
+    /// drops to each of `reminders`
+    #[must_use]
+    pub fn with_war_reminders(mut self, reminders: impl IntoIterator<Item = Duration>) -> Self {
+        self.war_reminders = reminders.into_iter().collect();
+        self
+    }
+
+    /// Start polling `target`, the first poll only records a snapshot
+    pub fn watch(&mut self, target: Target) {
+        let target = match target {
+            Target::Clan(tag) => Target::Clan(normalize_tag(&tag)),
+            Target::Members(tag) => Target::Members(normalize_tag(&tag)),
+            Target::CurrentWar(tag) => Target::CurrentWar(normalize_tag(&tag)),
+            Target::RaidSeason(tag) => Target::RaidSeason(normalize_tag(&tag)),
+            Target::Player(tag) => Target::Player(normalize_tag(&tag)),
+        };
+        self.schedule.entry(target).or_insert_with(Instant::now);
+    }
+
+    /// Stop polling `target` and forget its last snapshot
+    pub fn unwatch(&mut self, target: &Target) {
+        self.schedule.remove(target);
+        self.snapshots.remove(target);
+        if let Target::Members(tag) = target {
+            self.former_members.remove(tag);
+        }
+    }
+
+    /// Watch a clan, its members, its current war and its raid weekends
+    pub fn watch_clan(&mut self, clantag: &str) {
+        self.watch(Target::Clan(clantag.to_string()));
+        self.watch(Target::Members(clantag.to_string()));
+        self.watch(Target::CurrentWar(clantag.to_string()));
+        self.watch(Target::RaidSeason(clantag.to_string()));
+    }
+
+    pub fn unwatch_clan(&mut self, clantag: &str) {
+        let clantag = normalize_tag(clantag);
+        self.unwatch(&Target::Clan(clantag.clone()));
+        self.unwatch(&Target::Members(clantag.clone()));
+        self.unwatch(&Target::CurrentWar(clantag.clone()));
+        self.unwatch(&Target::RaidSeason(clantag));
+    }
+
+    pub fn watch_player(&mut self, player_tag: &str) {
+        self.watch(Target::Player(player_tag.to_string()));
+    }
+
+    pub fn unwatch_player(&mut self, player_tag: &str) {
+        self.unwatch(&Target::Player(normalize_tag(player_tag)));
+    }
+
+    pub fn targets(&self) -> impl Iterator<Item = &Target> {
+        self.schedule.keys()
+    }
+
+    /// Call `handler` for every event. Handlers are awaited one after another
+    /// in the order they were registered.
+    pub fn on_event<F, Fut>(&mut self, handler: F)
+    where
+        F: Fn(Event) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.handlers
+            .push(Arc::new(move |event| Box::pin(handler(event))));
+    }
+
+    /// Receive every event through a channel
+    pub fn subscribe(&mut self) -> mpsc::UnboundedReceiver<Event> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.senders.push(sender);
+        receiver
+    }
+
+    /// Poll every target that is due, deliver the resulting events and
+    /// return them
+    pub async fn poll(&mut self) -> Vec<Event> {
+        let now = Instant::now();
+        let mut tasks = JoinSet::new();
+        for (target, _) in self.schedule.iter().filter(|(_, due)| **due <= now) {
+            let client = self.client.clone();
+            let target = target.clone();
+            tasks.spawn(async move {
+                let result = fetch(&client, &target).await;
+                (target, result)
+            });
+        }
+
+        let mut events = Vec::new();
+        while let Some(joined) = tasks.join_next().await {
+            let Ok((target, result)) = joined else {
+                continue;
+            };
+
+            let wait = match result {
+                Ok(cached) => {
+                    if let Some(snapshot) = cached.data {
+                        let now = SystemTime::now();
+                        if let Some((old, since)) = self.snapshots.remove(&target) {
+                            events.extend(self.diff(&target, &old, &snapshot, since, now));
+                        }
+                        self.snapshots.insert(target.clone(), (snapshot, now));
+                    }
+                    cached.max_age.unwrap_or_default().max(self.min_interval)
+                }
+                Err(e) => {
+                    events.push(Event::PollFailed {
+                        target: target.clone(),
+                        message: e.to_string(),
+                    });
+                    self.min_interval
+                }
+            };
+            self.schedule.insert(target, Instant::now() + wait);
+        }
+
+        self.dispatch(&events).await;
+        events
+    }
+
+    /// Poll forever, sleeping until the next target is due
+    pub async fn run(mut self) {
+        loop {
+            self.poll().await;
+            let next = self
+                .schedule
+                .values()
+                .min()
+                .copied()
+                .unwrap_or_else(|| Instant::now() + self.min_interval);
+            sleep_until(next).await;
+        }
+    }
+
+    fn diff(
+        &mut self,
+        target: &Target,
+        old: &Snapshot,
+        new: &Snapshot,
+        since: SystemTime,
+        now: SystemTime,
+    ) -> Vec<Event> {
+        match (target, old, new) {
+            (_, Snapshot::Clan(old), Snapshot::Clan(new)) => diff_clan(old, new),
+            (Target::Members(tag), Snapshot::Members(old), Snapshot::Members(new)) => {
+                let former = self.former_members.entry(tag.clone()).or_default();
+                let events = diff_members(tag, old, new, &mut former.members);
+                former.expire(now, self.former_member_retention);
+                events
+            }
+            (Target::CurrentWar(tag), Snapshot::CurrentWar(old), Snapshot::CurrentWar(new)) => {
+                let mut events = diff_war(tag, old, new);
+                events.extend(diff_war_events(tag, old, new));
+                events.extend(war_reminders(tag, new, &self.war_reminders, since, now));
+                events
+            }
+            (Target::RaidSeason(tag), Snapshot::RaidSeason(old), Snapshot::RaidSeason(new)) => {
+                diff_raid_season(tag, old, new)
+            }
+            (_, Snapshot::Player(old), Snapshot::Player(new)) => {
+                let mut events = diff_player(old, new);
+                events.extend(diff_player_events(old, new));
+                events
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    async fn dispatch(&mut self, events: &[Event]) {
+        for event in events {
+            self.senders
+                .retain(|sender| sender.send(event.clone()).is_ok());
+            for handler in &self.handlers {
+                handler(event.clone()).await;
+            }
+        }
+    }
+}
+
+/// Fetch the current snapshot of `target`. A clan without any raid weekend
+/// has no snapshot.
+async fn fetch(client: &Client, target: &Target) -> Result<Cached<Option<Snapshot>>, APIError> {
+    let cached = match target {
+        Target::Clan(tag) => {
+            let cached = client
+                .get_cached::<Clan>(&api::clan_endpoint(&encode_tag(tag)))
+                .await?;
+            (Some(Snapshot::Clan(Box::new(cached.data))), cached.max_age)
+        }
+        Target::Members(tag) => {
+            let cached = client
+                .get_items_cached::<ClanMemberList>(&api::clan_members_endpoint(&encode_tag(tag)))
+                .await?;
+            (Some(Snapshot::Members(cached.data)), cached.max_age)
+        }
+        Target::CurrentWar(tag) => {
+            let cached = client.get_current_war_cached(tag).await?;
+            (
+                Some(Snapshot::CurrentWar(Box::new(cached.data))),
+                cached.max_age,
+            )
+        }
+        Target::RaidSeason(tag) => {
+            let endpoint = format!(
+                "{}?limit=1",
+                api::clan_capital_raid_seasons_endpoint(&encode_tag(tag))
+            );
+            let cached = client
+                .get_items_cached::<ClanCapitalRaidSeasons>(&endpoint)
+                .await?;
+            let season = cached.data.0.into_iter().next();
+            (
+                season.map(|season| Snapshot::RaidSeason(Box::new(season))),
+                cached.max_age,
+            )
+        }
+        Target::Player(tag) => {
+            let cached = client
+                .get_cached::<Player>(&api::player_endpoint(&encode_tag(tag)))
+                .await?;
+            (
+                Some(Snapshot::Player(Box::new(cached.data))),
+                cached.max_age,
+            )
+        }
+    };
+
+    Ok(Cached {
+        data: cached.0,
+        max_age: cached.1,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{FormerMembers, Poller};
+    use crate::core::Client;
+    use crate::events::{Event, Target};
+    use crate::models::clan::Clan;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, SystemTime};
+
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_poll_emits_changes() {
+        let mut clan: serde_json::Value =
+            serde_json::from_str(include_str!("../../tests/fixtures/clan.json")).unwrap();
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/clans/%232PP"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("Cache-Control", "public max-age=0")
+                    .set_body_json(clan.clone()),
+            )
+            .up_to_n_times(2)
+            .mount(&server)
+            .await;
+        clan["description"] = "We war even more".into();
+        Mock::given(method("GET"))
+            .and(path("/clans/%232PP"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(clan))
+            .mount(&server)
+            .await;
+
+        let client = Client::new("token").with_base_url(server.uri());
+        let mut poller = Poller::new(client).with_min_interval(Duration::ZERO);
+        poller.watch(Target::Clan("2pp".to_string()));
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        poller.on_event(move |_| {
+            let counter = counter.clone();
+            async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+        let mut receiver = poller.subscribe();
+
+        // The first poll only records a snapshot, the second sees no change
+        assert!(poller.poll().await.is_empty());
+        assert!(poller.poll().await.is_empty());
+
+        let events = poller.poll().await;
+        assert_eq!(events.len(), 1);
+        assert!(
+            matches!(&events[0], Event::ClanUpdated { new, .. } if new.description == "We war even more")
+        );
+        assert!(matches!(receiver.try_recv(), Ok(Event::ClanUpdated { .. })));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_poll_failure() {
+        let server = MockServer::start().await;
+        let client = Client::new("token").with_base_url(server.uri());
+        let mut poller = Poller::new(client);
+        poller.watch_player("#2PP");
+
+        let events = poller.poll().await;
+        assert!(
+            matches!(&events[0], Event::PollFailed { target: Target::Player(tag), .. } if tag == "#2PP")
+        );
+
+        // Failed targets wait for the minimum interval before being retried
+        assert!(poller.poll().await.is_empty());
+    }
+
+    #[test]
+    fn test_former_members_expire() {
+        let clan: Clan =
+            serde_json::from_str(include_str!("../../tests/fixtures/clan.json")).unwrap();
+        let mut members = clan.member_list.unwrap().0.into_iter();
+        let (first, second) = (members.next().unwrap(), members.next().unwrap());
+        let retention = Duration::from_secs(60);
+        let start = SystemTime::now();
+        let mut former = FormerMembers::default();
+
+        former.members.insert(first.tag.clone(), first.clone());
+        former.expire(start, retention);
+        former.members.insert(second.tag.clone(), second.clone());
+        former.expire(start + Duration::from_secs(30), retention);
+        assert_eq!(former.members.len(), 2);
+
+        // The first left a minute ago, the second only 30 seconds ago
+        former.expire(start + retention, retention);
+        assert!(!former.members.contains_key(&first.tag));
+        assert!(former.members.contains_key(&second.tag));
+        assert_eq!(former.left_at.len(), 1);
+
+        // Rejoining removes the member, and its time with it
+        former.members.remove(&second.tag);
+        former.expire(start + retention, retention);
+        assert!(former.left_at.is_empty());
+    }
+}
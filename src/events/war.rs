@@ -0,0 +1,362 @@
+//! Attack and lifecycle events found by diffing successive snapshots of a
+//! clan's current war
+
+use serde::Serialize;
+use std::time::{Duration, SystemTime};
+
+use super::Event;
+use crate::analytics::war::attack_breakdown;
+use crate::models::clan::{ClanWar, ClanWarAttack, ClanWarMember, CurrentWar, WarPreparation};
+use crate::models::enums::clan::WarResult;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WarAttackEvent {
+    /// Whether the attack was made by the watched clan rather than its
+    /// opponent
+    pub by_clan: bool,
+    pub attacker: ClanWarMember,
+    pub defender: Option<ClanWarMember>,
+    pub attack: ClanWarAttack,
+    /// Stars that improved on the best result against the defender so far
+    pub new_stars: u8,
+}
+
+impl WarAttackEvent {
+    #[must_use]
+    pub fn stars(&self) -> u8 {
+        self.attack.stars
+    }
+
+    #[must_use]
+    pub fn destruction(&self) -> u8 {
+        self.attack.destruction_percentage
+    }
+
+    #[must_use]
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs(u64::from(self.attack.duration))
+    }
+
+    #[must_use]
+    pub fn gained_new_stars(&self) -> bool {
+        self.new_stars > 0
+    }
+}
+
+/// Events of a single war, always from the watched clan's perspective
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WarEvent {
+    /// The clan was matched against a new opponent
+    Matched {
+        war: Box<CurrentWar>,
+    },
+    PreparationStarted {
+        war: Box<WarPreparation>,
+    },
+    /// Battle day started
+    Started {
+        war: Box<ClanWar>,
+    },
+    Ended {
+        war: Box<ClanWar>,
+        result: WarResult,
+    },
+    /// A configured amount of time is left until the war ends
+    Reminder {
+        war: Box<ClanWar>,
+        remaining: Duration,
+    },
+    Attack(Box<WarAttackEvent>),
+}
+
+impl WarEvent {
+    /// Name of the event, e.g. "war.attack"
+    #[must_use]
+    pub fn kind(&self) -> &'static str {
+        match self {
+            WarEvent::Matched { .. } => "war.matched",
+            WarEvent::PreparationStarted { .. } => "war.preparation_started",
+            WarEvent::Started { .. } => "war.started",
+            WarEvent::Ended { .. } => "war.ended",
+            WarEvent::Reminder { .. } => "war.reminder",
+            WarEvent::Attack(_) => "war.attack",
+        }
+    }
+}
+
+/// Highest attack order seen in a war, zero if nobody has attacked yet
+fn last_order(war: &ClanWar) -> u8 {
+    [&war.clan, &war.opponent]
+        .iter()
+        .flat_map(|side| side.members.0.iter())
+        .flat_map(|member| member.attacks.0.iter())
+        .map(|attack| attack.order)
+        .max()
+        .unwrap_or(0)
+}
+
+/// Attacks made on either side of `war` after the attack numbered `after`, in
+/// attack order
+fn new_attacks(war: &ClanWar, after: u8) -> Vec<WarEvent> {
+    let clan = attack_breakdown(&war.clan, &war.opponent)
+        .into_iter()
+        .map(|info| (true, info));
+    let opponent = attack_breakdown(&war.opponent, &war.clan)
+        .into_iter()
+        .map(|info| (false, info));
+
+    let mut attacks: Vec<_> = clan
+        .chain(opponent)
+        .filter(|(_, info)| info.attack.order > after)
+        .collect();
+    attacks.sort_by_key(|(_, info)| info.attack.order);
+
+    attacks
+        .into_iter()
+        .map(|(by_clan, info)| {
+            WarEvent::Attack(Box::new(WarAttackEvent {
+                by_clan,
+                attacker: info.attacker.clone(),
+                defender: info.defender.cloned(),
+                attack: info.attack.clone(),
+                new_stars: info.new_stars,
+            }))
+        })
+        .collect()
+}
+
+/// Lifecycle and attack events between two snapshots of `clan_tag`'s current
+/// war. Polls may skip a state, e.g. a war that was matched and started
+/// between two polls yields both `Matched` and `Started`. A war that is seen
+/// for the first time after it ended only yields `Ended`.
+#[must_use]
+pub fn diff_war_events(clan_tag: &str, old: &CurrentWar, new: &CurrentWar) -> Vec<Event> {
+    let mut events = Vec::new();
+
+    if new.war_key().is_some() {
+        let same_war = old.war_key() == new.war_key();
+        let ended = matches!(new, CurrentWar::Ended(_));
+        if !same_war && !ended {
+            events.push(WarEvent::Matched {
+                war: Box::new(new.clone()),
+            });
+        }
+
+        let state_changed = !same_war || old.state() != new.state();
+        if state_changed {
+            match new {
+                CurrentWar::Preparation(war) => events.push(WarEvent::PreparationStarted {
+                    war: Box::new(war.clone()),
+                }),
+                CurrentWar::InWar(war) => events.push(WarEvent::Started {
+                    war: Box::new(war.clone()),
+                }),
+                _ => {}
+            }
+        }
+
+        if let Some(war) = new.war() {
+            if same_war || !ended {
+                let previous = old.war().filter(|_| same_war);
+                events.extend(new_attacks(war, previous.map_or(0, last_order)));
+            }
+
+            if state_changed && ended {
+                events.push(WarEvent::Ended {
+                    war: Box::new(war.clone()),
+                    result: war.result(),
+                });
+            }
+        }
+    }
+
+    events
+        .into_iter()
+        .map(|event| Event::War {
+            clan_tag: clan_tag.to_string(),
+            event,
+        })
+        .collect()
+}
+
+/// Reminders for every duration in `reminders` that the time left in the war
+/// dropped to between the polls at `since` and `now`
+#[must_use]
+pub fn war_reminders(
+    clan_tag: &str,
+    war: &CurrentWar,
+    reminders: &[Duration],
+    since: SystemTime,
+    now: SystemTime,
+) -> Vec<Event> {
+    let CurrentWar::InWar(war) = war else {
+        return Vec::new();
+    };
+    let before = war.remaining(since);
+    let left = war.remaining(now);
+    let mut reminders: Vec<Duration> = reminders
+        .iter()
+        .copied()
+        .filter(|remaining| left <= *remaining && *remaining < before)
+        .collect();
+    // Longest first, the order they were crossed in
+    reminders.sort_by(|a, b| b.cmp(a));
+
+    reminders
+        .into_iter()
+        .map(|remaining| Event::War {
+            clan_tag: clan_tag.to_string(),
+            event: WarEvent::Reminder {
+                war: Box::new(war.clone()),
+                remaining,
+            },
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{diff_war_events, war_reminders, WarEvent};
+    use crate::events::Event;
+    use crate::models::clan::{ClanWar, CurrentWar};
+    use crate::models::enums::clan::WarResult;
+    use crate::utils::time::parse_api_time;
+    use std::time::Duration;
+
+    fn war() -> ClanWar {
+        serde_json::from_str(include_str!("../../tests/fixtures/current_war.json")).unwrap()
+    }
+
+    fn war_events(events: Vec<Event>) -> Vec<WarEvent> {
+        events
+            .into_iter()
+            .map(|event| match event {
+                Event::War { event, .. } => event,
+                other => panic!("unexpected event {other:?}"),
+            })
+            .collect()
+    }
+
+    /// The fixture war as it was before the attack numbered `order`
+    fn before_attack(order: u8) -> ClanWar {
+        let mut war = war();
+        for side in [&mut war.clan, &mut war.opponent] {
+            for member in &mut side.members.0 {
+                member.attacks.0.retain(|attack| attack.order < order);
+            }
+        }
+        war
+    }
+
+    #[test]
+    fn test_new_attacks() {
+        let old = CurrentWar::InWar(before_attack(9));
+        let new = CurrentWar::InWar(war());
+
+        let events = war_events(diff_war_events("#2PP", &old, &new));
+        let attacks: Vec<(u8, bool, u8)> = events
+            .iter()
+            .map(|event| match event {
+                WarEvent::Attack(attack) => (attack.attack.order, attack.by_clan, attack.new_stars),
+                other => panic!("unexpected event {other:?}"),
+            })
+            .collect();
+        assert_eq!(attacks, [(9, true, 3), (10, false, 3), (11, true, 1)]);
+
+        let WarEvent::Attack(first) = &events[0] else {
+            unreachable!()
+        };
+        assert_eq!(first.attacker.tag, "#PY");
+        assert!(first.defender.is_some());
+        assert!(first.gained_new_stars());
+    }
+
+    #[test]
+    fn test_lifecycle() {
+        let preparation = CurrentWar::Preparation(
+            serde_json::from_str(include_str!("../../tests/fixtures/current_war.json")).unwrap(),
+        );
+
+        let events = war_events(diff_war_events("#2PP", &CurrentWar::NotInWar, &preparation));
+        assert!(matches!(
+            &events[..],
+            [
+                WarEvent::Matched { .. },
+                WarEvent::PreparationStarted { .. }
+            ]
+        ));
+
+        let started = CurrentWar::InWar(before_attack(1));
+        let events = war_events(diff_war_events("#2PP", &preparation, &started));
+        assert!(matches!(&events[..], [WarEvent::Started { .. }]));
+
+        let ended = CurrentWar::Ended(war());
+        let events = war_events(diff_war_events("#2PP", &started, &ended));
+        assert_eq!(events.len(), 12);
+        assert!(matches!(
+            events.last(),
+            Some(WarEvent::Ended {
+                result: WarResult::Win,
+                ..
+            })
+        ));
+
+        assert!(diff_war_events("#2PP", &ended, &ended).is_empty());
+        assert!(diff_war_events("#2PP", &ended, &CurrentWar::NotInWar).is_empty());
+    }
+
+    #[test]
+    fn test_ended_war_seen_first() {
+        let ended = CurrentWar::Ended(war());
+        for old in [CurrentWar::NotInWar, CurrentWar::InMatchmaking] {
+            let events = war_events(diff_war_events("#2PP", &old, &ended));
+            assert!(matches!(
+                &events[..],
+                [WarEvent::Ended {
+                    result: WarResult::Win,
+                    ..
+                }]
+            ));
+        }
+    }
+
+    #[test]
+    fn test_war_seen_first_mid_war() {
+        let events = war_events(diff_war_events(
+            "#2PP",
+            &CurrentWar::NotInWar,
+            &CurrentWar::InWar(war()),
+        ));
+        assert!(matches!(
+            &events[..2],
+            [WarEvent::Matched { .. }, WarEvent::Started { .. }]
+        ));
+        assert_eq!(events.len(), 13);
+    }
+
+    #[test]
+    fn test_reminders() {
+        let war = CurrentWar::InWar(war());
+        let reminders = [
+            Duration::from_secs(3600),
+            Duration::from_secs(15 * 60),
+            Duration::from_secs(5 * 60),
+        ];
+
+        let since = parse_api_time("20240112T064000.000Z").unwrap();
+        let now = parse_api_time("20240112T075000.000Z").unwrap();
+        let events = war_events(war_reminders("#2PP", &war, &reminders, since, now));
+        let remaining: Vec<Duration> = events
+            .iter()
+            .map(|event| match event {
+                WarEvent::Reminder { remaining, .. } => *remaining,
+                other => panic!("unexpected event {other:?}"),
+            })
+            .collect();
+        assert_eq!(remaining, [reminders[0], reminders[1]]);
+
+        let later = parse_api_time("20240112T075100.000Z").unwrap();
+        assert!(war_reminders("#2PP", &war, &reminders, now, later).is_empty());
+    }
+}
@@ -0,0 +1,370 @@
+//! Change log of a clan's settings and standing, derived from stored snapshots
+//! of the clan. Member changes are left to the event poller.
+
+use std::time::SystemTime;
+
+use crate::error::StoreError;
+use crate::events::diff::changed;
+use crate::models::clan::{Clan, ClanMember};
+use crate::models::enums::clan::{EntryType, Role, WarFrequency};
+use crate::models::labels::{ClanLabelId, ClanLabels};
+use crate::models::league::{CapitalLeague, WarLeague};
+use crate::models::location::Location;
+use crate::store::{Snapshot, Store, StoreExt};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ClanField {
+    Name,
+    Description,
+    Labels,
+    Location,
+    WarFrequency,
+    Type,
+    RequiredTrophies,
+    RequiredTownHallLevel,
+    ClanLevel,
+    WarLeague,
+    CapitalLeague,
+}
+
+#[derive(Debug, Clone)]
+pub enum ClanChange {
+    Name {
+        old: String,
+        new: String,
+    },
+    Description {
+        old: String,
+        new: String,
+    },
+    Labels {
+        old: Vec<ClanLabels>,
+        new: Vec<ClanLabels>,
+    },
+    Location {
+        old: Option<Box<Location>>,
+        new: Option<Box<Location>>,
+    },
+    WarFrequency {
+        old: Option<WarFrequency>,
+        new: Option<WarFrequency>,
+    },
+    Type {
+        old: EntryType,
+        new: EntryType,
+    },
+    RequiredTrophies {
+        old: Option<u16>,
+        new: Option<u16>,
+    },
+    RequiredTownHallLevel {
+        old: Option<u16>,
+        new: Option<u16>,
+    },
+    ClanLevel {
+        old: u16,
+        new: u16,
+    },
+    WarLeague {
+        old: Option<WarLeague>,
+        new: Option<WarLeague>,
+    },
+    CapitalLeague {
+        old: Option<CapitalLeague>,
+        new: Option<CapitalLeague>,
+    },
+}
+
+impl ClanChange {
+    #[must_use]
+    pub fn field(&self) -> ClanField {
+        match self {
+            Self::Name { .. } => ClanField::Name,
+            Self::Description { .. } => ClanField::Description,
+            Self::Labels { .. } => ClanField::Labels,
+            Self::Location { .. } => ClanField::Location,
+            Self::WarFrequency { .. } => ClanField::WarFrequency,
+            Self::Type { .. } => ClanField::Type,
+            Self::RequiredTrophies { .. } => ClanField::RequiredTrophies,
+            Self::RequiredTownHallLevel { .. } => ClanField::RequiredTownHallLevel,
+            Self::ClanLevel { .. } => ClanField::ClanLevel,
+            Self::WarLeague { .. } => ClanField::WarLeague,
+            Self::CapitalLeague { .. } => ClanField::CapitalLeague,
+        }
+    }
+
+    /// Whether the change is a setting made by the leader or a co-leader,
+    /// rather than progress like the clan level or a league
+    #[must_use]
+    pub fn is_setting(&self) -> bool {
+        !matches!(
+            self,
+            Self::ClanLevel { .. } | Self::WarLeague { .. } | Self::CapitalLeague { .. }
+        )
+    }
+}
+
+fn label_ids(labels: &Option<Vec<ClanLabels>>) -> Vec<ClanLabelId> {
+    labels.iter().flatten().map(|label| label.id).collect()
+}
+
+fn location_id(clan: &Clan) -> Option<u32> {
+    clan.location.as_ref().map(|location| location.id)
+}
+
+/// Every tracked field that differs between two snapshots of the same clan
+#[must_use]
+pub fn diff_clan_fields(old: &Clan, new: &Clan) -> Vec<ClanChange> {
+    let mut changes = Vec::new();
+
+    if old.name != new.name {
+        changes.push(ClanChange::Name {
+            old: old.name.clone(),
+            new: new.name.clone(),
+        });
+    }
+    if old.description != new.description {
+        changes.push(ClanChange::Description {
+            old: old.description.clone(),
+            new: new.description.clone(),
+        });
+    }
+    if label_ids(&old.labels) != label_ids(&new.labels) {
+        changes.push(ClanChange::Labels {
+            old: old.labels.clone().unwrap_or_default(),
+            new: new.labels.clone().unwrap_or_default(),
+        });
+    }
+    if location_id(old) != location_id(new) {
+        changes.push(ClanChange::Location {
+            old: old.location.clone().map(Box::new),
+            new: new.location.clone().map(Box::new),
+        });
+    }
+    if changed(&old.war_frequency, &new.war_frequency) {
+        changes.push(ClanChange::WarFrequency {
+            old: old.war_frequency.clone(),
+            new: new.war_frequency.clone(),
+        });
+    }
+    if changed(&old.r#type, &new.r#type) {
+        changes.push(ClanChange::Type {
+            old: old.r#type.clone(),
+            new: new.r#type.clone(),
+        });
+    }
+    if old.required_trophies != new.required_trophies {
+        changes.push(ClanChange::RequiredTrophies {
+            old: old.required_trophies,
+            new: new.required_trophies,
+        });
+    }
+    if old.required_townhall_level != new.required_townhall_level {
+        changes.push(ClanChange::RequiredTownHallLevel {
+            old: old.required_townhall_level,
+            new: new.required_townhall_level,
+        });
+    }
+    if old.clan_level != new.clan_level {
+        changes.push(ClanChange::ClanLevel {
+            old: old.clan_level,
+            new: new.clan_level,
+        });
+    }
+    if changed(&old.war_league, &new.war_league) {
+        changes.push(ClanChange::WarLeague {
+            old: old.war_league.clone(),
+            new: new.war_league.clone(),
+        });
+    }
+    if changed(&old.capital_league, &new.capital_league) {
+        changes.push(ClanChange::CapitalLeague {
+            old: old.capital_league.clone(),
+            new: new.capital_league.clone(),
+        });
+    }
+
+    changes
+}
+
+#[derive(Debug, Clone)]
+pub struct ClanChangeEntry {
+    /// Time of the snapshot the change was first seen in
+    pub time: SystemTime,
+    pub change: ClanChange,
+    /// Leader of the clan at the time. The API does not say who changed a
+    /// setting, only the leader and co-leaders can.
+    pub leader: Option<Box<ClanMember>>,
+}
+
+/// Changes of a single clan, oldest first
+#[derive(Debug, Clone, Default)]
+pub struct ClanHistory {
+    pub tag: String,
+    pub changes: Vec<ClanChangeEntry>,
+}
+
+impl ClanHistory {
+    /// Build the change log from snapshots of the clan in any order
+    #[must_use]
+    pub fn from_snapshots(tag: &str, snapshots: &[Snapshot<Clan>]) -> Self {
+        let mut snapshots: Vec<&Snapshot<Clan>> = snapshots.iter().collect();
+        snapshots.sort_by_key(|snapshot| snapshot.time);
+
+        let changes = snapshots
+            .windows(2)
+            .flat_map(|pair| {
+                let (old, new) = (&pair[0].value, &pair[1].value);
+                let leader = new
+                    .member_list
+                    .iter()
+                    .flat_map(|members| &members.0)
+                    .find(|member| member.role == Role::Leader);
+                diff_clan_fields(old, new)
+                    .into_iter()
+                    .map(move |change| ClanChangeEntry {
+                        time: pair[1].time,
+                        change,
+                        leader: leader.cloned().map(Box::new),
+                    })
+            })
+            .collect();
+
+        Self {
+            tag: tag.to_string(),
+            changes,
+        }
+    }
+
+    /// Load the snapshots of a clan taken between `from` and `to` and build
+    /// the change log
+    pub fn load(
+        store: &impl Store,
+        tag: &str,
+        from: SystemTime,
+        to: SystemTime,
+    ) -> Result<Self, StoreError> {
+        let snapshots = store.snapshots::<Clan>(tag, from, to)?;
+        Ok(Self::from_snapshots(tag, &snapshots))
+    }
+
+    /// Changes of a single field, oldest first
+    pub fn changes_of(&self, field: ClanField) -> impl Iterator<Item = &ClanChangeEntry> {
+        self.changes
+            .iter()
+            .filter(move |entry| entry.change.field() == field)
+    }
+
+    /// Changes seen at or after `time`
+    pub fn since(&self, time: SystemTime) -> impl Iterator<Item = &ClanChangeEntry> {
+        self.changes.iter().filter(move |entry| entry.time >= time)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{diff_clan_fields, ClanChange, ClanField, ClanHistory};
+    use crate::models::clan::Clan;
+    use crate::models::enums::clan::{EntryType, Role};
+    use crate::store::{MemoryStore, StoreExt};
+    use crate::utils::time::parse_api_time;
+    use std::time::SystemTime;
+
+    fn clan() -> Clan {
+        serde_json::from_str(include_str!("../../tests/fixtures/clan.json")).unwrap()
+    }
+
+    #[test]
+    fn test_diff_clan_fields() {
+        let old = clan();
+        assert!(diff_clan_fields(&old, &old).is_empty());
+
+        let mut new = clan();
+        new.description = "We war a lot, join us".to_string();
+        new.labels = None;
+        new.location = None;
+        new.r#type = EntryType::Open;
+        new.required_townhall_level = Some(13);
+        new.clan_level = 13;
+        new.war_league = None;
+        // Fields outside the change log are ignored
+        new.clan_points += 100;
+
+        let changes = diff_clan_fields(&old, &new);
+        let fields: Vec<ClanField> = changes.iter().map(ClanChange::field).collect();
+        assert_eq!(
+            fields,
+            [
+                ClanField::Description,
+                ClanField::Labels,
+                ClanField::Location,
+                ClanField::Type,
+                ClanField::RequiredTownHallLevel,
+                ClanField::ClanLevel,
+                ClanField::WarLeague,
+            ]
+        );
+        assert!(matches!(
+            changes[4],
+            ClanChange::RequiredTownHallLevel {
+                old: Some(12),
+                new: Some(13)
+            }
+        ));
+        assert!(changes[3].is_setting());
+        assert!(!changes[5].is_setting());
+    }
+
+    #[test]
+    fn test_history() {
+        let mut clan = clan();
+        let mut store = MemoryStore::new();
+        let time = |value: &str| parse_api_time(value).unwrap();
+
+        store
+            .save_clan(&clan, time("20240101T000000.000Z"))
+            .unwrap();
+        clan.name = "Away Clan".to_string();
+        store
+            .save_clan(&clan, time("20240102T000000.000Z"))
+            .unwrap();
+        store
+            .save_clan(&clan, time("20240103T000000.000Z"))
+            .unwrap();
+        // The leader hands over the clan and changes the requirements
+        let members = &mut clan.member_list.as_mut().unwrap().0;
+        members[0].role = Role::Coleader;
+        members[1].role = Role::Leader;
+        clan.required_trophies = Some(2500);
+        clan.name = "Home Clan".to_string();
+        store
+            .save_clan(&clan, time("20240104T000000.000Z"))
+            .unwrap();
+
+        let history = ClanHistory::load(
+            &store,
+            "#2pp",
+            SystemTime::UNIX_EPOCH,
+            time("20240201T000000.000Z"),
+        )
+        .unwrap();
+        assert_eq!(history.changes.len(), 3);
+
+        let names: Vec<_> = history.changes_of(ClanField::Name).collect();
+        assert_eq!(names.len(), 2);
+        assert_eq!(names[0].time, time("20240102T000000.000Z"));
+        assert_eq!(names[0].leader.as_ref().unwrap().name, "Alpha");
+        assert_eq!(names[1].leader.as_ref().unwrap().name, "Bravo");
+
+        let recent: Vec<_> = history.since(time("20240104T000000.000Z")).collect();
+        assert_eq!(recent.len(), 2);
+        assert!(matches!(recent[0].change, ClanChange::Name { .. }));
+        assert!(matches!(
+            recent[1].change,
+            ClanChange::RequiredTrophies {
+                old: Some(2000),
+                new: Some(2500)
+            }
+        ));
+    }
+}
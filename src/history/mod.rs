@@ -0,0 +1,6 @@
+// History derived from stored snapshots
+pub mod clan;
+pub mod player;
+
+pub use clan::{ClanChange, ClanChangeEntry, ClanField, ClanHistory};
+pub use player::{Metric, Period, PlayerHistory, Point, Series};
@@ -0,0 +1,357 @@
+//! Time series of a player's progress, derived from stored snapshots of the
+//! player. Series can be resampled to days, weeks or seasons, turned into
+//! deltas and reduced to the best value of every season.
+
+use std::collections::BTreeMap;
+use std::time::SystemTime;
+
+use crate::calendar::season;
+use crate::error::StoreError;
+use crate::models::enums::player::VillageType;
+use crate::models::player::Player;
+use crate::store::{Snapshot, Store, StoreExt};
+use crate::utils::time::{from_unix, season_id, to_unix, weekday, SECONDS_PER_DAY};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Metric {
+    Trophies,
+    WarStars,
+    /// Troops donated this season, the counter resets when the season ends
+    Donations,
+    ClanCapitalContributions,
+    AttackWins,
+    /// Sum of the levels of every home village hero
+    HeroLevels,
+    /// Level of a single home village hero, e.g. "Barbarian King"
+    Hero(String),
+}
+
+impl Metric {
+    /// Whether the metric starts over from zero when the season ends
+    #[must_use]
+    pub fn resets_each_season(&self) -> bool {
+        matches!(self, Self::Donations)
+    }
+
+    /// Value of the metric for a player, `None` for a hero that is not
+    /// unlocked yet
+    #[must_use]
+    pub fn value(&self, player: &Player) -> Option<i64> {
+        let heroes = || {
+            player
+                .heroes
+                .iter()
+                .flatten()
+                .filter(|hero| hero.village == VillageType::HomeVillage)
+        };
+        let value = match self {
+            Self::Trophies => player.trophies,
+            Self::WarStars => player.war_stars,
+            Self::Donations => player.donations,
+            Self::ClanCapitalContributions => player.clan_capital_contributions,
+            Self::AttackWins => player.attack_wins,
+            Self::HeroLevels => heroes().map(|hero| hero.level).sum(),
+            Self::Hero(name) => heroes().find(|hero| &hero.name == name)?.level,
+        };
+        Some(i64::from(value))
+    }
+}
+
+/// Length of the buckets a series is resampled to. Days and weeks are UTC,
+/// weeks start on Monday and seasons end on the last Monday of the month at
+/// 05:00.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Period {
+    Day,
+    Week,
+    Season,
+}
+
+impl Period {
+    /// Start of the bucket `time` falls in
+    #[must_use]
+    pub fn start(&self, time: SystemTime) -> SystemTime {
+        let (seconds, _) = to_unix(time);
+        let days = seconds.div_euclid(SECONDS_PER_DAY);
+        let start = match self {
+            Self::Day => days,
+            Self::Week => days - i64::from(weekday(days)),
+            Self::Season => return season(time).start,
+        };
+        from_unix(start * SECONDS_PER_DAY, 0).unwrap_or(SystemTime::UNIX_EPOCH)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Point {
+    pub time: SystemTime,
+    pub value: i64,
+}
+
+/// Points of a metric, oldest first
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Series {
+    pub metric: Metric,
+    pub points: Vec<Point>,
+}
+
+impl Series {
+    #[must_use]
+    pub fn first(&self) -> Option<&Point> {
+        self.points.first()
+    }
+
+    #[must_use]
+    pub fn last(&self) -> Option<&Point> {
+        self.points.last()
+    }
+
+    /// Difference between the last and the first point. For a metric that
+    /// resets each season it is the sum of the deltas instead.
+    #[must_use]
+    pub fn change(&self) -> i64 {
+        if self.metric.resets_each_season() {
+            return self.deltas().points.iter().map(|point| point.value).sum();
+        }
+        match (self.first(), self.last()) {
+            (Some(first), Some(last)) => last.value - first.value,
+            _ => 0,
+        }
+    }
+
+    /// One point per bucket holding the last value seen in it, timed at the
+    /// start of the bucket
+    #[must_use]
+    pub fn resample(&self, period: Period) -> Self {
+        let mut points: Vec<Point> = Vec::new();
+        for point in &self.points {
+            let time = period.start(point.time);
+            match points.last_mut() {
+                Some(last) if last.time == time => last.value = point.value,
+                _ => points.push(Point {
+                    time,
+                    value: point.value,
+                }),
+            }
+        }
+        Self {
+            metric: self.metric.clone(),
+            points,
+        }
+    }
+
+    /// Change from every point to the next, timed at the later point.
+    /// Resample first to get the change per day, week or season. A metric that
+    /// resets each season and went down was reset, its change is the new value.
+    #[must_use]
+    pub fn deltas(&self) -> Self {
+        let resets = self.metric.resets_each_season();
+        let points = self
+            .points
+            .windows(2)
+            .map(|pair| {
+                let (before, after) = (pair[0].value, pair[1].value);
+                Point {
+                    time: pair[1].time,
+                    value: if resets && after < before {
+                        after
+                    } else {
+                        after - before
+                    },
+                }
+            })
+            .collect();
+        Self {
+            metric: self.metric.clone(),
+            points,
+        }
+    }
+
+    /// Highest point of every season, keyed by season id in "YYYY-MM" form.
+    /// The earliest point wins a tie.
+    #[must_use]
+    pub fn best_per_season(&self) -> BTreeMap<String, Point> {
+        let mut best: BTreeMap<String, Point> = BTreeMap::new();
+        for point in &self.points {
+            best.entry(season_id(point.time))
+                .and_modify(|best| {
+                    if point.value > best.value {
+                        *best = *point;
+                    }
+                })
+                .or_insert(*point);
+        }
+        best
+    }
+}
+
+/// Snapshots of a single player, oldest first
+#[derive(Debug, Clone)]
+pub struct PlayerHistory {
+    pub tag: String,
+    pub snapshots: Vec<Snapshot<Player>>,
+}
+
+impl PlayerHistory {
+    #[must_use]
+    pub fn new(tag: &str, mut snapshots: Vec<Snapshot<Player>>) -> Self {
+        snapshots.sort_by_key(|snapshot| snapshot.time);
+        Self {
+            tag: tag.to_string(),
+            snapshots,
+        }
+    }
+
+    /// Load the snapshots of a player taken between `from` and `to`
+    pub fn load(
+        store: &impl Store,
+        tag: &str,
+        from: SystemTime,
+        to: SystemTime,
+    ) -> Result<Self, StoreError> {
+        let snapshots = store.snapshots::<Player>(tag, from, to)?;
+        Ok(Self::new(tag, snapshots))
+    }
+
+    /// Every value of a metric, snapshots without a value are skipped
+    #[must_use]
+    pub fn series(&self, metric: Metric) -> Series {
+        let points = self
+            .snapshots
+            .iter()
+            .filter_map(|snapshot| {
+                Some(Point {
+                    time: snapshot.time,
+                    value: metric.value(&snapshot.value)?,
+                })
+            })
+            .collect();
+        Series { metric, points }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Metric, Period, PlayerHistory, Series};
+    use crate::models::player::Player;
+    use crate::store::{MemoryStore, StoreExt};
+    use crate::utils::time::{format_api_time, parse_api_time};
+    use std::time::SystemTime;
+
+    fn time(value: &str) -> SystemTime {
+        parse_api_time(value).unwrap()
+    }
+
+    /// Snapshots every twelve hours around the end of the January 2024 season,
+    /// 2024-01-29 05:00
+    fn history() -> PlayerHistory {
+        let mut player: Player =
+            serde_json::from_str(include_str!("../../tests/fixtures/player.json")).unwrap();
+        let mut store = MemoryStore::new();
+        let times = [
+            "20240127T000000.000Z",
+            "20240127T120000.000Z",
+            "20240128T000000.000Z",
+            "20240128T120000.000Z",
+            "20240129T120000.000Z",
+            "20240130T000000.000Z",
+        ];
+        let trophies = [5300, 5340, 5320, 5380, 5000, 5030];
+        let donations = [100, 150, 180, 200, 0, 40];
+        for (i, now) in times.iter().enumerate() {
+            player.trophies = trophies[i];
+            player.donations = donations[i];
+            if i == 3 {
+                player.heroes.as_mut().unwrap()[0].level += 1;
+            }
+            store.save_player(&player, time(now)).unwrap();
+        }
+        PlayerHistory::load(
+            &store,
+            "#P0",
+            SystemTime::UNIX_EPOCH,
+            time("20240201T000000.000Z"),
+        )
+        .unwrap()
+    }
+
+    /// Date and value of every point
+    fn values(series: &Series) -> Vec<(String, i64)> {
+        series
+            .points
+            .iter()
+            .map(|point| (format_api_time(point.time)[..8].to_string(), point.value))
+            .collect()
+    }
+
+    #[test]
+    fn test_resample_and_deltas() {
+        let history = history();
+        let trophies = history.series(Metric::Trophies);
+        assert_eq!(trophies.points.len(), 6);
+        assert_eq!(trophies.change(), -270);
+
+        let daily = trophies.resample(Period::Day);
+        assert_eq!(
+            values(&daily),
+            [
+                ("20240127".to_string(), 5340),
+                ("20240128".to_string(), 5380),
+                ("20240129".to_string(), 5000),
+                ("20240130".to_string(), 5030),
+            ]
+        );
+        let changes: Vec<i64> = daily.deltas().points.iter().map(|p| p.value).collect();
+        assert_eq!(changes, [40, -380, 30]);
+
+        // 2024-01-22 and 2024-01-29 are Mondays
+        let weekly = trophies.resample(Period::Week);
+        assert_eq!(
+            values(&weekly),
+            [
+                ("20240122".to_string(), 5380),
+                ("20240129".to_string(), 5030)
+            ]
+        );
+
+        let seasons = history.series(Metric::Donations).resample(Period::Season);
+        assert_eq!(seasons.points.len(), 2);
+        assert_eq!(
+            format_api_time(seasons.points[1].time),
+            "20240129T050000.000Z"
+        );
+    }
+
+    #[test]
+    fn test_deltas_across_season_reset() {
+        let donations = history().series(Metric::Donations);
+        let changes: Vec<i64> = donations.deltas().points.iter().map(|p| p.value).collect();
+        assert_eq!(changes, [50, 30, 20, 0, 40]);
+        assert_eq!(donations.change(), 140);
+
+        let seasons = donations.resample(Period::Season).deltas();
+        assert_eq!(values(&seasons), [("20240129".to_string(), 40)]);
+    }
+
+    #[test]
+    fn test_best_per_season_and_heroes() {
+        let history = history();
+        let best = history.series(Metric::Donations).best_per_season();
+        assert_eq!(best["2024-01"].value, 200);
+        assert_eq!(best["2024-02"].value, 40);
+
+        let trophies = history.series(Metric::Trophies).best_per_season();
+        assert_eq!(
+            format_api_time(trophies["2024-01"].time),
+            "20240128T120000.000Z"
+        );
+
+        let king = history.series(Metric::Hero("Barbarian King".to_string()));
+        assert_eq!((king.first().unwrap().value, king.change()), (85, 1));
+        assert_eq!(history.series(Metric::HeroLevels).change(), 1);
+        assert!(history
+            .series(Metric::Hero("Minion Prince".to_string()))
+            .points
+            .is_empty());
+    }
+}
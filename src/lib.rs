@@ -1,29 +1,32 @@
 // Declare submodules
+pub mod analytics;
 pub mod auth;
 pub mod core;
+pub mod cwl;
+pub mod events;
+pub mod history;
 pub mod models;
+pub mod proxy;
+pub mod store;
+#[cfg(feature = "testing")]
+pub mod testing;
 pub mod utils;
+pub mod webhooks;
 
 // Declare modules in this current directory
 pub mod api;
+pub mod calendar;
 pub mod error;
+pub mod linking;
 
 #[cfg(test)]
 mod test {
     use dotenv::dotenv;
     use std::env;
 
-    use crate::auth::dev::{APIAccount, LoginResponse};
-    use crate::auth::keys::Keys;
+    use crate::auth::dev::APIAccount;
     use crate::auth::credentials::Credentials;
 
-    use crate::api::api;
-
-    use anyhow;
-
-    use reqwest;
-    use serde_json::{from_str, Value};
-
     fn get_credentials() -> Credentials {
         dotenv().ok();
 
@@ -0,0 +1,266 @@
+//! Account linking between external users (e.g. a Discord user id) and the
+//! players they own. Ownership is proven with the player API token found in
+//! the game settings, which is verified through `/players/{tag}/verifytoken`.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+use crate::core::Client;
+use crate::error::LinkError;
+use crate::models::tag::PlayerTag;
+
+/// How long a used token is remembered. Player API tokens expire after a few
+/// minutes, a token older than this would be rejected by the API anyway.
+pub const USED_TOKEN_RETENTION: Duration = Duration::from_secs(15 * 60);
+
+/// Hex encoded SHA-256 of a token, so used tokens are not kept in plaintext
+fn token_hash(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountLink {
+    pub user_id: String,
+    pub tag: PlayerTag,
+    pub linked_at: SystemTime,
+}
+
+/// Registry of verified links. The registry is serializable so it can be
+/// persisted between restarts.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LinkRegistry {
+    links: Vec<AccountLink>,
+    /// Hashes of the tokens used to link, with the time they were used
+    #[serde(default)]
+    used_token_hashes: HashMap<String, SystemTime>,
+}
+
+impl LinkRegistry {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Verify `token` for `tag` against the API and link the player to
+    /// `user_id` if the token is valid.
+    pub async fn verify_and_link(
+        &mut self,
+        client: &Client,
+        user_id: &str,
+        tag: &PlayerTag,
+        token: &str,
+    ) -> Result<&AccountLink, LinkError> {
+        self.check_link(user_id, tag, token)?;
+
+        let response = client.verify_player_token(tag.as_str(), token).await?;
+        if !response.is_ok() {
+            return Err(LinkError::InvalidToken(tag.to_string()));
+        }
+
+        self.insert(user_id, tag, token)
+    }
+
+    /// Record a link for a token that has already been verified.
+    pub fn link(
+        &mut self,
+        user_id: &str,
+        tag: &PlayerTag,
+        token: &str,
+    ) -> Result<&AccountLink, LinkError> {
+        self.check_link(user_id, tag, token)?;
+        self.insert(user_id, tag, token)
+    }
+
+    fn check_link(&self, user_id: &str, tag: &PlayerTag, token: &str) -> Result<(), LinkError> {
+        if self.used_token_hashes.contains_key(&token_hash(token)) {
+            return Err(LinkError::TokenReused);
+        }
+
+        match self.owner_of(tag) {
+            Some(link) if link.user_id != user_id => Err(LinkError::AlreadyLinked {
+                tag: tag.to_string(),
+                user_id: link.user_id.clone(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    fn insert(
+        &mut self,
+        user_id: &str,
+        tag: &PlayerTag,
+        token: &str,
+    ) -> Result<&AccountLink, LinkError> {
+        let now = SystemTime::now();
+        self.used_token_hashes.retain(|_, used_at| {
+            now.duration_since(*used_at)
+                .map_or(true, |age| age < USED_TOKEN_RETENTION)
+        });
+        self.used_token_hashes.insert(token_hash(token), now);
+
+        // Re-linking an account the user already owns refreshes the timestamp
+        let index = match self.links.iter().position(|link| &link.tag == tag) {
+            Some(index) => {
+                self.links[index].linked_at = SystemTime::now();
+                index
+            }
+            None => {
+                self.links.push(AccountLink {
+                    user_id: user_id.to_string(),
+                    tag: tag.clone(),
+                    linked_at: SystemTime::now(),
+                });
+                self.links.len() - 1
+            }
+        };
+
+        Ok(&self.links[index])
+    }
+
+    /// Remove the link between `user_id` and `tag`, returning it if it existed
+    pub fn unlink(&mut self, user_id: &str, tag: &PlayerTag) -> Option<AccountLink> {
+        let index = self
+            .links
+            .iter()
+            .position(|link| link.user_id == user_id && &link.tag == tag)?;
+        Some(self.links.remove(index))
+    }
+
+    /// Remove every link belonging to `user_id`
+    pub fn unlink_user(&mut self, user_id: &str) -> Vec<AccountLink> {
+        let (removed, kept) = self
+            .links
+            .drain(..)
+            .partition(|link| link.user_id == user_id);
+        self.links = kept;
+        removed
+    }
+
+    /// The link owning `tag`, if any
+    #[must_use]
+    pub fn owner_of(&self, tag: &PlayerTag) -> Option<&AccountLink> {
+        self.links.iter().find(|link| &link.tag == tag)
+    }
+
+    /// All players linked to `user_id`
+    pub fn links_for<'a>(&'a self, user_id: &'a str) -> impl Iterator<Item = &'a AccountLink> {
+        self.links
+            .iter()
+            .filter(move |link| link.user_id == user_id)
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.links.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.links.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{token_hash, LinkRegistry, USED_TOKEN_RETENTION};
+    use crate::core::Client;
+    use crate::error::LinkError;
+    use crate::models::tag::PlayerTag;
+
+    use serde_json::json;
+    use std::time::Duration;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn test_verify_and_link() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/players/%232PP/verifytoken"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "tag": "#2PP",
+                "token": "abc",
+                "status": "ok"
+            })))
+            .mount(&server)
+            .await;
+
+        let client = Client::new("token").with_base_url(server.uri());
+        let tag = PlayerTag::new("#2PP").unwrap();
+        let mut registry = LinkRegistry::new();
+
+        let link = registry
+            .verify_and_link(&client, "user", &tag, "abc")
+            .await
+            .unwrap();
+        assert_eq!(link.user_id, "user");
+
+        // Tokens are single use
+        let reused = registry.verify_and_link(&client, "user", &tag, "abc").await;
+        assert!(matches!(reused, Err(LinkError::TokenReused)));
+
+        // Only a hash of the token is kept
+        let json = serde_json::to_string(&registry).unwrap();
+        assert!(!json.contains("\"abc\""));
+        assert!(json.contains(&token_hash("abc")));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_token() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/players/%232PP/verifytoken"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "tag": "#2PP",
+                "token": "abc",
+                "status": "invalid"
+            })))
+            .mount(&server)
+            .await;
+
+        let client = Client::new("token").with_base_url(server.uri());
+        let tag = PlayerTag::new("#2PP").unwrap();
+        let mut registry = LinkRegistry::new();
+
+        let result = registry.verify_and_link(&client, "user", &tag, "abc").await;
+        assert!(matches!(result, Err(LinkError::InvalidToken(_))));
+        assert!(registry.is_empty());
+    }
+
+    #[test]
+    fn test_link_and_unlink() {
+        let tag = PlayerTag::new("#2PP").unwrap();
+        let mut registry = LinkRegistry::new();
+
+        registry.link("user", &tag, "first").unwrap();
+        let taken = registry.link("other", &tag, "second");
+        assert!(matches!(taken, Err(LinkError::AlreadyLinked { .. })));
+
+        assert!(registry.unlink("user", &tag).is_some());
+        assert!(registry.owner_of(&tag).is_none());
+        registry.link("other", &tag, "third").unwrap();
+        assert_eq!(registry.links_for("other").count(), 1);
+    }
+
+    #[test]
+    fn test_expired_tokens_pruned() {
+        let tag = PlayerTag::new("#2PP").unwrap();
+        let mut registry = LinkRegistry::new();
+        registry.link("user", &tag, "first").unwrap();
+
+        // Pretend the first token was used long enough ago to have expired
+        let used_at = registry
+            .used_token_hashes
+            .get_mut(&token_hash("first"))
+            .unwrap();
+        *used_at -= USED_TOKEN_RETENTION + Duration::from_secs(1);
+
+        registry.link("user", &tag, "second").unwrap();
+        assert_eq!(registry.used_token_hashes.len(), 1);
+        assert!(registry
+            .used_token_hashes
+            .contains_key(&token_hash("second")));
+    }
+}
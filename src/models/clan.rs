@@ -1,18 +1,22 @@
+use serde::de::{self, Deserializer};
+use serde::ser::{SerializeStruct, Serializer};
 use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime};
 use super::badge_urls::BadgeUrls;
-use super::league::League;
+use super::league::{BuilderBaseLeague, CapitalLeague, League, WarLeague};
 use super::player::PlayerHouse;
 use super::language::Language;
 use super::labels::ClanLabels;
 use super::location::Location;
 use super::clan_capital::ClanCapital;
 use super::enums::clan::*;
+use super::timestamp::Timestamp;
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Clan {
-    pub war_league: Option<League>,
-    pub capital_league: Option<League>,
+    pub war_league: Option<WarLeague>,
+    pub capital_league: Option<CapitalLeague>,
     pub member_list: Option<ClanMemberList>,
     pub tag: String,
     pub chat_language: Option<Language>,
@@ -32,7 +36,7 @@ pub struct Clan {
     pub war_frequency: Option<WarFrequency>,
     pub labels: Option<Vec<ClanLabels>>,
     pub name: String,
-    pub location: Location,
+    pub location: Option<Location>,
     pub r#type: EntryType,
     pub members: u8,
     pub description: String,
@@ -47,7 +51,7 @@ pub struct ClanList(pub Vec<Clan>);
 #[serde(rename_all = "camelCase")]
 pub struct ClanMember {
     pub league: League,
-    pub builder_base_league: League,
+    pub builder_base_league: Option<BuilderBaseLeague>,
     pub tag: String,
     pub name: String,
     pub role: Role,
@@ -59,7 +63,7 @@ pub struct ClanMember {
     pub donations_received: u32,
     pub trophies: u16,
     pub builder_base_trophies: u16,
-    pub player_house: PlayerHouse,
+    pub player_house: Option<PlayerHouse>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -68,7 +72,8 @@ pub struct ClanMemberList(pub Vec<ClanMember>);
 // ----- Clan War Leagues -----
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ClanWarLeagueGroup {
-    pub tag: String,
+    // Not always included in the response
+    pub tag: Option<String>,
     pub state: WarLeagueState,
     pub season: String,
     pub clans: ClanWarLeagueClanList,
@@ -119,21 +124,31 @@ pub struct ClanWarLogEntry {
     pub opponent: WarClan,
     pub team_size: u8,
     pub attacks_per_member: u8,
+    #[serde(default)]
     pub battle_modifier: BattleModifier,
-    pub end_time: String,
-    pub result: WarResult,
+    pub end_time: Timestamp,
+    // Clan war league entries have no result
+    pub result: Option<WarResult>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct WarClan {
     pub destruction_percentage: f32,
+    // The opponent of a clan war league war log entry has no tag or name
+    #[serde(default)]
     pub tag: String,
+    #[serde(default)]
     pub name: String,
     pub badge_urls: BadgeUrls,
     pub clan_level: u8,
+    #[serde(default)]
     pub attacks: u8,
     pub stars: u16,
-    pub exp_earned: u16,
+    // Only present in war log entries
+    pub exp_earned: Option<u16>,
+    // Not present in war log entries
+    #[serde(default)]
     pub members: ClanWarMemberList,
 }
 
@@ -145,11 +160,14 @@ pub struct ClanWarMember {
     pub map_position: u8,
     pub townhall_level: u8,
     pub opponent_attacks: u8,
-    pub best_opponent_attack: ClanWarAttack,
+    // Not present until the member has been attacked
+    pub best_opponent_attack: Option<ClanWarAttack>,
+    // Not present until the member has attacked
+    #[serde(default)]
     pub attacks: ClanWarAttackList,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct ClanWarMemberList(pub Vec<ClanWarMember>);
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -163,7 +181,7 @@ pub struct ClanWarAttack {
     pub duration: u16,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct ClanWarAttackList(pub Vec<ClanWarAttack>);
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -172,10 +190,337 @@ pub struct ClanWar {
     pub clan: WarClan,
     pub opponent: WarClan,
     pub team_size: u8,
+    // Clan war league wars leave this out, they only allow one attack
+    #[serde(default = "default_attacks_per_member")]
     pub attacks_per_member: u8,
+    #[serde(default)]
     pub battle_modifier: BattleModifier,
-    pub start_time: String,
+    pub start_time: Timestamp,
     pub state: WarState,
-    pub end_time: String,
-    pub preparation_start_time: String,
+    pub end_time: Timestamp,
+    pub preparation_start_time: Timestamp,
+    // Only present in clan war league wars
+    pub war_start_time: Option<Timestamp>,
+}
+
+fn default_attacks_per_member() -> u8 {
+    1
+}
+
+impl ClanWar {
+    /// Returns the war from the perspective of `clantag`, swapping `clan` and
+    /// `opponent` if needed. Returns `None` if the clan is not part of the war.
+    #[must_use]
+    pub fn for_clan(mut self, clantag: &str) -> Option<Self> {
+        if self.clan.tag == clantag {
+            Some(self)
+        } else if self.opponent.tag == clantag {
+            std::mem::swap(&mut self.clan, &mut self.opponent);
+            Some(self)
+        } else {
+            None
+        }
+    }
+
+    /// Whether `clantag` is one of the two clans in this war
+    #[must_use]
+    pub fn involves(&self, clantag: &str) -> bool {
+        self.clan.tag == clantag || self.opponent.tag == clantag
+    }
+
+    /// Time left until battle day ends, zero once the war has ended
+    #[must_use]
+    pub fn remaining(&self, now: SystemTime) -> Duration {
+        self.end_time.time_until(now)
+    }
+
+    /// Time left until battle day starts, zero once it has started
+    #[must_use]
+    pub fn time_until_start(&self, now: SystemTime) -> Duration {
+        self.start_time.time_until(now)
+    }
+
+    /// Result for `clan` as the war stands, decided by stars and then by
+    /// destruction
+    #[must_use]
+    pub fn result(&self) -> WarResult {
+        match self.clan.stars.cmp(&self.opponent.stars).then(
+            self.clan
+                .destruction_percentage
+                .total_cmp(&self.opponent.destruction_percentage),
+        ) {
+            std::cmp::Ordering::Greater => WarResult::Win,
+            std::cmp::Ordering::Less => WarResult::Lose,
+            std::cmp::Ordering::Equal => WarResult::Tie,
+        }
+    }
+}
+
+/// A clan as seen on preparation day: nobody has attacked yet, so only the
+/// line-up is known
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WarLineup {
+    pub tag: String,
+    pub name: String,
+    pub badge_urls: BadgeUrls,
+    pub clan_level: u8,
+    #[serde(default)]
+    pub members: Vec<WarLineupMember>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WarLineupMember {
+    pub tag: String,
+    pub name: String,
+    pub map_position: u8,
+    pub townhall_level: u8,
+}
+
+/// A war on preparation day
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WarPreparation {
+    pub clan: WarLineup,
+    pub opponent: WarLineup,
+    pub team_size: u8,
+    #[serde(default = "default_attacks_per_member")]
+    pub attacks_per_member: u8,
+    #[serde(default)]
+    pub battle_modifier: BattleModifier,
+    pub preparation_start_time: Timestamp,
+    pub start_time: Timestamp,
+    pub end_time: Timestamp,
+    // Only present in clan war league wars
+    pub war_start_time: Option<Timestamp>,
+}
+
+/// The clan's current war as returned by `/clans/{tag}/currentwar`. The
+/// response shape depends on the war state, so each variant only carries the
+/// data that is valid in that state.
+#[derive(Debug, Clone)]
+pub enum CurrentWar {
+    /// The clan is not in a war, the response only contains the state
+    NotInWar,
+    /// The clan is searching for an opponent
+    InMatchmaking,
+    /// The search has finished and the war is being set up
+    EnterWar,
+    /// An opponent was found, preparation day has not started yet
+    Matched,
+    /// Preparation day, only the line-ups are known
+    Preparation(WarPreparation),
+    /// Battle day
+    InWar(ClanWar),
+    /// The war has ended but a new war has not been searched for yet
+    Ended(ClanWar),
+    /// The clan's war log is private so the current war cannot be viewed
+    PrivateWarLog,
+    /// The clan does not exist
+    ClanNotFound,
+}
+
+impl CurrentWar {
+    /// The war, if it is on battle day or has ended and is visible
+    #[must_use]
+    pub fn war(&self) -> Option<&ClanWar> {
+        match self {
+            Self::InWar(war) | Self::Ended(war) => Some(war),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn into_war(self) -> Option<ClanWar> {
+        match self {
+            Self::InWar(war) | Self::Ended(war) => Some(war),
+            _ => None,
+        }
+    }
+
+    /// The war, if it is on preparation day
+    #[must_use]
+    pub fn preparation(&self) -> Option<&WarPreparation> {
+        match self {
+            Self::Preparation(war) => Some(war),
+            _ => None,
+        }
+    }
+
+    /// When preparation day started and the opponent's tag, which together
+    /// identify a war across states
+    #[must_use]
+    pub fn war_key(&self) -> Option<(Timestamp, &str)> {
+        match self {
+            Self::Preparation(war) => Some((war.preparation_start_time, &war.opponent.tag)),
+            Self::InWar(war) | Self::Ended(war) => {
+                Some((war.preparation_start_time, &war.opponent.tag))
+            }
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn state(&self) -> WarState {
+        match self {
+            Self::NotInWar => WarState::NotInWar,
+            Self::InMatchmaking => WarState::InMatchmaking,
+            Self::EnterWar => WarState::EnterWar,
+            Self::Matched => WarState::Matched,
+            Self::Preparation(_) => WarState::Preparation,
+            Self::InWar(_) => WarState::InWar,
+            Self::Ended(_) => WarState::Ended,
+            Self::PrivateWarLog => WarState::AccessDenied,
+            Self::ClanNotFound => WarState::ClanNotFound,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for CurrentWar {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = serde_json::Value::deserialize(deserializer)?;
+        let state = value
+            .get("state")
+            .cloned()
+            .ok_or_else(|| de::Error::missing_field("state"))?;
+        let state: WarState = serde_json::from_value(state).map_err(de::Error::custom)?;
+
+        let war = || serde_json::from_value::<ClanWar>(value.clone()).map_err(de::Error::custom);
+
+        match state {
+            WarState::NotInWar => Ok(Self::NotInWar),
+            WarState::InMatchmaking => Ok(Self::InMatchmaking),
+            WarState::EnterWar => Ok(Self::EnterWar),
+            WarState::Matched => Ok(Self::Matched),
+            WarState::Preparation => serde_json::from_value(value.clone())
+                .map(Self::Preparation)
+                .map_err(de::Error::custom),
+            WarState::InWar | WarState::War => Ok(Self::InWar(war()?)),
+            WarState::Ended => Ok(Self::Ended(war()?)),
+            WarState::AccessDenied => Ok(Self::PrivateWarLog),
+            WarState::ClanNotFound => Ok(Self::ClanNotFound),
+        }
+    }
+}
+
+/// A preparation day war with its state, the shape the API returns it in
+#[derive(Serialize)]
+struct StatedPreparation<'a> {
+    state: WarState,
+    #[serde(flatten)]
+    war: &'a WarPreparation,
+}
+
+impl Serialize for CurrentWar {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::InWar(war) | Self::Ended(war) => war.serialize(serializer),
+            Self::Preparation(war) => StatedPreparation {
+                state: self.state(),
+                war,
+            }
+            .serialize(serializer),
+            _ => {
+                let mut state = serializer.serialize_struct("CurrentWar", 1)?;
+                state.serialize_field("state", &self.state())?;
+                state.end()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Clan, CurrentWar};
+    use crate::models::enums::clan::WarState;
+    use serde_json::json;
+
+    fn clan_json() -> serde_json::Value {
+        serde_json::from_str(include_str!("../../tests/fixtures/clan.json")).unwrap()
+    }
+
+    #[test]
+    fn test_clan_optional_fields() {
+        let mut clan = clan_json();
+        clan["location"] = json!({
+            "id": 32000006,
+            "name": "International",
+            "isCountry": false
+        });
+        let member = clan["memberList"][0].as_object_mut().unwrap();
+        member.remove("builderBaseLeague");
+        member.remove("playerHouse");
+
+        let clan: Clan = serde_json::from_value(clan).unwrap();
+        let location = clan.location.unwrap();
+        assert!(location.country_code.is_none());
+        assert!(location.localized_name.is_none());
+        // Language ids don't fit in a u16
+        assert_eq!(clan.chat_language.unwrap().id, 75_000_000);
+        let member = &clan.member_list.unwrap().0[0];
+        assert!(member.builder_base_league.is_none());
+        assert!(member.player_house.is_none());
+
+        let mut clan = clan_json();
+        clan.as_object_mut().unwrap().remove("location");
+        let clan: Clan = serde_json::from_value(clan).unwrap();
+        assert!(clan.location.is_none());
+    }
+
+    #[test]
+    fn test_current_war_not_in_war() {
+        let war: CurrentWar = serde_json::from_str(r#"{"state": "notInWar"}"#).unwrap();
+        assert!(matches!(war, CurrentWar::NotInWar));
+        assert!(war.war().is_none());
+    }
+
+    #[test]
+    fn test_current_war_in_war() {
+        let war: CurrentWar =
+            serde_json::from_str(include_str!("../../tests/fixtures/current_war.json")).unwrap();
+        assert!(matches!(war.state(), WarState::InWar));
+
+        let war = war.into_war().unwrap();
+        assert_eq!(war.clan.members.0.len(), 5);
+        assert!(war.clan.members.0[4].best_opponent_attack.is_none());
+    }
+
+    #[test]
+    fn test_current_war_round_trip() {
+        let war: CurrentWar = serde_json::from_str(r#"{"state": "notInWar"}"#).unwrap();
+        let json = serde_json::to_string(&war).unwrap();
+        let war: CurrentWar = serde_json::from_str(&json).unwrap();
+        assert!(matches!(war, CurrentWar::NotInWar));
+    }
+
+    #[test]
+    fn test_current_war_states() {
+        for (state, expected) in [
+            ("IN_MATCHMAKING", WarState::InMatchmaking),
+            ("ENTER_WAR", WarState::EnterWar),
+            ("MATCHED", WarState::Matched),
+            ("CLAN_NOT_FOUND", WarState::ClanNotFound),
+            ("accessDenied", WarState::AccessDenied),
+        ] {
+            let json = format!(r#"{{"state": "{state}"}}"#);
+            let war: CurrentWar = serde_json::from_str(&json).unwrap();
+            assert_eq!(war.state(), expected);
+            assert!(war.war_key().is_none());
+        }
+    }
+
+    #[test]
+    fn test_current_war_preparation() {
+        let json = include_str!("../../tests/fixtures/current_war.json")
+            .replacen(r#""inWar""#, r#""preparation""#, 1);
+        let war: CurrentWar = serde_json::from_str(&json).unwrap();
+        let preparation = war.preparation().unwrap();
+        assert_eq!(preparation.clan.members.len(), 5);
+        assert!(war.war().is_none());
+
+        let json = serde_json::to_string(&war).unwrap();
+        let war: CurrentWar = serde_json::from_str(&json).unwrap();
+        assert_eq!(war.state(), WarState::Preparation);
+    }
 }
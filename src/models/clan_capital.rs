@@ -1,6 +1,9 @@
 use serde::{Deserialize, Serialize};
 
+use std::time::{Duration, SystemTime};
+
 use crate::models::badge_urls::BadgeUrls;
+use crate::models::timestamp::Timestamp;
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -13,7 +16,7 @@ pub struct ClanCapital {
 #[serde(rename_all = "camelCase")]
 pub struct ClanDistrictData {
     pub name: String, // JsonLocalizedName
-    pub id: u8, // Unsure
+    pub id: u32,
     pub district_hall_level: u8,
 }
 
@@ -23,17 +26,33 @@ pub struct ClanCapitalRaidSeason {
     pub attack_log: ClanCapitalRaidSeasonAttackLog,
     pub defense_log: ClanCapitalRaidSeasonDefenseLog,
     pub state: String,
-    pub start_time: String,
-    pub end_time: String,
+    pub start_time: Timestamp,
+    pub end_time: Timestamp,
     pub capital_total_loot: u32,
     pub raids_completed: u16,
     pub total_attacks: u16,
     pub enemy_districts_destroyed: u16,
     pub offensive_reward: u16,
     pub defensive_reward: u16,
+    // Not present until a member has attacked
+    #[serde(default)]
     pub members: ClanCapitalRaidSeasonMemberList,
 }
 
+impl ClanCapitalRaidSeason {
+    /// Whether the raid weekend is still running at `now`
+    #[must_use]
+    pub fn is_ongoing(&self, now: SystemTime) -> bool {
+        self.start_time.is_past(now) && !self.end_time.is_past(now)
+    }
+
+    /// Time left in the raid weekend, zero once it has ended
+    #[must_use]
+    pub fn remaining(&self, now: SystemTime) -> Duration {
+        self.end_time.time_until(now)
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ClanCapitalRaidSeasons(pub Vec<ClanCapitalRaidSeason>);
 
@@ -74,7 +93,7 @@ pub struct ClanCapitalRaidSeasonMember {
     pub capital_resources_looted: u32,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct ClanCapitalRaidSeasonMemberList(pub Vec<ClanCapitalRaidSeasonMember>);
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -86,19 +105,37 @@ pub struct ClanCapitalRaidSeasonClanInfo {
     pub badge_urls: BadgeUrls,
 }
 
+/// District id of the capital peak, its district hall level is the capital
+/// hall level
+pub const CAPITAL_PEAK_ID: u32 = 70_000_000;
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ClanCapitalRaidSeasonDistrict {
     pub stars: u8,
     pub name: String, // JsonLocalizedName
-    pub id: u16,
+    pub id: u32,
     pub destruction_percent: u8,
     pub attack_count: u8,
-    pub total_looted: u16,
+    pub total_looted: u32,
+    // Not present until the district has been attacked
+    #[serde(default)]
     pub attacks: ClanCapitalRaidSeasonAttackList,
     pub district_hall_level: u8,
 }
 
+impl ClanCapitalRaidSeasonDistrict {
+    #[must_use]
+    pub fn is_capital_peak(&self) -> bool {
+        self.id == CAPITAL_PEAK_ID
+    }
+
+    #[must_use]
+    pub fn is_destroyed(&self) -> bool {
+        self.destruction_percent == 100
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ClanCapitalRaidSeasonDistrictList(pub Vec<ClanCapitalRaidSeasonDistrict>);
 
@@ -110,7 +147,7 @@ pub struct ClanCapitalRaidSeasonAttack {
     pub stars: u8,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct ClanCapitalRaidSeasonAttackList(pub Vec<ClanCapitalRaidSeasonAttack>);
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+/// Ordered from the lowest rank to the highest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
 pub enum Role {
     #[serde(rename = "notMember")]
     NotMember,
@@ -21,59 +22,85 @@ pub enum Role {
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum WarLeagueState {
+    #[serde(alias = "groupNotFound")]
     GroupNotFound,
+    #[serde(alias = "notInWar")]
     NotInWar,
+    #[serde(alias = "preparation")]
     Preparation,
+    #[serde(alias = "inWar")]
     War,
+    #[serde(alias = "ended")]
     Ended,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum WarState {
     ClanNotFound,
+    #[serde(alias = "accessDenied")]
     AccessDenied,
+    #[serde(alias = "notInWar")]
     NotInWar,
     InMatchmaking,
     EnterWar,
     Matched,
+    #[serde(alias = "preparation")]
     Preparation,
     War,
+    #[serde(alias = "inWar")]
     InWar,
+    #[serde(alias = "warEnded")]
     Ended,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum WarFrequency {
+    #[serde(alias = "unknown")]
     Unknown,
+    #[serde(alias = "always")]
     Always,
+    #[serde(alias = "moreThanOncePerWeek")]
     MoreThanOncePerWeek,
+    #[serde(alias = "oncePerWeek")]
     OncePerWeek,
+    #[serde(alias = "lessThanOncePerWeek")]
     LessThanOncePerWeek,
+    #[serde(alias = "never")]
     Never,
+    #[serde(alias = "any")]
     Any,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum EntryType {
+    #[serde(alias = "open")]
     Open,
+    #[serde(alias = "inviteOnly")]
     InviteOnly,
+    #[serde(alias = "closed")]
     Closed,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize, Serialize)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum BattleModifier {
+    #[default]
+    #[serde(alias = "none")]
     None,
+    #[serde(alias = "hardMode")]
     HardMode,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum WarResult {
+    #[serde(alias = "lose")]
     Lose,
+    #[serde(alias = "win")]
     Win,
+    #[serde(alias = "tie")]
     Tie,
 }
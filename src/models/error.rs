@@ -1,4 +1,5 @@
-/// Errors returned from the Clash of Clans API
+//! Errors returned from the Clash of Clans API
+
 use serde::{Serialize, Deserialize};
 
 /// I don't know when this error is returned but it is listed in the supercell
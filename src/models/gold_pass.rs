@@ -1,8 +1,24 @@
 use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime};
+
+use super::timestamp::Timestamp;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GoldPassSeason {
-    pub start_time: String,
-    pub end_time: String,
+    pub start_time: Timestamp,
+    pub end_time: Timestamp,
+}
+
+impl GoldPassSeason {
+    #[must_use]
+    pub fn is_ongoing(&self, now: SystemTime) -> bool {
+        self.start_time.is_past(now) && !self.end_time.is_past(now)
+    }
+
+    /// Time left in the season, zero once it has ended
+    #[must_use]
+    pub fn remaining(&self, now: SystemTime) -> Duration {
+        self.end_time.time_until(now)
+    }
 }
@@ -3,7 +3,7 @@ use serde_repr::{Deserialize_repr, Serialize_repr};
 
 use super::icon_urls;
 
-#[derive(Debug, Serialize_repr, Deserialize_repr, Clone, Copy)]
+#[derive(Debug, Serialize_repr, Deserialize_repr, Clone, Copy, PartialEq, Eq, Hash)]
 #[repr(u32)]
 pub enum PlayerLabelId {
     ClanWars = 57_000_000,
@@ -27,7 +27,7 @@ pub enum PlayerLabelId {
     ClanCapital = 57_000_018,
 }
 
-#[derive(Debug, Serialize_repr, Deserialize_repr, Clone, Copy)]
+#[derive(Debug, Serialize_repr, Deserialize_repr, Clone, Copy, PartialEq, Eq, Hash)]
 #[repr(u32)]
 pub enum ClanLabelId {
     ClanWars = 56_000_000,
@@ -59,7 +59,7 @@ pub enum LeagueId {
     Legend = 29_000_022,
 }
 
-#[derive(Debug, Clone, Deserialize_repr, Serialize_repr, Copy)]
+#[derive(Debug, Clone, Deserialize_repr, Serialize_repr, Copy, PartialEq, Eq, Hash)]
 #[repr(u32)]
 pub enum WarLeagueId {
     Unranked = 48_000_000,
@@ -83,6 +83,58 @@ pub enum WarLeagueId {
     ChampI = 48_000_018,
 }
 
+impl WarLeagueId {
+    /// Number of clans in a clan war league group promoted at the end of the
+    /// season
+    #[must_use]
+    pub fn promotion_spots(&self) -> usize {
+        match self {
+            Self::Unranked | Self::ChampI => 0,
+            Self::BronzeIII | Self::BronzeII | Self::BronzeI => 3,
+            Self::SilverIII
+            | Self::SilverII
+            | Self::SilverI
+            | Self::GoldIII
+            | Self::GoldII
+            | Self::GoldI
+            | Self::CrystalIII => 2,
+            Self::CrystalII
+            | Self::CrystalI
+            | Self::MasterIII
+            | Self::MasterII
+            | Self::MasterI
+            | Self::ChampIII
+            | Self::ChampII => 1,
+        }
+    }
+
+    /// Number of clans in a clan war league group demoted at the end of the
+    /// season
+    #[must_use]
+    pub fn demotion_spots(&self) -> usize {
+        match self {
+            Self::Unranked | Self::BronzeIII => 0,
+            Self::BronzeII
+            | Self::BronzeI
+            | Self::SilverIII
+            | Self::SilverII
+            | Self::SilverI
+            | Self::GoldIII => 1,
+            Self::GoldII
+            | Self::GoldI
+            | Self::CrystalIII
+            | Self::CrystalII
+            | Self::CrystalI
+            | Self::MasterIII
+            | Self::MasterII
+            | Self::MasterI
+            | Self::ChampIII
+            | Self::ChampII => 2,
+            Self::ChampI => 3,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Deserialize_repr, Serialize_repr, Copy)]
 #[repr(u32)]
 pub enum BuilderLeagueId {
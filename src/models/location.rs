@@ -3,11 +3,12 @@ use serde::{Serialize, Deserialize};
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Location {
-    pub localized_name: String,
+    pub localized_name: Option<String>,
     pub id: u32,
     pub name: String,
     pub is_country: bool,
-    pub country_code: String
+    // Only present for countries
+    pub country_code: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
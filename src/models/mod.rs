@@ -14,3 +14,5 @@ pub mod player;
 pub mod ranking;
 pub mod role;
 pub mod status;
+pub mod tag;
+pub mod timestamp;
@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 
+use super::league::LeagueId;
 use super::{badge_urls, icon_urls, labels};
 use crate::models::enums::player::*;
 use crate::models::enums::clan::Role;
@@ -55,6 +56,13 @@ pub struct PlayerLeague {
     pub icon_urls: icon_urls::LeagueIconUrls,
 }
 
+impl PlayerLeague {
+    #[must_use]
+    pub fn is_legend(&self) -> bool {
+        self.id == LeagueId::Legend as u32
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct BuilderBaseLeague {
     pub id: u32,
@@ -73,11 +81,11 @@ impl WarPreference {
     }
 }
 
-impl ToString for WarPreference {
-    fn to_string(&self) -> String {
+impl std::fmt::Display for WarPreference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::Out => "out".to_string(),
-            Self::In => "in".to_string(),
+            Self::Out => write!(f, "out"),
+            Self::In => write!(f, "in"),
         }
     }
 }
@@ -181,216 +189,12 @@ pub struct VerifyTokenRequest {
 pub struct VerifyTokenResponse {
     pub tag: String,
     pub token: String,
-    pub status: String,
+    pub status: VerifyTokenStatus,
 }
 
-impl Player {
-    const ALL_UNITS: [&'static str; 74] = [
-        "Barbarian",
-        "Archer",
-        "Goblin",
-        "Giant",
-        "Wall Breaker",
-        "Balloon",
-        "Wizard",
-        "Healer",
-        "Dragon",
-        "P.E.K.K.A",
-        "Minion",
-        "Hog Rider",
-        "Valkyrie",
-        "Golem",
-        "Witch",
-        "Lava Hound",
-        "Bowler",
-        "Baby Dragon",
-        "Miner",
-        "Super Barbarian",
-        "Super Archer",
-        "Super Wall Breaker",
-        "Super Giant",
-        "Raged Barbarian",
-        "Sneaky Archer",
-        "Beta Minion",
-        "Boxer Giant",
-        "Bomber",
-        "Power P.E.K.K.A",
-        "Cannon Cart",
-        "Drop Ship",
-        "Baby Dragon", // builder base
-        "Night Witch",
-        "Wall Wrecker",
-        "Battle Blimp",
-        "Yeti",
-        "Sneaky Goblin",
-        "Super Miner",
-        "Rocket Balloon",
-        "Ice Golem",
-        "Electro Dragon",
-        "Stone Slammer",
-        "Inferno Dragon",
-        "Super Valkyrie",
-        "Dragon Rider",
-        "Super Witch",
-        "Hog Glider",
-        "Siege Barracks",
-        "Ice Hound",
-        "Super Bowler",
-        "Super Dragon",
-        "Headhunter",
-        "Super Wizard",
-        "Super Minion",
-        "Log Launcher",
-        "Flame Flinger",
-        "Battle Drill",
-        "Electro Titan",
-        "Apprentice Warden",
-        "Super Hog Rider",
-        "Electrofire Wizard",
-        "Root Rider",
-        "Druid",
-        "Thrower",
-        "L.A.S.S.I",
-        "Mighty Yak",
-        "Electro Owl",
-        "Unicorn",
-        "Phoenix",
-        "Poison Lizard",
-        "Diggy",
-        "Frosty",
-        "Spirit Fox",
-        "Angry Jelly",
-    ];
-
-    /*
-    const HOME_TROOP_ORDER: [&'static str; 10] = [
-    ];
-
-    const SUPER_TROOP_ORDER: [&'static str; 10] = [
-    ];
-
-    const BUILDER_TROOP_ORDER: [&'static str; 10] = [
-    ];
-
-    const SIEGE_MACHINE_ORDER: [&'static str; 10] = [
-    ];
-    */
-
-    const HERO_ORDER: [&'static str; 7] = [
-        "Barbarian King",
-        "Archer Queen",
-        "Grand Warden",
-        "Battle Machine",
-        "Royal Champion",
-        "Battle Copter",
-        "Minion Prince",
-    ];
-
-    const HERO_EQ_ORDER: [&'static str; 26] = [
-        "Giant Gauntlet",
-        "Rocket Spear",
-        "Spiky Ball",
-        "Frozen Arrow",
-        "Fireball",
-        "Magic Mirror",
-        "Electro Boots",
-        "Lavaloon Puppet",
-        "Barbarian Puppet",
-        "Rage Vial",
-        "Archer Puppet",
-        "Invisibility Vial",
-        "Eternal Tome",
-        "Life Gem",
-        "Seeking Shield",
-        "Royal Gem",
-        "Earthquake Boots",
-        "Hog Rider Puppet",
-        "Vampstache",
-        "Haste Vial",
-        "Giant Arrow",
-        "Healer Puppet",
-        "Rage Gem",
-        "Healing Tome",
-        "Henchmen Puppet",
-        "Dark Orb",
-    ];
-
-    const SPELL_ORDER: [&'static str; 15] = [
-        "Lightning Spell",
-        "Healing Spell",
-        "Rage Spell",
-        "Jump Spell",
-        "Freeze Spell",
-        "Poison Spell",
-        "Earthquake Spell",
-        "Haste Spell",
-        "Clone Spell",
-        "Skeleton Spell",
-        "Bat Spell",
-        "Invisibility Spell",
-        "Recall Spell",
-        "Overgrowth Spell",
-        "Revive Spell",
-    ];
-
-    /*
-    const PET_ORDER: &'static str; 10] = [
-    ];
-
-    const CLAN_CAPITAL_TROOP_ORDER: &'static str; 10] = [
-    ];
-
-    const CLAN_CAPITAL_SPELL_ORDER: &'static str; 10] = [
-    ];
-    */
-
-    const ACHIEVEMENT_ORDER: [&'static str; 47] = [
-        "Bigger Coffers",
-        "Get those Goblins!",
-        "Bigger & Better",
-        "Nice and Tidy",
-        "Discover New Troops",
-        "Gold Grab",
-        "Elixir Escapade",
-        "Sweet Victory!",
-        "Empire Builder",
-        "Wall Buster",
-        "Humiliator",
-        "Union Buster",
-        "Conqueror",
-        "Unbreakable",
-        "Friend in Need",
-        "Mortar Mauler",
-        "Heroic Heist",
-        "League All-Star",
-        "X-Bow Exterminator",
-        "Firefighter",
-        "War Hero",
-        "Clan War Wealth",
-        "Anti-Artillery",
-        "Sharing is caring",
-        "Keep Your Account Safe!", // connect to social network
-        "Master Engineering",
-        "Next Generation Model",
-        "Un-Build It",
-        "Champion Builder",
-        "High Gear",
-        "Hidden Treasures",
-        "Games Champion",
-        "Dragon Slayer",
-        "War League Legend",
-        "Keep Your Account Safe!", // connect to supercell id
-        "Well Seasoned",
-        "Shattered and Scattered",
-        "Not So Easy This Time",
-        "Bust This!",
-        "Superb Work",
-        "Siege Sharer",
-        "Aggressive Capitalism",
-        "Most Valuable Clanmate",
-        "Counterspell",
-        "Monolith Masher",
-        "Ungrateful Child",
-        "Supercharger",
-    ];
+impl VerifyTokenResponse {
+    #[must_use]
+    pub fn is_ok(&self) -> bool {
+        self.status == VerifyTokenStatus::Ok
+    }
 }
@@ -0,0 +1,95 @@
+//! Validated player tags. Tags are normalized on construction, so two tags
+//! for the same player compare equal.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::APIError;
+use crate::utils::format_tag;
+
+/// Characters that can appear in a player or clan tag
+const TAG_CHARACTERS: &str = "0289PYLQGRJCUV";
+
+/// A normalized player tag such as `#2PP`. Tags are upper-cased, always
+/// prefixed with '#' and have the letter 'O' replaced with '0', matching how
+/// the game treats them.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct PlayerTag(String);
+
+impl PlayerTag {
+    pub fn new(tag: &str) -> Result<Self, APIError> {
+        let body: String = tag
+            .trim()
+            .trim_start_matches('#')
+            .to_uppercase()
+            .replace('O', "0");
+
+        if body.is_empty() || !body.chars().all(|c| TAG_CHARACTERS.contains(c)) {
+            return Err(APIError::InvalidTag(tag.to_string()));
+        }
+
+        Ok(Self(format!("#{}", body)))
+    }
+
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The tag url encoded for use in an endpoint, i.e. '#' -> '%23'
+    #[must_use]
+    pub fn encoded(&self) -> String {
+        format_tag(&self.0)
+    }
+}
+
+impl FromStr for PlayerTag {
+    type Err = APIError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s)
+    }
+}
+
+impl TryFrom<String> for PlayerTag {
+    type Error = APIError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::new(&value)
+    }
+}
+
+impl From<PlayerTag> for String {
+    fn from(tag: PlayerTag) -> Self {
+        tag.0
+    }
+}
+
+impl fmt::Display for PlayerTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::PlayerTag;
+    use crate::utils::normalize_tag;
+
+    #[test]
+    fn test_normalizes_tag() {
+        let tag = PlayerTag::new(" 2ppoq ").unwrap();
+        assert_eq!(tag.as_str(), "#2PP0Q");
+        assert_eq!(tag.encoded(), "%232PP0Q");
+        assert_eq!(normalize_tag(" 2ppoq "), tag.as_str());
+    }
+
+    #[test]
+    fn test_rejects_invalid_tag() {
+        assert!(PlayerTag::new("#").is_err());
+        assert!(PlayerTag::new("#ABC").is_err());
+        assert_eq!(normalize_tag(" #ABC "), "#ABC");
+    }
+}
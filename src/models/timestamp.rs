@@ -0,0 +1,208 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::error::APIError;
+use crate::utils::time::{format_api_time, parse_api_time, time_until};
+
+/// A point in time as returned by the API, e.g. `20240101T000000.000Z`. The
+/// API uses millisecond precision, so formatting a parsed timestamp gives back
+/// the original string. Timestamps made from finer times are truncated to
+/// whole milliseconds, so they too are equal after a round trip.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Timestamp(SystemTime);
+
+impl Timestamp {
+    pub fn parse(value: &str) -> Result<Self, APIError> {
+        parse_api_time(value)
+            .map(Self)
+            .ok_or_else(|| APIError::InvalidTimestamp(value.to_string()))
+    }
+
+    #[must_use]
+    pub fn now() -> Self {
+        Self::from(SystemTime::now())
+    }
+
+    #[must_use]
+    pub fn as_system_time(&self) -> SystemTime {
+        self.0
+    }
+
+    /// Duration from `now` until this timestamp, zero if it has passed
+    #[must_use]
+    pub fn time_until(&self, now: SystemTime) -> Duration {
+        time_until(self.0, now)
+    }
+
+    /// Duration from this timestamp until `now`, zero if it is still to come
+    #[must_use]
+    pub fn time_since(&self, now: SystemTime) -> Duration {
+        time_until(now, self.0)
+    }
+
+    #[must_use]
+    pub fn is_past(&self, now: SystemTime) -> bool {
+        self.0 <= now
+    }
+}
+
+/// `time` rounded down to a whole millisecond
+fn truncate_to_millis(time: SystemTime) -> SystemTime {
+    const NANOS_PER_MILLI: u128 = 1_000_000;
+
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(since) => UNIX_EPOCH + Duration::from_millis(since.as_millis() as u64),
+        Err(before) => {
+            let millis = before.duration().as_nanos().div_ceil(NANOS_PER_MILLI);
+            UNIX_EPOCH - Duration::from_millis(millis as u64)
+        }
+    }
+}
+
+impl From<SystemTime> for Timestamp {
+    fn from(time: SystemTime) -> Self {
+        Self(truncate_to_millis(time))
+    }
+}
+
+impl From<Timestamp> for SystemTime {
+    fn from(timestamp: Timestamp) -> Self {
+        timestamp.0
+    }
+}
+
+impl FromStr for Timestamp {
+    type Err = APIError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl TryFrom<String> for Timestamp {
+    type Error = APIError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::parse(&value)
+    }
+}
+
+impl From<Timestamp> for String {
+    fn from(timestamp: Timestamp) -> Self {
+        format_api_time(timestamp.0)
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&format_api_time(self.0))
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<Timestamp> for chrono::DateTime<chrono::Utc> {
+    fn from(timestamp: Timestamp) -> Self {
+        timestamp.0.into()
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<chrono::DateTime<chrono::Utc>> for Timestamp {
+    fn from(time: chrono::DateTime<chrono::Utc>) -> Self {
+        Self::from(SystemTime::from(time))
+    }
+}
+
+#[cfg(feature = "time")]
+impl From<Timestamp> for time::OffsetDateTime {
+    fn from(timestamp: Timestamp) -> Self {
+        timestamp.0.into()
+    }
+}
+
+#[cfg(feature = "time")]
+impl From<time::OffsetDateTime> for Timestamp {
+    fn from(time: time::OffsetDateTime) -> Self {
+        Self::from(SystemTime::from(time))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Timestamp;
+    use std::collections::HashSet;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    #[test]
+    fn test_round_trip() {
+        let json = r#""20240229T235959.250Z""#;
+        let timestamp: Timestamp = serde_json::from_str(json).unwrap();
+        assert_eq!(serde_json::to_string(&timestamp).unwrap(), json);
+        assert_eq!(timestamp.to_string(), "20240229T235959.250Z");
+
+        assert!(serde_json::from_str::<Timestamp>(r#""2024-02-29""#).is_err());
+        assert!("20240230T000000.000Z".parse::<Timestamp>().is_err());
+    }
+
+    #[test]
+    fn test_sub_millisecond_round_trip() {
+        let time = UNIX_EPOCH + Duration::new(1_704_067_200, 250_999_999);
+        let timestamp = Timestamp::from(time);
+        assert_eq!(timestamp.to_string(), "20240101T000000.250Z");
+
+        let parsed: Timestamp = timestamp.to_string().parse().unwrap();
+        assert_eq!(parsed, timestamp);
+        let json = serde_json::to_string(&timestamp).unwrap();
+        assert_eq!(serde_json::from_str::<Timestamp>(&json).unwrap(), timestamp);
+        assert_eq!(HashSet::from([parsed, timestamp]).len(), 1);
+
+        let before_epoch = UNIX_EPOCH - Duration::from_nanos(1_500_000);
+        assert_eq!(
+            Timestamp::from(before_epoch).as_system_time(),
+            UNIX_EPOCH - Duration::from_millis(2)
+        );
+        assert!(Timestamp::now().as_system_time() <= SystemTime::now());
+    }
+
+    #[test]
+    fn test_durations() {
+        let start: Timestamp = "20240101T000000.000Z".parse().unwrap();
+        let now: Timestamp = "20240101T013000.000Z".parse().unwrap();
+
+        assert_eq!(
+            start.time_since(now.as_system_time()),
+            Duration::from_secs(5400)
+        );
+        assert_eq!(start.time_until(now.as_system_time()), Duration::ZERO);
+        assert!(start.is_past(now.as_system_time()));
+        assert!(start < now);
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_chrono() {
+        let timestamp: Timestamp = "20240101T120000.500Z".parse().unwrap();
+        let time: chrono::DateTime<chrono::Utc> = timestamp.into();
+        assert_eq!(time.to_rfc3339(), "2024-01-01T12:00:00.500+00:00");
+        assert_eq!(Timestamp::from(time), timestamp);
+
+        let precise = time + chrono::Duration::microseconds(999);
+        assert_eq!(Timestamp::from(precise), timestamp);
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn test_time() {
+        let timestamp: Timestamp = "20240101T120000.500Z".parse().unwrap();
+        let time: time::OffsetDateTime = timestamp.into();
+        assert_eq!(time.unix_timestamp(), 1_704_110_400);
+        assert_eq!(time.millisecond(), 500);
+        assert_eq!(Timestamp::from(time), timestamp);
+
+        let precise = time + time::Duration::microseconds(999);
+        assert_eq!(Timestamp::from(precise), timestamp);
+    }
+}
@@ -0,0 +1,191 @@
+//! The audit log of the proxy: the latest requests of every consumer with
+//! the endpoint, status and latency, and running totals per consumer.
+
+use serde::{Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::models::timestamp::Timestamp;
+
+fn millis<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64() * 1000.0)
+}
+
+/// The endpoint a path belongs to, with tags and ids replaced by
+/// placeholders, e.g. `/clans/%232PP/members` is `/clans/{tag}/members`
+#[must_use]
+pub fn endpoint(path: &str) -> String {
+    let path = path.split('?').next().unwrap_or_default();
+    path.split('/')
+        .map(|segment| {
+            if segment.starts_with("%23") || segment.starts_with('#') {
+                "{tag}"
+            } else if !segment.is_empty() && segment.bytes().all(|b| b.is_ascii_digit()) {
+                "{id}"
+            } else {
+                segment
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AuditEntry {
+    pub time: Timestamp,
+    pub consumer: String,
+    pub method: String,
+    /// See `endpoint`
+    pub endpoint: String,
+    /// Path and query as requested
+    pub path: String,
+    pub status: u16,
+    #[serde(rename = "latency_ms", serialize_with = "millis")]
+    pub latency: Duration,
+    /// Whether the response came from the cache
+    pub cached: bool,
+}
+
+/// What a consumer did since the proxy started
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ConsumerTotals {
+    pub requests: u64,
+    /// Responses with a 4xx or 5xx status
+    pub errors: u64,
+    /// Requests denied by the consumer's quota
+    pub over_quota: u64,
+    pub cache_hits: u64,
+    #[serde(rename = "total_latency_ms", serialize_with = "millis")]
+    pub total_latency: Duration,
+    /// Requests per endpoint
+    pub endpoints: BTreeMap<String, u64>,
+}
+
+impl ConsumerTotals {
+    #[must_use]
+    pub fn average_latency(&self) -> Duration {
+        match u32::try_from(self.requests) {
+            Ok(0) => Duration::ZERO,
+            Ok(requests) => self.total_latency / requests,
+            Err(_) => self.total_latency.div_f64(self.requests as f64),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct AuditLog {
+    capacity: usize,
+    entries: Mutex<VecDeque<AuditEntry>>,
+    totals: Mutex<HashMap<String, ConsumerTotals>>,
+}
+
+impl AuditLog {
+    /// Keeps the latest `capacity` entries, totals are kept for every request
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::new(VecDeque::new()),
+            totals: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Add an entry, `over_quota` when the request was denied by a quota
+    pub fn record(&self, entry: AuditEntry, over_quota: bool) {
+        {
+            let mut totals = self.totals.lock().expect("audit log poisoned");
+            let totals = totals.entry(entry.consumer.clone()).or_default();
+            totals.requests += 1;
+            totals.errors += u64::from(entry.status >= 400);
+            totals.over_quota += u64::from(over_quota);
+            totals.cache_hits += u64::from(entry.cached);
+            totals.total_latency += entry.latency;
+            *totals.endpoints.entry(entry.endpoint.clone()).or_default() += 1;
+        }
+
+        if self.capacity == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock().expect("audit log poisoned");
+        if entries.len() >= self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    /// The latest entries, newest first, of one consumer or of all of them
+    #[must_use]
+    pub fn entries(&self, consumer: Option<&str>, limit: usize) -> Vec<AuditEntry> {
+        let entries = self.entries.lock().expect("audit log poisoned");
+        entries
+            .iter()
+            .rev()
+            .filter(|entry| consumer.is_none_or(|consumer| entry.consumer == consumer))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    #[must_use]
+    pub fn totals(&self, consumer: &str) -> ConsumerTotals {
+        let totals = self.totals.lock().expect("audit log poisoned");
+        totals.get(consumer).cloned().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{endpoint, AuditEntry, AuditLog};
+    use crate::models::timestamp::Timestamp;
+    use std::time::Duration;
+
+    fn entry(consumer: &str, path: &str, status: u16, latency: u64) -> AuditEntry {
+        AuditEntry {
+            time: Timestamp::now(),
+            consumer: consumer.to_string(),
+            method: "GET".to_string(),
+            endpoint: endpoint(path),
+            path: path.to_string(),
+            status,
+            latency: Duration::from_millis(latency),
+            cached: false,
+        }
+    }
+
+    #[test]
+    fn test_endpoint() {
+        assert_eq!(
+            endpoint("/clans/%232PP/members?limit=5"),
+            "/clans/{tag}/members"
+        );
+        assert_eq!(
+            endpoint("/locations/32000006/rankings/clans"),
+            "/locations/{id}/rankings/clans"
+        );
+        assert_eq!(
+            endpoint("/goldpass/seasons/current"),
+            "/goldpass/seasons/current"
+        );
+    }
+
+    #[test]
+    fn test_audit_log() {
+        let log = AuditLog::new(2);
+        log.record(entry("bot", "/players/%23P0", 200, 10), false);
+        log.record(entry("site", "/clans/%232PP", 404, 20), false);
+        log.record(entry("bot", "/players/%23P2", 429, 30), true);
+
+        let latest = log.entries(None, 10);
+        assert_eq!(latest.len(), 2);
+        assert_eq!(latest[0].path, "/players/%23P2");
+        assert_eq!(log.entries(Some("bot"), 10).len(), 1);
+
+        // Totals cover entries that were dropped from the log
+        let bot = log.totals("bot");
+        assert_eq!((bot.requests, bot.errors, bot.over_quota), (2, 1, 1));
+        assert_eq!(bot.endpoints["/players/{tag}"], 2);
+        assert_eq!(bot.average_latency(), Duration::from_millis(20));
+        assert_eq!(log.totals("nobody").requests, 0);
+    }
+}
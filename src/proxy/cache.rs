@@ -0,0 +1,132 @@
+//! Responses cached for as long as the API allows (`Cache-Control: max-age`)
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+
+#[derive(Debug, Clone)]
+pub struct CachedResponse {
+    pub content_type: Option<String>,
+    pub body: Vec<u8>,
+    pub expires: Instant,
+}
+
+impl CachedResponse {
+    /// Time left until the response expires
+    #[must_use]
+    pub fn max_age(&self, now: Instant) -> Duration {
+        self.expires.saturating_duration_since(now)
+    }
+}
+
+#[derive(Debug)]
+pub struct ResponseCache {
+    capacity: usize,
+    entries: Mutex<HashMap<String, CachedResponse>>,
+}
+
+impl ResponseCache {
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The response cached for `key` if it has not expired
+    #[must_use]
+    pub fn get(&self, key: &str, now: Instant) -> Option<CachedResponse> {
+        let mut entries = self.entries.lock().expect("cache poisoned");
+        match entries.get(key) {
+            Some(response) if response.expires > now => Some(response.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Cache a response. When the cache is full, expired responses are
+    /// dropped first and then the ones closest to expiring.
+    pub fn insert(&self, key: String, response: CachedResponse, now: Instant) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock().expect("cache poisoned");
+        if entries.len() >= self.capacity && !entries.contains_key(&key) {
+            entries.retain(|_, cached| cached.expires > now);
+            while entries.len() >= self.capacity {
+                let Some(oldest) = entries
+                    .iter()
+                    .min_by_key(|(_, cached)| cached.expires)
+                    .map(|(key, _)| key.clone())
+                else {
+                    break;
+                };
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(key, response);
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries.lock().expect("cache poisoned").len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CachedResponse, ResponseCache};
+    use std::time::Duration;
+    use tokio::time::Instant;
+
+    fn response(expires: Instant) -> CachedResponse {
+        CachedResponse {
+            content_type: None,
+            body: b"{}".to_vec(),
+            expires,
+        }
+    }
+
+    #[test]
+    fn test_expiry_and_eviction() {
+        let now = Instant::now();
+        let cache = ResponseCache::new(2);
+        cache.insert(
+            "a".to_string(),
+            response(now + Duration::from_secs(10)),
+            now,
+        );
+        cache.insert(
+            "b".to_string(),
+            response(now + Duration::from_secs(60)),
+            now,
+        );
+        assert!(cache.get("a", now).is_some());
+        assert!(cache.get("a", now + Duration::from_secs(10)).is_none());
+
+        cache.insert(
+            "a".to_string(),
+            response(now + Duration::from_secs(30)),
+            now,
+        );
+        // Full, the response closest to expiring makes room
+        cache.insert(
+            "c".to_string(),
+            response(now + Duration::from_secs(90)),
+            now,
+        );
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get("a", now).is_none());
+        assert!(cache.get("b", now).is_some());
+    }
+}
@@ -0,0 +1,146 @@
+//! The pool of API keys the proxy sends requests with. Keys are handed out in
+//! turn, each is rate limited on its own and a key the API throttled is
+//! left out for a while.
+
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::{sleep, Instant};
+
+use super::limit::TokenBucket;
+
+#[derive(Debug)]
+struct PooledKey {
+    token: String,
+    bucket: TokenBucket,
+    /// Left out until then after the API throttled it
+    throttled_until: Option<Instant>,
+}
+
+impl PooledKey {
+    /// Time until the key can be used again
+    fn wait_time(&mut self, now: Instant) -> Duration {
+        let throttled = self
+            .throttled_until
+            .map(|until| until.saturating_duration_since(now))
+            .unwrap_or_default();
+        throttled.max(self.bucket.wait_time(now))
+    }
+}
+
+/// A key handed out by the pool
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeasedKey {
+    pub index: usize,
+    pub token: String,
+}
+
+#[derive(Debug)]
+pub struct KeyPool {
+    keys: Mutex<Vec<PooledKey>>,
+    /// Index of the key to try first
+    next: Mutex<usize>,
+}
+
+impl KeyPool {
+    /// A pool where every key allows `rate` requests per second
+    #[must_use]
+    pub fn new(tokens: impl IntoIterator<Item = String>, rate: f64) -> Self {
+        let keys = tokens
+            .into_iter()
+            .map(|token| PooledKey {
+                token,
+                bucket: TokenBucket::new(rate, rate.max(1.0)),
+                throttled_until: None,
+            })
+            .collect();
+        Self {
+            keys: Mutex::new(keys),
+            next: Mutex::new(0),
+        }
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.keys.lock().expect("key pool poisoned").len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Take the next key that is free, or how long until one will be
+    pub fn try_acquire(&self) -> Result<LeasedKey, Duration> {
+        let mut keys = self.keys.lock().expect("key pool poisoned");
+        let mut next = self.next.lock().expect("key pool poisoned");
+        let now = Instant::now();
+        let count = keys.len();
+        let mut wait = Duration::MAX;
+
+        for offset in 0..count {
+            let index = (*next + offset) % count;
+            let key = &mut keys[index];
+            let key_wait = key.wait_time(now);
+            if key_wait.is_zero() && key.bucket.try_take(now) {
+                key.throttled_until = None;
+                *next = (index + 1) % count;
+                return Ok(LeasedKey {
+                    index,
+                    token: key.token.clone(),
+                });
+            }
+            wait = wait.min(key_wait.max(Duration::from_millis(1)));
+        }
+        Err(wait)
+    }
+
+    /// Wait for the next key that is free, `None` if the pool is empty or no
+    /// key will ever be free again
+    pub async fn acquire(&self) -> Option<LeasedKey> {
+        if self.is_empty() {
+            return None;
+        }
+        loop {
+            match self.try_acquire() {
+                Ok(key) => return Some(key),
+                Err(Duration::MAX) => return None,
+                Err(wait) => sleep(wait).await,
+            }
+        }
+    }
+
+    /// Leave a key out for `cooldown` after the API throttled it
+    pub fn throttle(&self, key: &LeasedKey, cooldown: Duration) {
+        let mut keys = self.keys.lock().expect("key pool poisoned");
+        if let Some(pooled) = keys.get_mut(key.index) {
+            pooled.throttled_until = Some(Instant::now() + cooldown);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::KeyPool;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_rotation_and_throttling() {
+        let pool = KeyPool::new(["a".to_string(), "b".to_string()], 100.0);
+        let first = pool.acquire().await.unwrap();
+        let second = pool.acquire().await.unwrap();
+        assert_eq!((first.token.as_str(), second.token.as_str()), ("a", "b"));
+
+        pool.throttle(&first, Duration::from_secs(60));
+        for _ in 0..3 {
+            assert_eq!(pool.acquire().await.unwrap().token, "b");
+        }
+
+        assert!(KeyPool::new([], 1.0).acquire().await.is_none());
+
+        // A key that never refills is used once, then the pool gives up
+        let pool = KeyPool::new(["a".to_string()], 0.0);
+        assert!(pool.acquire().await.is_some());
+        assert_eq!(pool.try_acquire(), Err(Duration::MAX));
+        assert!(pool.acquire().await.is_none());
+    }
+}
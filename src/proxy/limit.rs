@@ -0,0 +1,87 @@
+//! Token bucket rate limiting
+
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Allows `rate` requests per second on average, with bursts of up to
+/// `capacity` requests
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    capacity: f64,
+    rate: f64,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    /// A full bucket
+    #[must_use]
+    pub fn new(rate: f64, capacity: f64) -> Self {
+        Self {
+            capacity,
+            rate,
+            tokens: capacity,
+            updated: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.updated = now;
+    }
+
+    /// Take a token if there is one
+    pub fn try_take(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Time until a token is available, `Duration::MAX` if the bucket is
+    /// empty and never refills
+    pub fn wait_time(&mut self, now: Instant) -> Duration {
+        self.refill(now);
+        if self.tokens >= 1.0 {
+            Duration::ZERO
+        } else if !(self.rate > 0.0 && self.rate.is_finite()) {
+            Duration::MAX
+        } else {
+            Duration::from_secs_f64((1.0 - self.tokens) / self.rate)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::TokenBucket;
+    use std::time::Duration;
+    use tokio::time::Instant;
+
+    #[test]
+    fn test_token_bucket() {
+        let now = Instant::now();
+        let mut bucket = TokenBucket::new(2.0, 2.0);
+        assert!(bucket.try_take(now));
+        assert!(bucket.try_take(now));
+        assert!(!bucket.try_take(now));
+        assert_eq!(bucket.wait_time(now), Duration::from_millis(500));
+
+        let later = now + Duration::from_millis(500);
+        assert!(bucket.try_take(later));
+        assert!(!bucket.try_take(later));
+    }
+
+    #[test]
+    fn test_empty_bucket_without_rate() {
+        let now = Instant::now();
+        let mut bucket = TokenBucket::new(0.0, 1.0);
+        assert!(bucket.try_take(now));
+        assert_eq!(bucket.wait_time(now), Duration::MAX);
+        assert!(!bucket.try_take(now + Duration::from_secs(60)));
+    }
+}
@@ -0,0 +1,906 @@
+//! An HTTP proxy in front of the Clash of Clans API. Internal services send
+//! their requests to the proxy's `/v1/...` paths, the same paths as
+//! `api::api`, authenticated with their own consumer token. The proxy holds
+//! the pool of API keys and applies caching, rate limiting and retries in one
+//! place, so it is the only process that talks to the API.
+//!
+//! Every consumer can be given a quota of requests per minute and per day,
+//! and every request is written to an audit log. Only requests sent on to the
+//! API count against a quota, cache hits and rejected methods are free.
+//! Admins list the usage of every consumer at `/admin/usage` and the latest
+//! requests at `/admin/audit`.
+//!
+//! Point a `Client` at the proxy with `Client::new(consumer_token)
+//! .with_base_url("http://<proxy>/v1")`.
+
+pub mod audit;
+pub mod cache;
+pub mod keys;
+pub mod limit;
+pub mod quota;
+
+pub use audit::{AuditEntry, AuditLog, ConsumerTotals};
+pub use cache::{CachedResponse, ResponseCache};
+pub use keys::{KeyPool, LeasedKey};
+pub use limit::TokenBucket;
+pub use quota::{Quota, QuotaExceeded, QuotaTracker, QuotaWindow};
+
+use actix_web::dev::Server;
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::http::{Method, StatusCode};
+use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::net::ToSocketAddrs;
+use std::time::{Duration, SystemTime};
+use tokio::time::{sleep, Instant};
+
+use crate::api::api;
+use crate::core::client::parse_max_age;
+use crate::error::APIError;
+use crate::models::error::SupercellAPIError;
+use crate::models::timestamp::Timestamp;
+
+/// Header telling whether a response came from the cache, "HIT" or "MISS"
+pub const CACHE_HEADER: &str = "X-Cache";
+
+#[derive(Debug, Clone)]
+pub struct ProxyConfig {
+    /// Base url of the API, including the `/v1` prefix
+    pub upstream: String,
+    /// API keys to send requests with
+    pub keys: Vec<String>,
+    /// Consumer names keyed by their token
+    pub consumers: HashMap<String, String>,
+    /// Quotas keyed by consumer name
+    pub quotas: HashMap<String, Quota>,
+    /// Quota of consumers without one of their own
+    pub default_quota: Quota,
+    /// Token for the admin endpoints, they are disabled without one
+    pub admin_token: Option<String>,
+    /// Requests per second allowed for every key
+    pub requests_per_second: f64,
+    /// Attempts after the first one when the API throttles a key, fails or
+    /// can not be reached
+    pub max_retries: u32,
+    /// Wait before retrying a failed request, doubled after every retry
+    pub retry_backoff: Duration,
+    /// How long a key the API throttled is left out of the pool
+    pub throttle_cooldown: Duration,
+    /// Responses kept in the cache
+    pub cache_capacity: usize,
+    /// Requests kept in the audit log
+    pub audit_capacity: usize,
+}
+
+impl Default for ProxyConfig {
+    fn default() -> Self {
+        Self {
+            upstream: api::BASE_URL.to_string(),
+            keys: Vec::new(),
+            consumers: HashMap::new(),
+            quotas: HashMap::new(),
+            default_quota: Quota::unlimited(),
+            admin_token: None,
+            requests_per_second: 10.0,
+            max_retries: 2,
+            retry_backoff: Duration::from_millis(250),
+            throttle_cooldown: Duration::from_secs(1),
+            cache_capacity: 10_000,
+            audit_capacity: 10_000,
+        }
+    }
+}
+
+/// Whether `rate` can be used as a rate limit
+fn is_valid_rate(rate: f64) -> bool {
+    rate.is_finite() && rate > 0.0
+}
+
+impl ProxyConfig {
+    #[must_use]
+    pub fn new(keys: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            keys: keys.into_iter().map(Into::into).collect(),
+            ..Self::default()
+        }
+    }
+
+    /// Read the configuration from the environment:
+    ///
+    /// - `PROXY_API_KEYS`: comma separated API keys, required
+    /// - `PROXY_CONSUMERS`: comma separated `name:token` pairs, required
+    /// - `PROXY_UPSTREAM`: base url of the API
+    /// - `PROXY_RATE_LIMIT`: requests per second per key, a positive number
+    /// - `PROXY_MAX_RETRIES`
+    /// - `PROXY_CACHE_CAPACITY`
+    /// - `PROXY_ADMIN_TOKEN`
+    /// - `PROXY_QUOTA_PER_MINUTE`, `PROXY_QUOTA_PER_DAY`: the default quota
+    /// - `PROXY_QUOTAS`: comma separated `name:per_minute/per_day` quotas,
+    ///   leave a limit empty for none, e.g. `bot:60/,site:/10000`
+    pub fn from_env() -> Result<Self, APIError> {
+        Self::from_vars(|name| env::var(name).ok())
+    }
+
+    /// Read the configuration from the variables `lookup` returns, see
+    /// [`ProxyConfig::from_env`] for the variables
+    pub fn from_vars(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, APIError> {
+        fn parse<T: std::str::FromStr>(
+            var: &dyn Fn(&str) -> Option<String>,
+            name: &str,
+        ) -> Result<Option<T>, APIError> {
+            var(name)
+                .map(|value| {
+                    value
+                        .trim()
+                        .parse()
+                        .map_err(|_| APIError::InvalidParameters(format!("{name}: {value}")))
+                })
+                .transpose()
+        }
+        fn list(value: &str) -> impl Iterator<Item = &str> {
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+        }
+        let var = |name: &str| lookup(name).filter(|value| !value.trim().is_empty());
+
+        let keys = var("PROXY_API_KEYS")
+            .ok_or_else(|| APIError::InvalidParameters("PROXY_API_KEYS is missing".to_string()))?;
+        let consumers = var("PROXY_CONSUMERS")
+            .ok_or_else(|| APIError::InvalidParameters("PROXY_CONSUMERS is missing".to_string()))?;
+
+        let mut config = Self::new(list(&keys));
+        for consumer in list(&consumers) {
+            let (name, token) = consumer.split_once(':').ok_or_else(|| {
+                APIError::InvalidParameters(format!("PROXY_CONSUMERS: {consumer}"))
+            })?;
+            config = config.with_consumer(name, token);
+        }
+        if let Some(upstream) = var("PROXY_UPSTREAM") {
+            config = config.with_upstream(&upstream);
+        }
+        if let Some(rate) = parse::<f64>(&var, "PROXY_RATE_LIMIT")? {
+            if !is_valid_rate(rate) {
+                return Err(APIError::InvalidParameters(format!(
+                    "PROXY_RATE_LIMIT: {rate} is not a positive number"
+                )));
+            }
+            config.requests_per_second = rate;
+        }
+        if let Some(retries) = parse(&var, "PROXY_MAX_RETRIES")? {
+            config.max_retries = retries;
+        }
+        if let Some(capacity) = parse(&var, "PROXY_CACHE_CAPACITY")? {
+            config.cache_capacity = capacity;
+        }
+        if let Some(token) = var("PROXY_ADMIN_TOKEN") {
+            config = config.with_admin_token(&token);
+        }
+        config.default_quota.per_minute = parse(&var, "PROXY_QUOTA_PER_MINUTE")?;
+        config.default_quota.per_day = parse(&var, "PROXY_QUOTA_PER_DAY")?;
+        if let Some(quotas) = var("PROXY_QUOTAS") {
+            for quota in list(&quotas) {
+                let invalid = || APIError::InvalidParameters(format!("PROXY_QUOTAS: {quota}"));
+                let (name, limits) = quota.split_once(':').ok_or_else(invalid)?;
+                let (minute, day) = limits.split_once('/').ok_or_else(invalid)?;
+                let limit = |value: &str| match value.trim() {
+                    "" => Ok(None),
+                    value => value.parse().map(Some).map_err(|_| invalid()),
+                };
+                let quota = Quota {
+                    per_minute: limit(minute)?,
+                    per_day: limit(day)?,
+                };
+                config = config.with_quota(name, quota);
+            }
+        }
+        Ok(config)
+    }
+
+    #[must_use]
+    pub fn with_upstream(mut self, upstream: &str) -> Self {
+        self.upstream = upstream.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Allow a consumer to use the proxy with `token`
+    #[must_use]
+    pub fn with_consumer(mut self, name: &str, token: &str) -> Self {
+        self.consumers.insert(token.to_string(), name.to_string());
+        self
+    }
+
+    /// Limit the requests of the consumer called `name`
+    #[must_use]
+    pub fn with_quota(mut self, name: &str, quota: Quota) -> Self {
+        self.quotas.insert(name.to_string(), quota);
+        self
+    }
+
+    /// Limit the requests of consumers without a quota of their own
+    #[must_use]
+    pub fn with_default_quota(mut self, quota: Quota) -> Self {
+        self.default_quota = quota;
+        self
+    }
+
+    #[must_use]
+    pub fn with_admin_token(mut self, token: &str) -> Self {
+        self.admin_token = Some(token.to_string());
+        self
+    }
+
+    /// Allow `requests_per_second` requests per second with every key
+    ///
+    /// # Panics
+    ///
+    /// If `requests_per_second` is not a positive, finite number
+    #[must_use]
+    pub fn with_rate_limit(mut self, requests_per_second: f64) -> Self {
+        assert!(
+            is_valid_rate(requests_per_second),
+            "rate limit must be a positive number, got {requests_per_second}"
+        );
+        self.requests_per_second = requests_per_second;
+        self
+    }
+
+    #[must_use]
+    pub fn with_retries(mut self, max_retries: u32, backoff: Duration) -> Self {
+        self.max_retries = max_retries;
+        self.retry_backoff = backoff;
+        self
+    }
+
+    #[must_use]
+    pub fn with_throttle_cooldown(mut self, cooldown: Duration) -> Self {
+        self.throttle_cooldown = cooldown;
+        self
+    }
+
+    #[must_use]
+    pub fn with_cache_capacity(mut self, capacity: usize) -> Self {
+        self.cache_capacity = capacity;
+        self
+    }
+
+    #[must_use]
+    pub fn with_audit_capacity(mut self, capacity: usize) -> Self {
+        self.audit_capacity = capacity;
+        self
+    }
+}
+
+/// A consumer's quota and what it used, as listed by `/admin/usage`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConsumerUsage {
+    pub consumer: String,
+    pub quota: Quota,
+    /// Requests this minute
+    pub minute: u32,
+    /// Requests today (UTC)
+    pub day: u32,
+    #[serde(flatten)]
+    pub totals: ConsumerTotals,
+}
+
+/// Shared state of the proxy's workers
+#[derive(Debug)]
+pub struct Proxy {
+    config: ProxyConfig,
+    http: reqwest::Client,
+    keys: KeyPool,
+    cache: ResponseCache,
+    quotas: QuotaTracker,
+    audit: AuditLog,
+}
+
+impl Proxy {
+    #[must_use]
+    pub fn new(config: ProxyConfig) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            keys: KeyPool::new(config.keys.clone(), config.requests_per_second),
+            cache: ResponseCache::new(config.cache_capacity),
+            quotas: QuotaTracker::new(config.default_quota, config.quotas.clone()),
+            audit: AuditLog::new(config.audit_capacity),
+            config,
+        }
+    }
+
+    #[must_use]
+    pub fn config(&self) -> &ProxyConfig {
+        &self.config
+    }
+
+    #[must_use]
+    pub fn audit(&self) -> &AuditLog {
+        &self.audit
+    }
+
+    /// Usage of every consumer, sorted by name
+    #[must_use]
+    pub fn usage(&self, now: SystemTime) -> Vec<ConsumerUsage> {
+        let mut names: Vec<&String> = self.config.consumers.values().collect();
+        names.sort();
+        names.dedup();
+        names
+            .into_iter()
+            .map(|name| {
+                let (minute, day) = self.quotas.used(name, now);
+                ConsumerUsage {
+                    consumer: name.clone(),
+                    quota: self.quotas.quota(name),
+                    minute,
+                    day,
+                    totals: self.audit.totals(name),
+                }
+            })
+            .collect()
+    }
+
+    /// Name of the consumer a request was made by, from its bearer token
+    fn consumer(&self, request: &HttpRequest) -> Option<&str> {
+        self.config
+            .consumers
+            .get(bearer_token(request)?)
+            .map(String::as_str)
+    }
+
+    fn is_admin(&self, request: &HttpRequest) -> bool {
+        self.config.admin_token.is_some()
+            && self.config.admin_token.as_deref() == bearer_token(request)
+    }
+}
+
+fn bearer_token(request: &HttpRequest) -> Option<&str> {
+    let token = request
+        .headers()
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")?;
+    Some(token.trim())
+}
+
+/// Register the proxy's routes, the `Proxy` must be added as app data
+pub fn configure(config: &mut web::ServiceConfig) {
+    config
+        .route("/health", web::get().to(health))
+        .route("/admin/usage", web::get().to(usage))
+        .route("/admin/audit", web::get().to(audit))
+        .route("/v1/{path:.*}", web::to(forward));
+}
+
+/// Start the proxy on `address`. The returned server runs until it is
+/// awaited or stopped through its handle.
+pub fn serve(proxy: Proxy, address: impl ToSocketAddrs) -> std::io::Result<Server> {
+    let proxy = web::Data::new(proxy);
+    Ok(
+        HttpServer::new(move || App::new().app_data(proxy.clone()).configure(configure))
+            .bind(address)?
+            .run(),
+    )
+}
+
+/// An error response in the shape the API uses
+fn error(status: StatusCode, reason: &str, message: &str) -> HttpResponse {
+    HttpResponse::build(status).json(SupercellAPIError {
+        reason: Some(reason.to_string()),
+        message: Some(message.to_string()),
+    })
+}
+
+async fn health() -> HttpResponse {
+    HttpResponse::Ok().body("ok")
+}
+
+/// Only reads, and verifying player tokens, are passed on
+fn allowed(method: &Method, path: &str) -> bool {
+    method == Method::GET || (method == Method::POST && path.ends_with("/verifytoken"))
+}
+
+#[derive(Debug, Deserialize)]
+struct AuditQuery {
+    consumer: Option<String>,
+    limit: Option<usize>,
+}
+
+fn admin_denied() -> HttpResponse {
+    error(
+        StatusCode::UNAUTHORIZED,
+        "accessDenied",
+        "Missing or invalid admin token",
+    )
+}
+
+async fn usage(request: HttpRequest, proxy: web::Data<Proxy>) -> HttpResponse {
+    if !proxy.is_admin(&request) {
+        return admin_denied();
+    }
+    HttpResponse::Ok().json(proxy.usage(SystemTime::now()))
+}
+
+async fn audit(
+    request: HttpRequest,
+    query: web::Query<AuditQuery>,
+    proxy: web::Data<Proxy>,
+) -> HttpResponse {
+    if !proxy.is_admin(&request) {
+        return admin_denied();
+    }
+    let limit = query.limit.unwrap_or(100);
+    HttpResponse::Ok().json(proxy.audit.entries(query.consumer.as_deref(), limit))
+}
+
+fn over_quota(exceeded: &QuotaExceeded) -> HttpResponse {
+    let mut response = error(
+        StatusCode::TOO_MANY_REQUESTS,
+        "requestThrottled",
+        &format!(
+            "Quota of {} requests per {} used up",
+            exceeded.limit,
+            exceeded.window.as_str()
+        ),
+    );
+    let retry_after = exceeded.retry_after.as_secs_f64().ceil() as u64;
+    if let Ok(value) = HeaderValue::from_str(&retry_after.to_string()) {
+        response.headers_mut().insert(header::RETRY_AFTER, value);
+    }
+    response
+}
+
+async fn forward(request: HttpRequest, body: web::Bytes, proxy: web::Data<Proxy>) -> HttpResponse {
+    let Some(consumer) = proxy.consumer(&request).map(str::to_string) else {
+        return error(
+            StatusCode::UNAUTHORIZED,
+            "accessDenied",
+            "Missing or invalid consumer token",
+        );
+    };
+
+    let started = Instant::now();
+    let time = SystemTime::now();
+    // The path is passed on as received, tags stay url encoded
+    let path = request.path().strip_prefix("/v1").unwrap_or_default();
+    let target = match request.query_string() {
+        "" => path.to_string(),
+        query => format!("{path}?{query}"),
+    };
+
+    let mut quota_exceeded = false;
+    let response = if !allowed(request.method(), path) {
+        error(
+            StatusCode::METHOD_NOT_ALLOWED,
+            "notAllowed",
+            "Only GET requests and token verification are proxied",
+        )
+    } else if let Some(response) = cached(&request, &target, &proxy) {
+        response
+    } else {
+        match proxy.quotas.check(&consumer, time) {
+            Ok(()) => fetch(&request, &target, &body, &proxy).await,
+            Err(exceeded) => {
+                quota_exceeded = true;
+                over_quota(&exceeded)
+            }
+        }
+    };
+
+    let entry = AuditEntry {
+        time: Timestamp::from(time),
+        consumer,
+        method: request.method().to_string(),
+        endpoint: audit::endpoint(path),
+        path: target,
+        status: response.status().as_u16(),
+        latency: started.elapsed(),
+        cached: response
+            .headers()
+            .get(CACHE_HEADER)
+            .is_some_and(|value| value == "HIT"),
+    };
+    proxy.audit.record(entry, quota_exceeded);
+    response
+}
+
+/// The cached response to a GET request, if there is one
+fn cached(request: &HttpRequest, target: &str, proxy: &Proxy) -> Option<HttpResponse> {
+    if request.method() != Method::GET {
+        return None;
+    }
+    let now = Instant::now();
+    let cached = proxy.cache.get(target, now)?;
+    let mut response = HttpResponse::Ok();
+    if let Some(content_type) = &cached.content_type {
+        response.insert_header((header::CONTENT_TYPE, content_type.as_str()));
+    }
+    Some(
+        response
+            .insert_header((
+                header::CACHE_CONTROL,
+                format!("max-age={}", cached.max_age(now).as_secs()),
+            ))
+            .insert_header((CACHE_HEADER, "HIT"))
+            .body(cached.body),
+    )
+}
+
+/// Send a request on to the API and cache the response
+async fn fetch(
+    request: &HttpRequest,
+    target: &str,
+    body: &web::Bytes,
+    proxy: &Proxy,
+) -> HttpResponse {
+    let cacheable = request.method() == Method::GET;
+    let upstream = match send(proxy, request.method(), target, body).await {
+        Ok(upstream) => upstream,
+        Err(message) => return error(StatusCode::BAD_GATEWAY, "badGateway", &message),
+    };
+
+    let status = StatusCode::from_u16(upstream.status().as_u16())
+        .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    let header_value = |name: header::HeaderName| {
+        upstream
+            .headers()
+            .get(name.as_str())
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    };
+    let content_type = header_value(header::CONTENT_TYPE);
+    let cache_control = header_value(header::CACHE_CONTROL);
+    let body = match upstream.bytes().await {
+        Ok(body) => body.to_vec(),
+        Err(e) => return error(StatusCode::BAD_GATEWAY, "badGateway", &e.to_string()),
+    };
+
+    if cacheable && status == StatusCode::OK {
+        if let Some(max_age) = cache_control.as_deref().and_then(parse_max_age) {
+            let now = Instant::now();
+            proxy.cache.insert(
+                target.to_string(),
+                CachedResponse {
+                    content_type: content_type.clone(),
+                    body: body.clone(),
+                    expires: now + max_age,
+                },
+                now,
+            );
+        }
+    }
+
+    let mut response = HttpResponse::build(status);
+    for (name, value) in [
+        (header::CONTENT_TYPE, content_type),
+        (header::CACHE_CONTROL, cache_control),
+    ] {
+        if let Some(value) = value.and_then(|value| HeaderValue::from_str(&value).ok()) {
+            response.insert_header((name, value));
+        }
+    }
+    response.insert_header((CACHE_HEADER, "MISS")).body(body)
+}
+
+/// Send a request to the API with the next free key, retrying with another
+/// key when the API throttles one, fails or can not be reached
+async fn send(
+    proxy: &Proxy,
+    method: &Method,
+    target: &str,
+    body: &web::Bytes,
+) -> Result<reqwest::Response, String> {
+    let config = &proxy.config;
+    let url = format!("{}{}", config.upstream, target);
+    let mut backoff = config.retry_backoff;
+    let mut attempt = 0;
+
+    loop {
+        let key = proxy
+            .keys
+            .acquire()
+            .await
+            .ok_or_else(|| "No API key available".to_string())?;
+
+        let mut request = if method == Method::POST {
+            proxy
+                .http
+                .post(&url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body.to_vec())
+        } else {
+            proxy.http.get(&url)
+        };
+        request = request.bearer_auth(&key.token);
+
+        let result = request.send().await;
+        let retries_left = attempt < config.max_retries;
+        attempt += 1;
+        match result {
+            Ok(response) if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS => {
+                proxy.keys.throttle(&key, config.throttle_cooldown);
+                if !retries_left {
+                    return Ok(response);
+                }
+            }
+            // Maintenance is not going to be over by the next attempt
+            Ok(response)
+                if response.status().is_server_error()
+                    && response.status() != reqwest::StatusCode::SERVICE_UNAVAILABLE =>
+            {
+                if !retries_left {
+                    return Ok(response);
+                }
+                sleep(backoff).await;
+                backoff *= 2;
+            }
+            Ok(response) => return Ok(response),
+            Err(e) => {
+                if !retries_left {
+                    return Err(e.to_string());
+                }
+                sleep(backoff).await;
+                backoff *= 2;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{configure, Proxy, ProxyConfig, Quota, CACHE_HEADER};
+    use actix_web::{test, web, App};
+    use serde_json::json;
+    use std::time::Duration;
+    use wiremock::matchers::{header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn config(server: &MockServer) -> ProxyConfig {
+        ProxyConfig::new(["key-a", "key-b"])
+            .with_upstream(&server.uri())
+            .with_consumer("bot", "consumer-token")
+            .with_rate_limit(100.0)
+            .with_retries(2, Duration::from_millis(1))
+    }
+
+    macro_rules! proxy_app {
+        ($config:expr) => {
+            test::init_service(
+                App::new()
+                    .app_data(web::Data::new(Proxy::new($config)))
+                    .configure(configure),
+            )
+            .await
+        };
+    }
+
+    fn get(uri: &str) -> test::TestRequest {
+        test::TestRequest::get()
+            .uri(uri)
+            .insert_header(("Authorization", "Bearer consumer-token"))
+    }
+
+    #[actix_web::test]
+    async fn test_authentication() {
+        let server = MockServer::start().await;
+        let app = proxy_app!(config(&server));
+
+        let response = test::call_service(
+            &app,
+            test::TestRequest::get()
+                .uri("/v1/players/%23P0")
+                .to_request(),
+        )
+        .await;
+        assert_eq!(response.status(), 401);
+
+        let response = test::call_service(
+            &app,
+            test::TestRequest::delete()
+                .uri("/v1/players/%23P0")
+                .insert_header(("Authorization", "Bearer consumer-token"))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(response.status(), 405);
+    }
+
+    #[actix_web::test]
+    async fn test_forward_and_cache() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/players/%23P0"))
+            .and(header("Authorization", "Bearer key-a"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("Cache-Control", "max-age=60")
+                    .set_body_json(json!({ "tag": "#P0" })),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/clans"))
+            .and(query_param("name", "home"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "items": [] })))
+            .expect(2)
+            .mount(&server)
+            .await;
+        let app = proxy_app!(config(&server));
+
+        for expected in ["MISS", "HIT"] {
+            let response = test::call_service(&app, get("/v1/players/%23P0").to_request()).await;
+            assert_eq!(response.status(), 200);
+            assert_eq!(response.headers().get(CACHE_HEADER).unwrap(), expected);
+            let body: serde_json::Value = test::read_body_json(response).await;
+            assert_eq!(body["tag"], "#P0");
+        }
+
+        // Responses without a max-age are not cached
+        for _ in 0..2 {
+            let response = test::call_service(&app, get("/v1/clans?name=home").to_request()).await;
+            assert_eq!(response.status(), 200);
+            assert_eq!(response.headers().get(CACHE_HEADER).unwrap(), "MISS");
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_quotas_and_audit() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/clans/%232PP"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "tag": "#2PP" })))
+            .expect(2)
+            .mount(&server)
+            .await;
+        let app = proxy_app!(config(&server)
+            .with_consumer("site", "site-token")
+            .with_quota("bot", Quota::unlimited().with_per_day(2))
+            .with_admin_token("admin-token"));
+
+        for _ in 0..2 {
+            let response = test::call_service(&app, get("/v1/clans/%232PP").to_request()).await;
+            assert_eq!(response.status(), 200);
+        }
+        // The bot used up its quota
+        let response = test::call_service(&app, get("/v1/clans/%232PP").to_request()).await;
+        assert_eq!(response.status(), 429);
+        assert!(response.headers().contains_key("Retry-After"));
+
+        let admin = |uri: &str, token: &str| {
+            test::TestRequest::get()
+                .uri(uri)
+                .insert_header(("Authorization", format!("Bearer {token}")))
+                .to_request()
+        };
+        let response = test::call_service(&app, admin("/admin/usage", "consumer-token")).await;
+        assert_eq!(response.status(), 401);
+
+        let response = test::call_service(&app, admin("/admin/usage", "admin-token")).await;
+        let usage: serde_json::Value = test::read_body_json(response).await;
+        assert_eq!(usage[0]["consumer"], "bot");
+        assert_eq!(usage[0]["quota"]["per_day"], 2);
+        assert_eq!(usage[0]["day"], 2);
+        assert_eq!(usage[0]["requests"], 3);
+        assert_eq!(usage[0]["over_quota"], 1);
+        assert_eq!(usage[0]["endpoints"]["/clans/{tag}"], 3);
+        assert_eq!(usage[1]["consumer"], "site");
+        assert_eq!(usage[1]["requests"], 0);
+
+        let response = test::call_service(
+            &app,
+            admin("/admin/audit?consumer=bot&limit=2", "admin-token"),
+        )
+        .await;
+        let entries: serde_json::Value = test::read_body_json(response).await;
+        let statuses: Vec<_> = entries
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entry| entry["status"].as_u64().unwrap())
+            .collect();
+        assert_eq!(statuses, [429, 200]);
+        assert_eq!(entries[0]["path"], "/clans/%232PP");
+    }
+
+    #[actix_web::test]
+    async fn test_free_requests() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/players/%23P0"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("Cache-Control", "max-age=60")
+                    .set_body_json(json!({ "tag": "#P0" })),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        let app = proxy_app!(config(&server).with_quota("bot", Quota::unlimited().with_per_day(1)));
+
+        // Rejected methods and cache hits don't use up the quota
+        let response = test::call_service(
+            &app,
+            test::TestRequest::delete()
+                .uri("/v1/players/%23P0")
+                .insert_header(("Authorization", "Bearer consumer-token"))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(response.status(), 405);
+        for cache in ["MISS", "HIT", "HIT"] {
+            let response = test::call_service(&app, get("/v1/players/%23P0").to_request()).await;
+            assert_eq!(response.status(), 200);
+            assert_eq!(response.headers().get(CACHE_HEADER).unwrap(), cache);
+        }
+
+        let response = test::call_service(&app, get("/v1/players/%23P2").to_request()).await;
+        assert_eq!(response.status(), 429);
+    }
+
+    #[actix_web::test]
+    async fn test_retries() {
+        let server = MockServer::start().await;
+        // The first key is throttled, the request is retried with the second
+        Mock::given(method("GET"))
+            .and(path("/clans/%232PP"))
+            .and(header("Authorization", "Bearer key-a"))
+            .respond_with(ResponseTemplate::new(429))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/clans/%232PP"))
+            .and(header("Authorization", "Bearer key-b"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "tag": "#2PP" })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/clans/%232PG"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(3)
+            .mount(&server)
+            .await;
+        let app = proxy_app!(config(&server));
+
+        let response = test::call_service(&app, get("/v1/clans/%232PP").to_request()).await;
+        assert_eq!(response.status(), 200);
+
+        // Gives up after the retries and passes the error on
+        let response = test::call_service(&app, get("/v1/clans/%232PG").to_request()).await;
+        assert_eq!(response.status(), 500);
+    }
+
+    /// Variables with `PROXY_RATE_LIMIT` set to `rate`
+    fn vars(rate: &str) -> impl Fn(&str) -> Option<String> + '_ {
+        move |name| match name {
+            "PROXY_API_KEYS" => Some("key-a".to_string()),
+            "PROXY_CONSUMERS" => Some("bot:consumer-token".to_string()),
+            "PROXY_RATE_LIMIT" => Some(rate.to_string()),
+            _ => None,
+        }
+    }
+
+    // `test` is the actix test module here, so the attribute is spelled out
+    #[::core::prelude::v1::test]
+    fn test_invalid_rate_limit() {
+        for rate in ["0", "-1", "NaN", "inf"] {
+            assert!(
+                ProxyConfig::from_vars(vars(rate)).is_err(),
+                "{rate} was accepted"
+            );
+        }
+        let config = ProxyConfig::from_vars(vars("2.5")).unwrap();
+        assert_eq!(config.requests_per_second, 2.5);
+    }
+
+    #[::core::prelude::v1::test]
+    #[should_panic(expected = "rate limit must be a positive number")]
+    fn test_zero_rate_limit() {
+        let _ = ProxyConfig::new(["key-a"]).with_rate_limit(0.0);
+    }
+}
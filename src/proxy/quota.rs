@@ -0,0 +1,224 @@
+//! Request quotas per consumer, counted in fixed windows: the current minute
+//! and the current UTC day.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use crate::utils::time::{to_unix, SECONDS_PER_DAY};
+
+/// Requests a consumer may make, `None` means no limit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Quota {
+    pub per_minute: Option<u32>,
+    pub per_day: Option<u32>,
+}
+
+impl Quota {
+    #[must_use]
+    pub fn unlimited() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn with_per_minute(mut self, requests: u32) -> Self {
+        self.per_minute = Some(requests);
+        self
+    }
+
+    #[must_use]
+    pub fn with_per_day(mut self, requests: u32) -> Self {
+        self.per_day = Some(requests);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuotaWindow {
+    Minute,
+    Day,
+}
+
+impl QuotaWindow {
+    #[must_use]
+    pub fn seconds(&self) -> i64 {
+        match self {
+            Self::Minute => 60,
+            Self::Day => SECONDS_PER_DAY,
+        }
+    }
+
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Minute => "minute",
+            Self::Day => "day",
+        }
+    }
+
+    /// Start of the window `time` falls in, in unix seconds
+    fn start(&self, time: SystemTime) -> i64 {
+        to_unix(time).0.div_euclid(self.seconds()) * self.seconds()
+    }
+}
+
+/// A request denied because the consumer used up its quota
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuotaExceeded {
+    pub window: QuotaWindow,
+    pub limit: u32,
+    /// Time until the window ends
+    pub retry_after: Duration,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Counter {
+    window: QuotaWindow,
+    start: i64,
+    count: u32,
+}
+
+impl Counter {
+    fn new(window: QuotaWindow) -> Self {
+        Self {
+            window,
+            start: i64::MIN,
+            count: 0,
+        }
+    }
+
+    /// Requests counted in the window `time` falls in
+    fn count(&self, time: SystemTime) -> u32 {
+        if self.window.start(time) == self.start {
+            self.count
+        } else {
+            0
+        }
+    }
+
+    fn increment(&mut self, time: SystemTime) {
+        let start = self.window.start(time);
+        if start != self.start {
+            self.start = start;
+            self.count = 0;
+        }
+        self.count += 1;
+    }
+
+    fn check(&self, limit: Option<u32>, time: SystemTime) -> Result<(), QuotaExceeded> {
+        match limit {
+            Some(limit) if self.count(time) >= limit => {
+                let (seconds, millis) = to_unix(time);
+                let end = self.window.start(time) + self.window.seconds();
+                let left = Duration::from_secs((end - seconds).max(0) as u64)
+                    .saturating_sub(Duration::from_millis(millis));
+                Err(QuotaExceeded {
+                    window: self.window,
+                    limit,
+                    retry_after: left,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Counters {
+    minute: Counter,
+    day: Counter,
+}
+
+impl Default for Counters {
+    fn default() -> Self {
+        Self {
+            minute: Counter::new(QuotaWindow::Minute),
+            day: Counter::new(QuotaWindow::Day),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct QuotaTracker {
+    default: Quota,
+    quotas: HashMap<String, Quota>,
+    counters: Mutex<HashMap<String, Counters>>,
+}
+
+impl QuotaTracker {
+    /// Consumers without a quota of their own get `default`
+    #[must_use]
+    pub fn new(default: Quota, quotas: HashMap<String, Quota>) -> Self {
+        Self {
+            default,
+            quotas,
+            counters: Mutex::new(HashMap::new()),
+        }
+    }
+
+    #[must_use]
+    pub fn quota(&self, consumer: &str) -> Quota {
+        self.quotas.get(consumer).copied().unwrap_or(self.default)
+    }
+
+    /// Count a request of `consumer` if its quota allows it. Denied requests
+    /// are not counted.
+    pub fn check(&self, consumer: &str, time: SystemTime) -> Result<(), QuotaExceeded> {
+        let quota = self.quota(consumer);
+        let mut counters = self.counters.lock().expect("quota tracker poisoned");
+        let counters = counters.entry(consumer.to_string()).or_default();
+        counters.minute.check(quota.per_minute, time)?;
+        counters.day.check(quota.per_day, time)?;
+        counters.minute.increment(time);
+        counters.day.increment(time);
+        Ok(())
+    }
+
+    /// Requests counted for `consumer` this minute and today
+    #[must_use]
+    pub fn used(&self, consumer: &str, time: SystemTime) -> (u32, u32) {
+        let counters = self.counters.lock().expect("quota tracker poisoned");
+        counters.get(consumer).map_or((0, 0), |counters| {
+            (counters.minute.count(time), counters.day.count(time))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Quota, QuotaTracker, QuotaWindow};
+    use std::collections::HashMap;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn test_quota_windows() {
+        let quotas = HashMap::from([("bot".to_string(), Quota::unlimited().with_per_minute(2))]);
+        let tracker = QuotaTracker::new(Quota::unlimited().with_per_day(3), quotas);
+        // 2024-01-01 00:00:30 UTC
+        let time = SystemTime::UNIX_EPOCH + Duration::from_secs(1_704_067_230);
+
+        assert!(tracker.check("bot", time).is_ok());
+        assert!(tracker.check("bot", time).is_ok());
+        let exceeded = tracker.check("bot", time).unwrap_err();
+        assert_eq!(exceeded.window, QuotaWindow::Minute);
+        assert_eq!(exceeded.retry_after, Duration::from_secs(30));
+        assert_eq!(tracker.used("bot", time), (2, 2));
+
+        // A new minute starts the count over
+        let later = time + Duration::from_secs(30);
+        assert!(tracker.check("bot", later).is_ok());
+        assert_eq!(tracker.used("bot", later), (1, 3));
+
+        // Other consumers have the default quota
+        for _ in 0..3 {
+            assert!(tracker.check("site", time).is_ok());
+        }
+        let exceeded = tracker.check("site", later).unwrap_err();
+        assert_eq!((exceeded.window, exceeded.limit), (QuotaWindow::Day, 3));
+        assert!(tracker
+            .check("site", time + Duration::from_secs(86_400))
+            .is_ok());
+    }
+}
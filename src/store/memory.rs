@@ -0,0 +1,131 @@
+//! A store that keeps snapshots in memory, for tests and short lived processes
+
+use std::collections::{BTreeMap, HashMap};
+use std::time::SystemTime;
+
+use super::{SnapshotKind, Store};
+use crate::error::StoreError;
+use crate::models::timestamp::Timestamp;
+
+/// `time` rounded down to a whole millisecond, the precision stores keep
+fn millis(time: SystemTime) -> SystemTime {
+    Timestamp::from(time).as_system_time()
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStore {
+    snapshots: HashMap<(SnapshotKind, String), BTreeMap<SystemTime, serde_json::Value>>,
+}
+
+impl MemoryStore {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of snapshots of every kind
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.snapshots.values().map(BTreeMap::len).sum()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Store for MemoryStore {
+    fn insert(
+        &mut self,
+        kind: SnapshotKind,
+        tag: &str,
+        time: SystemTime,
+        data: &serde_json::Value,
+    ) -> Result<(), StoreError> {
+        self.snapshots
+            .entry((kind, tag.to_string()))
+            .or_default()
+            .insert(millis(time), data.clone());
+        Ok(())
+    }
+
+    fn range(
+        &self,
+        kind: SnapshotKind,
+        tag: &str,
+        from: SystemTime,
+        to: SystemTime,
+    ) -> Result<Vec<(SystemTime, serde_json::Value)>, StoreError> {
+        let (from, to) = (millis(from), millis(to));
+        if from > to {
+            return Ok(Vec::new());
+        }
+        Ok(self
+            .snapshots
+            .get(&(kind, tag.to_string()))
+            .map(|snapshots| {
+                snapshots
+                    .range(from..=to)
+                    .map(|(time, data)| (*time, data.clone()))
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    fn latest(
+        &self,
+        kind: SnapshotKind,
+        tag: &str,
+    ) -> Result<Option<(SystemTime, serde_json::Value)>, StoreError> {
+        Ok(self
+            .snapshots
+            .get(&(kind, tag.to_string()))
+            .and_then(|snapshots| snapshots.last_key_value())
+            .map(|(time, data)| (*time, data.clone())))
+    }
+
+    fn tags(&self, kind: SnapshotKind) -> Result<Vec<String>, StoreError> {
+        let mut tags: Vec<String> = self
+            .snapshots
+            .iter()
+            .filter(|((k, _), snapshots)| *k == kind && !snapshots.is_empty())
+            .map(|((_, tag), _)| tag.clone())
+            .collect();
+        tags.sort();
+        Ok(tags)
+    }
+
+    fn delete_before(&mut self, kind: SnapshotKind, time: SystemTime) -> Result<usize, StoreError> {
+        let time = millis(time);
+        let mut deleted = 0;
+        for ((k, _), snapshots) in &mut self.snapshots {
+            if *k == kind {
+                let kept = snapshots.split_off(&time);
+                deleted += snapshots.len();
+                *snapshots = kept;
+            }
+        }
+        self.snapshots.retain(|_, snapshots| !snapshots.is_empty());
+        Ok(deleted)
+    }
+
+    fn truncate(
+        &mut self,
+        kind: SnapshotKind,
+        tag: &str,
+        keep: usize,
+    ) -> Result<usize, StoreError> {
+        let Some(snapshots) = self.snapshots.get_mut(&(kind, tag.to_string())) else {
+            return Ok(0);
+        };
+        let excess = snapshots.len().saturating_sub(keep);
+        for _ in 0..excess {
+            snapshots.pop_first();
+        }
+        if snapshots.is_empty() {
+            self.snapshots.remove(&(kind, tag.to_string()));
+        }
+        Ok(excess)
+    }
+}
@@ -0,0 +1,344 @@
+//! Storage for timestamped snapshots of players, clans, wars and raid seasons.
+//! Snapshots are kept as JSON, keyed by their kind, a tag and the time they
+//! were taken. Wars and raid seasons are stored under the tag of the clan they
+//! were fetched for.
+//!
+//! [`Store`] is the low level interface a backend implements, [`StoreExt`]
+//! adds typed access on top of it for every store.
+
+pub mod memory;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+pub use memory::MemoryStore;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStore;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::time::{Duration, SystemTime};
+
+use crate::error::StoreError;
+use crate::models::clan::{Clan, ClanWar};
+use crate::models::clan_capital::ClanCapitalRaidSeason;
+use crate::models::player::Player;
+use crate::utils::normalize_tag;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SnapshotKind {
+    Player,
+    Clan,
+    War,
+    RaidSeason,
+}
+
+impl SnapshotKind {
+    pub const ALL: [Self; 4] = [Self::Player, Self::Clan, Self::War, Self::RaidSeason];
+
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Player => "player",
+            Self::Clan => "clan",
+            Self::War => "war",
+            Self::RaidSeason => "raid_season",
+        }
+    }
+}
+
+/// Models that can be stored as snapshots
+pub trait Snapshotted: Serialize + DeserializeOwned {
+    const KIND: SnapshotKind;
+}
+
+impl Snapshotted for Player {
+    const KIND: SnapshotKind = SnapshotKind::Player;
+}
+
+impl Snapshotted for Clan {
+    const KIND: SnapshotKind = SnapshotKind::Clan;
+}
+
+impl Snapshotted for ClanWar {
+    const KIND: SnapshotKind = SnapshotKind::War;
+}
+
+impl Snapshotted for ClanCapitalRaidSeason {
+    const KIND: SnapshotKind = SnapshotKind::RaidSeason;
+}
+
+#[derive(Debug, Clone)]
+pub struct Snapshot<T> {
+    pub tag: String,
+    pub time: SystemTime,
+    pub value: T,
+}
+
+/// How long snapshots are kept. Both limits apply when both are set.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Retention {
+    /// Snapshots older than this are deleted
+    pub max_age: Option<Duration>,
+    /// Only this many of the newest snapshots are kept per tag
+    pub max_snapshots: Option<usize>,
+}
+
+impl Retention {
+    /// Keep everything
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    #[must_use]
+    pub fn with_max_snapshots(mut self, max_snapshots: usize) -> Self {
+        self.max_snapshots = Some(max_snapshots);
+        self
+    }
+}
+
+/// A backend that stores raw snapshots. Times are kept in whole milliseconds,
+/// so two snapshots taken within the same millisecond are taken at the same
+/// time.
+pub trait Store {
+    /// Save a snapshot, replacing any of the same kind and tag taken at the
+    /// same time
+    fn insert(
+        &mut self,
+        kind: SnapshotKind,
+        tag: &str,
+        time: SystemTime,
+        data: &serde_json::Value,
+    ) -> Result<(), StoreError>;
+
+    /// Snapshots of a tag taken between `from` and `to`, both inclusive,
+    /// oldest first
+    fn range(
+        &self,
+        kind: SnapshotKind,
+        tag: &str,
+        from: SystemTime,
+        to: SystemTime,
+    ) -> Result<Vec<(SystemTime, serde_json::Value)>, StoreError>;
+
+    fn latest(
+        &self,
+        kind: SnapshotKind,
+        tag: &str,
+    ) -> Result<Option<(SystemTime, serde_json::Value)>, StoreError>;
+
+    /// Every tag with snapshots of a kind, sorted
+    fn tags(&self, kind: SnapshotKind) -> Result<Vec<String>, StoreError>;
+
+    /// Delete snapshots taken before `time`, returns how many were deleted
+    fn delete_before(&mut self, kind: SnapshotKind, time: SystemTime) -> Result<usize, StoreError>;
+
+    /// Delete all but the `keep` newest snapshots of a tag, returns how many
+    /// were deleted
+    fn truncate(&mut self, kind: SnapshotKind, tag: &str, keep: usize)
+        -> Result<usize, StoreError>;
+
+    /// Delete the snapshots of a kind that `retention` no longer keeps,
+    /// returns how many were deleted
+    fn apply_retention(
+        &mut self,
+        kind: SnapshotKind,
+        retention: &Retention,
+        now: SystemTime,
+    ) -> Result<usize, StoreError> {
+        let mut deleted = 0;
+        if let Some(cutoff) = retention.max_age.and_then(|age| now.checked_sub(age)) {
+            deleted += self.delete_before(kind, cutoff)?;
+        }
+        if let Some(keep) = retention.max_snapshots {
+            for tag in self.tags(kind)? {
+                deleted += self.truncate(kind, &tag, keep)?;
+            }
+        }
+        Ok(deleted)
+    }
+}
+
+/// Typed access to any [`Store`]. Tags are normalized, so `#2pg` and `2PG`
+/// refer to the same snapshots.
+pub trait StoreExt: Store {
+    fn save<T: Snapshotted>(
+        &mut self,
+        tag: &str,
+        time: SystemTime,
+        value: &T,
+    ) -> Result<(), StoreError> {
+        let data = serde_json::to_value(value)?;
+        self.insert(T::KIND, &normalize_tag(tag), time, &data)
+    }
+
+    fn save_player(&mut self, player: &Player, time: SystemTime) -> Result<(), StoreError> {
+        self.save(&player.tag, time, player)
+    }
+
+    fn save_clan(&mut self, clan: &Clan, time: SystemTime) -> Result<(), StoreError> {
+        self.save(&clan.tag, time, clan)
+    }
+
+    /// Snapshots of a tag taken between `from` and `to`, both inclusive,
+    /// oldest first
+    fn snapshots<T: Snapshotted>(
+        &self,
+        tag: &str,
+        from: SystemTime,
+        to: SystemTime,
+    ) -> Result<Vec<Snapshot<T>>, StoreError> {
+        let tag = normalize_tag(tag);
+        self.range(T::KIND, &tag, from, to)?
+            .into_iter()
+            .map(|(time, data)| {
+                Ok(Snapshot {
+                    tag: tag.clone(),
+                    time,
+                    value: serde_json::from_value(data)?,
+                })
+            })
+            .collect()
+    }
+
+    fn latest_snapshot<T: Snapshotted>(
+        &self,
+        tag: &str,
+    ) -> Result<Option<Snapshot<T>>, StoreError> {
+        let tag = normalize_tag(tag);
+        self.latest(T::KIND, &tag)?
+            .map(|(time, data)| {
+                Ok(Snapshot {
+                    tag,
+                    time,
+                    value: serde_json::from_value(data)?,
+                })
+            })
+            .transpose()
+    }
+}
+
+impl<S: Store + ?Sized> StoreExt for S {}
+
+#[cfg(test)]
+mod test {
+    use super::{MemoryStore, Retention, SnapshotKind, Store, StoreExt};
+    use crate::models::clan::{Clan, ClanWar};
+    use crate::models::player::Player;
+    use crate::utils::time::parse_api_time;
+    use std::time::{Duration, SystemTime};
+
+    fn time(value: &str) -> SystemTime {
+        parse_api_time(value).unwrap()
+    }
+
+    /// Runs the same checks against every backend
+    fn check_store(mut store: impl Store) {
+        let mut player: Player =
+            serde_json::from_str(include_str!("../../tests/fixtures/player.json")).unwrap();
+        let clan: Clan =
+            serde_json::from_str(include_str!("../../tests/fixtures/clan.json")).unwrap();
+        let war: ClanWar =
+            serde_json::from_str(include_str!("../../tests/fixtures/current_war.json")).unwrap();
+
+        for day in 1..=5 {
+            player.trophies = 5000 + day;
+            let now = time(&format!("202401{day:02}T000000.000Z"));
+            store.save_player(&player, now).unwrap();
+        }
+        store
+            .save_clan(&clan, time("20240101T000000.000Z"))
+            .unwrap();
+        store
+            .save("#2pg", time("20240101T000000.000Z"), &war)
+            .unwrap();
+
+        let snapshots = store
+            .snapshots::<Player>(
+                "p0",
+                time("20240102T000000.000Z"),
+                time("20240104T000000.000Z"),
+            )
+            .unwrap();
+        let trophies: Vec<u32> = snapshots.iter().map(|s| s.value.trophies).collect();
+        assert_eq!(trophies, [5002, 5003, 5004]);
+        assert_eq!(snapshots[0].tag, "#P0");
+
+        let latest = store.latest_snapshot::<Player>("#P0").unwrap().unwrap();
+        assert_eq!(latest.value.trophies, 5005);
+        assert!(store.latest_snapshot::<Player>("#NONE").unwrap().is_none());
+        assert_eq!(
+            store
+                .latest_snapshot::<ClanWar>("#2PG")
+                .unwrap()
+                .unwrap()
+                .value
+                .team_size,
+            war.team_size
+        );
+        assert_eq!(store.tags(SnapshotKind::Clan).unwrap(), vec![clan.tag.clone()]);
+
+        // Saving at the same time replaces the snapshot
+        player.trophies = 6000;
+        store
+            .save_player(&player, time("20240105T000000.000Z"))
+            .unwrap();
+        let latest = store.latest_snapshot::<Player>("#P0").unwrap().unwrap();
+        assert_eq!(latest.value.trophies, 6000);
+
+        // Times are kept in whole milliseconds
+        player.trophies = 6001;
+        let fifth = time("20240105T000000.000Z");
+        store
+            .save_player(&player, fifth + Duration::from_micros(400))
+            .unwrap();
+        let latest = store.latest_snapshot::<Player>("#P0").unwrap().unwrap();
+        assert_eq!(latest.value.trophies, 6001);
+        assert_eq!(latest.time, fifth);
+        let fifth_only = store
+            .snapshots::<Player>("#P0", fifth + Duration::from_micros(100), fifth)
+            .unwrap();
+        assert_eq!(fifth_only.len(), 1);
+
+        let now = time("20240106T000000.000Z");
+        let retention = Retention::new().with_max_age(Duration::from_secs(4 * 86_400));
+        assert_eq!(
+            store
+                .apply_retention(SnapshotKind::Player, &retention, now)
+                .unwrap(),
+            1
+        );
+        let retention = Retention::new().with_max_snapshots(2);
+        assert_eq!(
+            store
+                .apply_retention(SnapshotKind::Player, &retention, now)
+                .unwrap(),
+            2
+        );
+        let all = store
+            .snapshots::<Player>("#P0", SystemTime::UNIX_EPOCH, now)
+            .unwrap();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].value.trophies, 5004);
+
+        // Other kinds are left alone
+        assert!(store.latest_snapshot::<Clan>(&clan.tag).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_memory_store() {
+        check_store(MemoryStore::new());
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_sqlite_store() {
+        check_store(super::SqliteStore::open_in_memory().unwrap());
+    }
+}
@@ -0,0 +1,140 @@
+//! A store backed by a SQLite database. Every snapshot is a row of the
+//! `snapshots` table, with its time in milliseconds since the unix epoch and
+//! its data as JSON.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::Path;
+use std::time::SystemTime;
+
+use super::{SnapshotKind, Store};
+use crate::error::StoreError;
+use crate::utils::time::{from_unix, to_unix};
+
+const SCHEMA: &str = "CREATE TABLE IF NOT EXISTS snapshots (
+    kind TEXT NOT NULL,
+    tag TEXT NOT NULL,
+    time INTEGER NOT NULL,
+    data TEXT NOT NULL,
+    PRIMARY KEY (kind, tag, time)
+)";
+
+fn millis(time: SystemTime) -> i64 {
+    let (seconds, millis) = to_unix(time);
+    seconds * 1000 + millis as i64
+}
+
+fn time(millis: i64) -> Result<SystemTime, StoreError> {
+    from_unix(millis.div_euclid(1000), millis.rem_euclid(1000) as u64)
+        .ok_or(StoreError::InvalidTime(millis))
+}
+
+fn snapshot(row: (i64, String)) -> Result<(SystemTime, serde_json::Value), StoreError> {
+    Ok((time(row.0)?, serde_json::from_str(&row.1)?))
+}
+
+#[derive(Debug)]
+pub struct SqliteStore {
+    connection: Connection,
+}
+
+impl SqliteStore {
+    /// Open or create the database at `path`
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, StoreError> {
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self, StoreError> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    /// Use an existing connection, creating the table if it is missing
+    pub fn from_connection(connection: Connection) -> Result<Self, StoreError> {
+        connection.execute(SCHEMA, [])?;
+        Ok(Self { connection })
+    }
+}
+
+impl Store for SqliteStore {
+    fn insert(
+        &mut self,
+        kind: SnapshotKind,
+        tag: &str,
+        time: SystemTime,
+        data: &serde_json::Value,
+    ) -> Result<(), StoreError> {
+        self.connection.execute(
+            "INSERT OR REPLACE INTO snapshots (kind, tag, time, data) VALUES (?1, ?2, ?3, ?4)",
+            params![kind.as_str(), tag, millis(time), data.to_string()],
+        )?;
+        Ok(())
+    }
+
+    fn range(
+        &self,
+        kind: SnapshotKind,
+        tag: &str,
+        from: SystemTime,
+        to: SystemTime,
+    ) -> Result<Vec<(SystemTime, serde_json::Value)>, StoreError> {
+        let mut statement = self.connection.prepare_cached(
+            "SELECT time, data FROM snapshots
+             WHERE kind = ?1 AND tag = ?2 AND time BETWEEN ?3 AND ?4
+             ORDER BY time",
+        )?;
+        let rows = statement.query_map(
+            params![kind.as_str(), tag, millis(from), millis(to)],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        rows.map(|row| snapshot(row?)).collect()
+    }
+
+    fn latest(
+        &self,
+        kind: SnapshotKind,
+        tag: &str,
+    ) -> Result<Option<(SystemTime, serde_json::Value)>, StoreError> {
+        self.connection
+            .query_row(
+                "SELECT time, data FROM snapshots
+                 WHERE kind = ?1 AND tag = ?2
+                 ORDER BY time DESC LIMIT 1",
+                params![kind.as_str(), tag],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?
+            .map(snapshot)
+            .transpose()
+    }
+
+    fn tags(&self, kind: SnapshotKind) -> Result<Vec<String>, StoreError> {
+        let mut statement = self
+            .connection
+            .prepare_cached("SELECT DISTINCT tag FROM snapshots WHERE kind = ?1 ORDER BY tag")?;
+        let tags = statement
+            .query_map(params![kind.as_str()], |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+        Ok(tags)
+    }
+
+    fn delete_before(&mut self, kind: SnapshotKind, time: SystemTime) -> Result<usize, StoreError> {
+        Ok(self.connection.execute(
+            "DELETE FROM snapshots WHERE kind = ?1 AND time < ?2",
+            params![kind.as_str(), millis(time)],
+        )?)
+    }
+
+    fn truncate(
+        &mut self,
+        kind: SnapshotKind,
+        tag: &str,
+        keep: usize,
+    ) -> Result<usize, StoreError> {
+        Ok(self.connection.execute(
+            "DELETE FROM snapshots WHERE kind = ?1 AND tag = ?2 AND time NOT IN (
+                 SELECT time FROM snapshots WHERE kind = ?1 AND tag = ?2
+                 ORDER BY time DESC LIMIT ?3
+             )",
+            params![kind.as_str(), tag, keep as i64],
+        )?)
+    }
+}
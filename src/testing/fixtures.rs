@@ -0,0 +1,67 @@
+//! The models in `tests/fixtures`, the data `MockApi::seed_fixtures` serves.
+//! The clan is `#2PP` and the player `#P0`.
+
+use std::collections::HashMap;
+
+use crate::models::clan::{Clan, ClanWar, ClanWarLeagueGroup, ClanWarLog};
+use crate::models::clan_capital::ClanCapitalRaidSeasons;
+use crate::models::player::Player;
+
+fn parse<T: serde::de::DeserializeOwned>(name: &str, json: &str) -> T {
+    serde_json::from_str(json).unwrap_or_else(|e| panic!("invalid fixture {name}: {e}"))
+}
+
+#[must_use]
+pub fn player() -> Player {
+    parse(
+        "player.json",
+        include_str!("../../tests/fixtures/player.json"),
+    )
+}
+
+#[must_use]
+pub fn clan() -> Clan {
+    parse("clan.json", include_str!("../../tests/fixtures/clan.json"))
+}
+
+/// A war on battle day
+#[must_use]
+pub fn current_war() -> ClanWar {
+    parse(
+        "current_war.json",
+        include_str!("../../tests/fixtures/current_war.json"),
+    )
+}
+
+#[must_use]
+pub fn war_log() -> ClanWarLog {
+    parse(
+        "war_log.json",
+        include_str!("../../tests/fixtures/war_log.json"),
+    )
+}
+
+#[must_use]
+pub fn raid_seasons() -> ClanCapitalRaidSeasons {
+    parse(
+        "raid_seasons.json",
+        include_str!("../../tests/fixtures/raid_seasons.json"),
+    )
+}
+
+#[must_use]
+pub fn league_group() -> ClanWarLeagueGroup {
+    parse(
+        "cwl_group.json",
+        include_str!("../../tests/fixtures/cwl_group.json"),
+    )
+}
+
+/// The wars of `league_group`, keyed by war tag
+#[must_use]
+pub fn league_wars() -> HashMap<String, ClanWar> {
+    parse(
+        "cwl_wars.json",
+        include_str!("../../tests/fixtures/cwl_wars.json"),
+    )
+}
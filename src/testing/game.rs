@@ -0,0 +1,49 @@
+//! The fake game API: every `/v1` request goes through `GameApi`, which
+//! answers with a simulated failure, the response of a seeded route, or a
+//! 404.
+
+use serde_json::json;
+use wiremock::{Request, Respond, ResponseTemplate};
+
+use super::{Scope, Shared};
+
+/// An error response in the shape the game API uses
+pub(crate) fn api_error(status: u16, reason: &str, message: &str) -> ResponseTemplate {
+    ResponseTemplate::new(status).set_body_json(json!({
+        "reason": reason,
+        "message": message,
+    }))
+}
+
+fn has_token(request: &Request) -> bool {
+    request
+        .headers
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("Bearer "))
+}
+
+pub(crate) struct GameApi(pub Shared);
+
+impl Respond for GameApi {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        let mut state = self.0.lock().expect("mock state poisoned");
+        if let Some(response) = state.take_failure(Scope::Game) {
+            return response;
+        }
+        if !has_token(request) {
+            return api_error(
+                403,
+                "accessDenied",
+                "Invalid authorization: API key is missing",
+            );
+        }
+
+        // Paths are matched as sent, with tags url encoded
+        let route = (request.method.to_string(), request.url.path().to_string());
+        match state.routes.get(&route) {
+            Some(responder) => responder.respond(request),
+            None => api_error(404, "notFound", "Resource was not found."),
+        }
+    }
+}
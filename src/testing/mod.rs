@@ -0,0 +1,505 @@
+//! A programmable fake of the Clash of Clans API for tests that can not reach
+//! the real one, enabled with the `testing` feature. One server fakes both
+//! the developer portal (under `/api`) and the game API (under `/v1`).
+//!
+//! Game endpoints serve the models they are seeded with, see
+//! `seed_fixtures` and the `add_*` methods, and answer anything else with a
+//! 404. The `fail_*` methods simulate the errors of the real API until the
+//! returned `Failure` is dropped.
+//!
+//! ```no_run
+//! # async fn run() {
+//! use rsclashapi::testing::MockApi;
+//!
+//! let api = MockApi::start().await;
+//! api.seed_fixtures();
+//! let client = api.client("token");
+//! let clan = client.get_clan("#2PP").await.unwrap();
+//!
+//! let _maintenance = api.fail_maintenance();
+//! assert!(client.get_clan("#2PP").await.is_err());
+//! # }
+//! ```
+
+pub mod fixtures;
+mod game;
+mod portal;
+
+pub use portal::MAX_KEYS;
+
+use serde::Serialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use wiremock::matchers::{method, path, path_regex};
+use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+use crate::api::api;
+use crate::auth::credentials::Credential;
+use crate::auth::dev::{APIAccount, DEV_API_URL};
+use crate::auth::keys::Keys;
+use crate::core::client::{encode_tag, Client};
+use crate::models::clan::{Clan, ClanWar, ClanWarLeagueGroup, ClanWarLog};
+use crate::models::clan_capital::ClanCapitalRaidSeasons;
+use crate::models::player::Player;
+use crate::utils::{normalize_tag, IP_URL};
+use game::{api_error, GameApi};
+use portal::{
+    portal_error, CreateKeyResponder, ListKeys, Login, Logout, PortalState, RevokeKeyResponder,
+};
+
+/// Prefix of the developer portal endpoints
+pub const PORTAL_PREFIX: &str = "/api";
+/// Prefix of the game endpoints
+pub const API_PREFIX: &str = "/v1";
+/// Address the fake reports as the public ip of the machine
+pub const MOCK_IP: &str = "127.0.0.1";
+
+/// Requests a simulated failure applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Scope {
+    Game,
+    KeyCreate,
+}
+
+struct ActiveFailure {
+    id: u64,
+    scope: Scope,
+    response: ResponseTemplate,
+    /// Requests left to fail, `None` for all of them
+    remaining: Option<u64>,
+}
+
+#[derive(Default)]
+struct State {
+    portal: PortalState,
+    /// Game responses keyed by method and path
+    routes: HashMap<(String, String), Box<dyn Respond>>,
+    failures: Vec<ActiveFailure>,
+    next_failure: u64,
+}
+
+impl State {
+    /// The response of the latest failure of `scope`, if one is active
+    fn take_failure(&mut self, scope: Scope) -> Option<ResponseTemplate> {
+        let index = self.failures.iter().rposition(|f| f.scope == scope)?;
+        let failure = &mut self.failures[index];
+        let response = failure.response.clone();
+        if let Some(remaining) = &mut failure.remaining {
+            *remaining -= 1;
+            if *remaining == 0 {
+                self.failures.remove(index);
+            }
+        }
+        Some(response)
+    }
+}
+
+type Shared = Arc<Mutex<State>>;
+
+/// A simulated failure, it lasts until this is dropped
+#[must_use = "the failure ends when this is dropped"]
+pub struct Failure {
+    state: Shared,
+    id: u64,
+}
+
+impl Drop for Failure {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.failures.retain(|failure| failure.id != self.id);
+        }
+    }
+}
+
+/// The portal installed by `MockApi::install_portal`, the previous URLs are
+/// restored when this is dropped
+#[must_use = "the previous URLs are restored when this is dropped"]
+pub struct InstalledPortal {
+    dev_api_url: String,
+    ip_url: String,
+}
+
+impl Drop for InstalledPortal {
+    fn drop(&mut self) {
+        if let Ok(mut url) = DEV_API_URL.lock() {
+            *url = std::mem::take(&mut self.dev_api_url);
+        }
+        if let Ok(mut url) = IP_URL.lock() {
+            *url = std::mem::take(&mut self.ip_url);
+        }
+    }
+}
+
+pub struct MockApi {
+    server: MockServer,
+    state: Shared,
+}
+
+impl MockApi {
+    /// Start a server with the portal endpoints and no game data
+    pub async fn start() -> Self {
+        let server = MockServer::start().await;
+        let state = Shared::default();
+
+        let portal_endpoint = |endpoint: &str| {
+            Mock::given(method("POST")).and(path(format!("{PORTAL_PREFIX}{endpoint}")))
+        };
+        portal_endpoint(APIAccount::LOGIN_ENDPOINT)
+            .respond_with(Login(state.clone()))
+            .mount(&server)
+            .await;
+        portal_endpoint(APIAccount::LOGOUT_ENDPOINT)
+            .respond_with(Logout)
+            .mount(&server)
+            .await;
+        portal_endpoint(APIAccount::KEY_LIST_ENDPOINT)
+            .respond_with(ListKeys(state.clone()))
+            .mount(&server)
+            .await;
+        portal_endpoint(APIAccount::KEY_CREATE_ENDPOINT)
+            .respond_with(CreateKeyResponder(state.clone()))
+            .mount(&server)
+            .await;
+        portal_endpoint(APIAccount::KEY_REVOKE_ENDPOINT)
+            .respond_with(RevokeKeyResponder(state.clone()))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/ip"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(MOCK_IP))
+            .mount(&server)
+            .await;
+        Mock::given(path_regex(format!("^{API_PREFIX}/")))
+            .respond_with(GameApi(state.clone()))
+            .mount(&server)
+            .await;
+
+        Self { server, state }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().expect("mock state poisoned")
+    }
+
+    #[must_use]
+    pub fn uri(&self) -> String {
+        self.server.uri()
+    }
+
+    /// Base url of the game API, for `Client::with_base_url`
+    #[must_use]
+    pub fn api_url(&self) -> String {
+        format!("{}{API_PREFIX}", self.server.uri())
+    }
+
+    /// Base url of the developer portal
+    #[must_use]
+    pub fn portal_url(&self) -> String {
+        format!("{}{PORTAL_PREFIX}", self.server.uri())
+    }
+
+    /// A client for the game API of this server
+    #[must_use]
+    pub fn client(&self, token: &str) -> Client {
+        Client::new(token).with_base_url(self.api_url())
+    }
+
+    /// Point `APIAccount` and the public ip lookup at this server until the
+    /// returned guard is dropped. They are configured globally, so tests using
+    /// them must not run concurrently with tests that use another server.
+    pub fn install_portal(&self) -> InstalledPortal {
+        let dev_api_url = std::mem::replace(&mut *DEV_API_URL.lock().unwrap(), self.portal_url());
+        let ip_url = std::mem::replace(
+            &mut *IP_URL.lock().unwrap(),
+            format!("{}/ip", self.server.uri()),
+        );
+        InstalledPortal {
+            dev_api_url,
+            ip_url,
+        }
+    }
+
+    /// Only accept logins with these credentials, any are accepted otherwise
+    pub fn set_credentials(&self, email: &str, password: &str) {
+        self.state().portal.credentials = Some(Credential {
+            email: email.to_string(),
+            password: password.to_string(),
+        });
+    }
+
+    /// The keys of the developer account
+    #[must_use]
+    pub fn keys(&self) -> Keys {
+        Keys {
+            keys: self.state().portal.keys.clone(),
+        }
+    }
+
+    /// Every request the server received
+    pub async fn received_requests(&self) -> Vec<Request> {
+        self.server.received_requests().await.unwrap_or_default()
+    }
+
+    /// Answer requests to a game `endpoint`, e.g. one of `api::api`, with
+    /// `responder`. Replaces what the endpoint answered before.
+    pub fn add_route(&self, method: &str, endpoint: &str, responder: impl Respond + 'static) {
+        let endpoint = endpoint.split('?').next().unwrap_or_default();
+        self.state().routes.insert(
+            (method.to_uppercase(), format!("{API_PREFIX}{endpoint}")),
+            Box::new(responder),
+        );
+    }
+
+    /// Serve `body` as the response of a game `endpoint`
+    pub fn add_json<T: Serialize + ?Sized>(&self, endpoint: &str, body: &T) {
+        let response = ResponseTemplate::new(200)
+            .insert_header("Cache-Control", "max-age=60")
+            .set_body_json(body);
+        self.add_route("GET", endpoint, response);
+    }
+
+    fn add_items<T: Serialize + ?Sized>(&self, endpoint: &str, items: &T) {
+        self.add_json(
+            endpoint,
+            &json!({ "items": items, "paging": { "cursors": {} } }),
+        );
+    }
+
+    pub fn add_player(&self, player: &Player) {
+        self.add_json(&api::player_endpoint(&encode_tag(&player.tag)), player);
+    }
+
+    /// Serve the clan and its members
+    pub fn add_clan(&self, clan: &Clan) {
+        let tag = encode_tag(&clan.tag);
+        self.add_json(&api::clan_endpoint(&tag), clan);
+        if let Some(members) = &clan.member_list {
+            self.add_items(&api::clan_members_endpoint(&tag), members);
+        }
+    }
+
+    pub fn add_current_war(&self, clan_tag: &str, war: &ClanWar) {
+        self.add_json(&api::curr_war_endpoint(&encode_tag(clan_tag)), war);
+    }
+
+    /// Answer the current war of the clan as not being in a war
+    pub fn add_not_in_war(&self, clan_tag: &str) {
+        self.add_json(
+            &api::curr_war_endpoint(&encode_tag(clan_tag)),
+            &json!({ "state": "notInWar" }),
+        );
+    }
+
+    /// Answer the current war of the clan with a private war log
+    pub fn add_private_war_log(&self, clan_tag: &str) {
+        self.add_route(
+            "GET",
+            &api::curr_war_endpoint(&encode_tag(clan_tag)),
+            api_error(
+                403,
+                "accessDenied",
+                "Access denied, clan war log is private.",
+            ),
+        );
+    }
+
+    pub fn add_war_log(&self, clan_tag: &str, log: &ClanWarLog) {
+        self.add_items(&api::warlog_endpoint(&encode_tag(clan_tag)), log);
+    }
+
+    pub fn add_raid_seasons(&self, clan_tag: &str, seasons: &ClanCapitalRaidSeasons) {
+        let endpoint = api::clan_capital_raid_seasons_endpoint(&encode_tag(clan_tag));
+        self.add_items(&endpoint, seasons);
+    }
+
+    pub fn add_league_group(&self, clan_tag: &str, group: &ClanWarLeagueGroup) {
+        self.add_json(&api::league_group_endpoint(&encode_tag(clan_tag)), group);
+    }
+
+    pub fn add_league_war(&self, war_tag: &str, war: &ClanWar) {
+        self.add_json(&api::warleague_war_endpoint(&encode_tag(war_tag)), war);
+    }
+
+    /// Accept `token` when verifying the player's token, other tokens are
+    /// invalid
+    pub fn add_player_token(&self, player_tag: &str, token: &str) {
+        let tag = normalize_tag(player_tag);
+        let token = token.to_string();
+        let responder = move |request: &Request| {
+            let sent = request
+                .body_json::<serde_json::Value>()
+                .ok()
+                .and_then(|body| body["token"].as_str().map(str::to_string))
+                .unwrap_or_default();
+            let status = if sent == token { "ok" } else { "invalid" };
+            ResponseTemplate::new(200).set_body_json(json!({
+                "tag": tag,
+                "token": sent,
+                "status": status,
+            }))
+        };
+        self.add_route(
+            "POST",
+            &api::verify_token_endpoint(&encode_tag(player_tag)),
+            responder,
+        );
+    }
+
+    /// Serve the models of `fixtures`: the player, the clan with its members,
+    /// current war, war log, raid seasons, league group and league wars
+    pub fn seed_fixtures(&self) {
+        let clan = fixtures::clan();
+        self.add_player(&fixtures::player());
+        self.add_clan(&clan);
+        self.add_current_war(&clan.tag, &fixtures::current_war());
+        self.add_war_log(&clan.tag, &fixtures::war_log());
+        self.add_raid_seasons(&clan.tag, &fixtures::raid_seasons());
+        self.add_league_group(&clan.tag, &fixtures::league_group());
+        for (war_tag, war) in fixtures::league_wars() {
+            self.add_league_war(&war_tag, &war);
+        }
+    }
+
+    /// Answer the requests of `scope` with `response`, the latest failure
+    /// takes precedence
+    fn fail(&self, scope: Scope, response: ResponseTemplate, times: Option<u64>) -> Failure {
+        let mut state = self.state();
+        state.next_failure += 1;
+        let id = state.next_failure;
+        state.failures.push(ActiveFailure {
+            id,
+            scope,
+            response,
+            remaining: times.filter(|&times| times > 0),
+        });
+        Failure {
+            state: self.state.clone(),
+            id,
+        }
+    }
+
+    /// Reject game requests as coming from an ip the key does not allow
+    pub fn fail_invalid_ip(&self, ip: &str) -> Failure {
+        let message = format!("Invalid authorization: API key does not allow access from IP {ip}");
+        let response = api_error(403, "accessDenied.invalidIp", &message);
+        self.fail(Scope::Game, response, None)
+    }
+
+    /// Throttle the next `times` game requests, or all of them when `None`
+    pub fn fail_throttled(&self, times: Option<u64>) -> Failure {
+        let response = api_error(
+            429,
+            "requestThrottled",
+            "Request was throttled, because amount of requests was above the threshold defined for the used API token.",
+        );
+        self.fail(Scope::Game, response, times)
+    }
+
+    /// Answer game requests as if the API was down for maintenance
+    pub fn fail_maintenance(&self) -> Failure {
+        let response = api_error(
+            503,
+            "inMaintenance",
+            "Service is temporarily unavailable because of maintenance.",
+        );
+        self.fail(Scope::Game, response, None)
+    }
+
+    /// Fail creating keys as if the account had `MAX_KEYS` already
+    pub fn fail_key_limit(&self) -> Failure {
+        let response = portal_error(400, "Maximum number of keys reached");
+        self.fail(Scope::KeyCreate, response, None)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{fixtures, MockApi, MAX_KEYS};
+    use crate::auth::dev::{APIAccount, DEV_API_URL};
+    use crate::error::APIError;
+    use crate::models::clan::CurrentWar;
+
+    #[tokio::test]
+    async fn test_game_endpoints() {
+        let api = MockApi::start().await;
+        api.seed_fixtures();
+        let client = api.client("token");
+
+        let clan = client.get_clan("#2pp").await.unwrap();
+        assert_eq!(clan.tag, fixtures::clan().tag);
+        let members = client.get_clan_members("#2PP").await.unwrap();
+        assert!(!members.0.is_empty());
+        let war = client.get_current_war("#2PP").await.unwrap();
+        assert!(matches!(war, CurrentWar::InWar(_)));
+        assert!(client.get_player("#P0").await.is_ok());
+        assert!(matches!(
+            client.get_player("#P2").await,
+            Err(APIError::NotFound)
+        ));
+
+        {
+            let _guard = api.fail_throttled(Some(1));
+            assert!(matches!(
+                client.get_clan("#2PP").await,
+                Err(APIError::Throttle)
+            ));
+            assert!(client.get_clan("#2PP").await.is_ok());
+        }
+        {
+            let _guard = api.fail_maintenance();
+            assert!(matches!(
+                client.get_clan("#2PP").await,
+                Err(APIError::Maintenance)
+            ));
+        }
+        {
+            let _guard = api.fail_invalid_ip("10.0.0.1");
+            assert!(matches!(
+                client.get_current_war("#2PP").await,
+                Err(APIError::AccessDenied)
+            ));
+        }
+        assert!(client.get_clan("#2PP").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_developer_portal() {
+        let api = MockApi::start().await;
+        api.set_credentials("dev@example.com", "secret");
+        let previous = DEV_API_URL.lock().unwrap().clone();
+        let portal = api.install_portal();
+
+        assert!(APIAccount::login("dev@example.com", "wrong").await.is_err());
+        let mut account = APIAccount::login("dev@example.com", "secret")
+            .await
+            .unwrap();
+        assert!(account.keys.is_empty());
+
+        let key = APIAccount::create_key("bot", &mut account).await.unwrap();
+        assert_eq!(key.cidr_ranges, [super::MOCK_IP]);
+        assert_eq!(api.keys().len(), 1);
+        assert_eq!(APIAccount::list_keys().await.unwrap().len(), 1);
+
+        APIAccount::revoke_key(&key.id, &mut account).await.unwrap();
+        assert!(api.keys().is_empty());
+        assert!(account.keys.is_empty());
+
+        {
+            let _guard = api.fail_key_limit();
+            assert!(APIAccount::create_key("bot", &mut account).await.is_err());
+        }
+        for i in 0..MAX_KEYS {
+            APIAccount::create_key(&format!("bot-{i}"), &mut account)
+                .await
+                .unwrap();
+        }
+        assert!(APIAccount::create_key("one-too-many", &mut account)
+            .await
+            .is_err());
+        assert_eq!(account.keys.len(), MAX_KEYS);
+
+        drop(portal);
+        assert_eq!(*DEV_API_URL.lock().unwrap(), previous);
+    }
+}
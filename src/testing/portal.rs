@@ -0,0 +1,178 @@
+//! A fake developer portal: logging in and listing, creating and revoking
+//! keys, with the keys kept in memory.
+
+use serde::Deserialize;
+use serde_json::json;
+use wiremock::{Request, Respond, ResponseTemplate};
+
+use super::{Scope, Shared};
+use crate::auth::credentials::Credential;
+use crate::auth::dev::{Auth, Developer, LoginResponse, LogoutResponse};
+use crate::auth::keys::Key;
+use crate::models::status::Status;
+
+/// Keys an account can have, creating more fails
+pub const MAX_KEYS: usize = 10;
+
+const DEVELOPER_ID: &str = "mock-developer";
+const SESSION_SECONDS: u32 = 3600;
+
+fn ok() -> Status {
+    Status {
+        code: 0,
+        message: "ok".to_string(),
+        detail: None,
+    }
+}
+
+/// An error response in the shape the portal uses
+pub(crate) fn portal_error(code: u16, message: &str) -> ResponseTemplate {
+    ResponseTemplate::new(code).set_body_json(json!({
+        "status": { "code": code, "message": message, "detail": null },
+        "sessionExpiresInSeconds": 0
+    }))
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct PortalState {
+    /// Accepted credentials, any are accepted when `None`
+    pub credentials: Option<Credential>,
+    pub keys: Vec<Key>,
+    pub created: u32,
+}
+
+pub(crate) struct Login(pub Shared);
+
+impl Respond for Login {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        let Ok(credential) = request.body_json::<Credential>() else {
+            return portal_error(400, "Invalid request body");
+        };
+        let state = self.0.lock().expect("mock state poisoned");
+        let accepted = state.portal.credentials.as_ref().is_none_or(|expected| {
+            expected.email == credential.email && expected.password == credential.password
+        });
+        if !accepted {
+            return portal_error(403, "Invalid credentials");
+        }
+
+        ResponseTemplate::new(200).set_body_json(LoginResponse {
+            status: ok(),
+            session_expires_in_seconds: SESSION_SECONDS,
+            auth: Some(Auth {
+                uid: DEVELOPER_ID.to_string(),
+                token: "mock-session-token".to_string(),
+                ua: None,
+                ip: None,
+            }),
+            developer: Developer {
+                id: DEVELOPER_ID.to_string(),
+                name: "Mock Developer".to_string(),
+                game: "clashofclans".to_string(),
+                email: credential.email,
+                tier: "developer/silver".to_string(),
+                allowed_scopes: None,
+                max_cidrs: None,
+                prev_login_ts: None,
+                prev_login_ip: None,
+                prev_login_ua: None,
+            },
+            temporary_api_token: "mock-temporary-token".to_string(),
+            swagger_url: "https://developer.clashofclans.com/api/swagger".to_string(),
+        })
+    }
+}
+
+pub(crate) struct Logout;
+
+impl Respond for Logout {
+    fn respond(&self, _: &Request) -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(LogoutResponse {
+            status: ok(),
+            session_expires_in_seconds: 0,
+        })
+    }
+}
+
+pub(crate) struct ListKeys(pub Shared);
+
+impl Respond for ListKeys {
+    fn respond(&self, _: &Request) -> ResponseTemplate {
+        let state = self.0.lock().expect("mock state poisoned");
+        ResponseTemplate::new(200).set_body_json(json!({
+            "status": ok(),
+            "sessionExpiresInSeconds": SESSION_SECONDS,
+            "keys": state.portal.keys,
+        }))
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateKey {
+    name: String,
+    description: Option<String>,
+    #[serde(default)]
+    cidr_ranges: Vec<String>,
+}
+
+pub(crate) struct CreateKeyResponder(pub Shared);
+
+impl Respond for CreateKeyResponder {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        let mut state = self.0.lock().expect("mock state poisoned");
+        if let Some(response) = state.take_failure(Scope::KeyCreate) {
+            return response;
+        }
+        let Ok(body) = request.body_json::<CreateKey>() else {
+            return portal_error(400, "Invalid request body");
+        };
+        let state = &mut state.portal;
+        if state.keys.len() >= MAX_KEYS {
+            return portal_error(400, "Maximum number of keys reached");
+        }
+
+        state.created += 1;
+        let id = state.created.to_string();
+        let key = Key {
+            key: format!("mock-key-{id}"),
+            id,
+            developer_id: DEVELOPER_ID.to_string(),
+            tier: "developer/silver".to_string(),
+            name: body.name,
+            description: body.description.unwrap_or_default(),
+            origins: None,
+            scopes: vec!["clash".to_string()],
+            cidr_ranges: body.cidr_ranges,
+            valid_until: None,
+        };
+        state.keys.push(key.clone());
+        ResponseTemplate::new(200).set_body_json(key)
+    }
+}
+
+#[derive(Deserialize)]
+struct RevokeKey {
+    id: String,
+}
+
+pub(crate) struct RevokeKeyResponder(pub Shared);
+
+impl Respond for RevokeKeyResponder {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        let Ok(body) = request.body_json::<RevokeKey>() else {
+            return portal_error(400, "Invalid request body");
+        };
+        let mut state = self.0.lock().expect("mock state poisoned");
+        let state = &mut state.portal;
+        let count = state.keys.len();
+        state.keys.retain(|key| key.id != body.id);
+        if state.keys.len() == count {
+            return portal_error(404, "Key not found");
+        }
+        ResponseTemplate::new(200).set_body_json(LogoutResponse {
+            status: ok(),
+            session_expires_in_seconds: SESSION_SECONDS as i32,
+        })
+    }
+}
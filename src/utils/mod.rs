@@ -1,16 +1,32 @@
+use lazy_static::lazy_static;
+use std::sync::Mutex;
+
+use crate::models::tag::PlayerTag;
+
+pub mod time;
+
+lazy_static! {
+    /// Service `get_ip` asks for the public ip address
+    pub static ref IP_URL: Mutex<String> = Mutex::new(String::from("https://api.ipify.org"));
+}
+
 /// Formats a player or clan tag to begin with the url encoding for a hashtag
 /// i.e. '#' -> '%23'
 pub fn format_tag(tag: &str) -> String {
-    if tag.starts_with('#') {
-        format!("%23{}", &tag[1..])
-    } else {
-        format!("%23{}", tag)
-    }
+    format!("%23{}", tag.strip_prefix('#').unwrap_or(tag))
+}
+
+/// Normalizes a player or clan tag the way [`PlayerTag::new`] does. A tag with
+/// characters no tag can have is only trimmed, use [`PlayerTag::new`] to
+/// reject it instead.
+pub fn normalize_tag(tag: &str) -> String {
+    PlayerTag::new(tag).map_or_else(|_| tag.trim().to_string(), String::from)
 }
 
 /// Get a users public ip address
 pub async fn get_ip() -> Result<String, Box<dyn std::error::Error>> {
-    let response = reqwest::get("https://api.ipify.org")
+    let url = IP_URL.lock().unwrap().clone();
+    let response = reqwest::get(url)
         .await
         .map_err(|e| format!("Failed to fetch ip: {}", e))?;
     let ip = response
@@ -32,10 +48,8 @@ pub mod test_utils {
         let email = env::var("EMAIL").expect("EMAIL environment variable missing");
         let password = env::var("PASSWORD").expect("PASSWORD environment variable missing");
 
-        let credentials = Credentials::builder()
+        Credentials::builder()
             .add_credential(email, password)
-            .build();
-
-        credentials
+            .build()
     }
 }
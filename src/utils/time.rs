@@ -0,0 +1,219 @@
+//! Date helpers for the timestamps returned by the API. Times are always UTC
+//! and formatted like `20240101T000000.000Z`.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub const SECONDS_PER_DAY: i64 = 86_400;
+
+/// Hour of the day (UTC) at which seasons and legend league days roll over
+pub const RESET_HOUR: i64 = 5;
+
+/// Parse an API timestamp, e.g. `20240101T000000.000Z`
+#[must_use]
+pub fn parse_api_time(value: &str) -> Option<SystemTime> {
+    let value = value.strip_suffix('Z')?;
+    let (date, time) = value.split_once('T')?;
+    let (time, millis) = time.split_once('.').unwrap_or((time, "0"));
+
+    if date.len() != 8 || time.len() != 6 || !date.is_ascii() || !time.is_ascii() {
+        return None;
+    }
+
+    let year: i64 = date[0..4].parse().ok()?;
+    let month: u32 = date[4..6].parse().ok()?;
+    let day: u32 = date[6..8].parse().ok()?;
+    let hour: i64 = time[0..2].parse().ok()?;
+    let minute: i64 = time[2..4].parse().ok()?;
+    let second: i64 = time[4..6].parse().ok()?;
+    let millis: u64 = millis.parse().ok()?;
+
+    if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+        return None;
+    }
+    if hour > 23 || minute > 59 || second > 59 || millis > 999 {
+        return None;
+    }
+
+    let seconds =
+        days_from_civil(year, month, day) * SECONDS_PER_DAY + hour * 3600 + minute * 60 + second;
+    from_unix(seconds, millis)
+}
+
+/// Format a time the way the API does, e.g. `20240101T000000.000Z`
+#[must_use]
+pub fn format_api_time(time: SystemTime) -> String {
+    let (seconds, millis) = to_unix(time);
+    let (year, month, day) = civil_from_days(seconds.div_euclid(SECONDS_PER_DAY));
+    let secs = seconds.rem_euclid(SECONDS_PER_DAY);
+
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60,
+        millis
+    )
+}
+
+/// Seconds and milliseconds since the unix epoch
+#[must_use]
+pub fn to_unix(time: SystemTime) -> (i64, u64) {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(since) => (since.as_secs() as i64, u64::from(since.subsec_millis())),
+        Err(before) => {
+            let before = before.duration();
+            let mut seconds = -(before.as_secs() as i64);
+            let mut millis = u64::from(before.subsec_millis());
+            if millis > 0 {
+                seconds -= 1;
+                millis = 1000 - millis;
+            }
+            (seconds, millis)
+        }
+    }
+}
+
+/// Build a time from seconds and milliseconds since the unix epoch
+#[must_use]
+pub fn from_unix(seconds: i64, millis: u64) -> Option<SystemTime> {
+    let offset = Duration::from_millis(millis);
+    if seconds >= 0 {
+        UNIX_EPOCH.checked_add(Duration::from_secs(seconds as u64) + offset)
+    } else {
+        UNIX_EPOCH
+            .checked_sub(Duration::from_secs(seconds.unsigned_abs()))?
+            .checked_add(offset)
+    }
+}
+
+/// Duration from `now` until `time`, zero if `time` has already passed
+#[must_use]
+pub fn time_until(time: SystemTime, now: SystemTime) -> Duration {
+    time.duration_since(now).unwrap_or_default()
+}
+
+#[must_use]
+pub fn is_leap_year(year: i64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+#[must_use]
+pub fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01 for a date in the proleptic gregorian calendar
+#[must_use]
+pub fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = i64::from(month);
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Day of the week for a number of days since 1970-01-01, 0 being Monday
+#[must_use]
+pub fn weekday(days: i64) -> u32 {
+    // 1970-01-01 was a Thursday
+    (days + 3).rem_euclid(7) as u32
+}
+
+/// End of the season of `month`: the last Monday of the month at 05:00 UTC
+#[must_use]
+pub fn season_end(year: i64, month: u32) -> SystemTime {
+    let last_day = days_from_civil(year, month, days_in_month(year, month));
+    let last_monday = last_day - i64::from(weekday(last_day));
+    from_unix(last_monday * SECONDS_PER_DAY + RESET_HOUR * 3600, 0).unwrap_or(UNIX_EPOCH)
+}
+
+/// Year and month of the season `time` falls in
+#[must_use]
+pub fn season_of(time: SystemTime) -> (i64, u32) {
+    let (seconds, _) = to_unix(time);
+    let (year, month, _) = civil_from_days(seconds.div_euclid(SECONDS_PER_DAY));
+    if time < season_end(year, month) {
+        (year, month)
+    } else if month == 12 {
+        (year + 1, 1)
+    } else {
+        (year, month + 1)
+    }
+}
+
+/// Id of the season `time` falls in, in "YYYY-MM" form
+#[must_use]
+pub fn season_id(time: SystemTime) -> String {
+    let (year, month) = season_of(time);
+    format!("{:04}-{:02}", year, month)
+}
+
+/// Date (year, month, day) for a number of days since 1970-01-01
+#[must_use]
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        civil_from_days, days_from_civil, format_api_time, parse_api_time, season_end, season_id,
+    };
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_parse_api_time() {
+        let time = parse_api_time("20240101T000000.000Z").unwrap();
+        assert_eq!(time, UNIX_EPOCH + Duration::from_secs(1_704_067_200));
+
+        let time = parse_api_time("20240229T235959.250Z").unwrap();
+        assert_eq!(format_api_time(time), "20240229T235959.250Z");
+
+        assert!(parse_api_time("20230229T000000.000Z").is_none());
+        assert!(parse_api_time("2024-01-01").is_none());
+    }
+
+    #[test]
+    fn test_civil_round_trip() {
+        for days in [-719_468, -1, 0, 19_723, 2_932_896] {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+    }
+
+    #[test]
+    fn test_season() {
+        // January 2024 ends on Monday the 29th
+        let end = season_end(2024, 1);
+        assert_eq!(format_api_time(end), "20240129T050000.000Z");
+        assert_eq!(format_api_time(season_end(2024, 9)), "20240930T050000.000Z");
+
+        assert_eq!(season_id(end - Duration::from_secs(1)), "2024-01");
+        assert_eq!(season_id(end), "2024-02");
+        assert_eq!(
+            season_id(parse_api_time("20241231T000000.000Z").unwrap()),
+            "2025-01"
+        );
+    }
+}
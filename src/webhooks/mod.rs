@@ -0,0 +1,606 @@
+//! Delivers events to webhooks. Every event is POSTed as JSON to the
+//! endpoints whose filter accepts it, signed with the endpoint's secret.
+//! Failed deliveries are retried with exponential backoff and end up in a
+//! dead-letter queue once every attempt has failed.
+//!
+//! In test mode every delivery goes to a local [`TestReceiver`] instead of
+//! the configured URLs.
+
+pub mod receiver;
+pub mod signature;
+
+pub use receiver::{Received, TestReceiver};
+pub use signature::{sign, verify, SIGNATURE_HEADER};
+
+use std::collections::{HashSet, VecDeque};
+use std::time::{Duration, SystemTime};
+use tokio::sync::mpsc;
+use tokio::task::JoinSet;
+
+use crate::events::Event;
+use crate::models::timestamp::Timestamp;
+use crate::utils::normalize_tag;
+use crate::utils::time::to_unix;
+
+/// Header holding the kind of the event, e.g. "member.joined"
+pub const EVENT_HEADER: &str = "X-Webhook-Event";
+/// Header holding the id of the delivery, the same for every retry
+pub const DELIVERY_HEADER: &str = "X-Webhook-Delivery";
+/// Dead letters kept before the oldest are dropped
+pub const DEFAULT_DEAD_LETTER_CAPACITY: usize = 1000;
+
+/// Which events an endpoint receives. An empty filter accepts every event.
+#[derive(Debug, Clone, Default)]
+pub struct EventFilter {
+    kinds: Vec<String>,
+    tags: HashSet<String>,
+}
+
+impl EventFilter {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only accept these kinds of events. A kind ending in `.*` accepts every
+    /// kind of the group, e.g. "member.*", and "*" accepts everything.
+    #[must_use]
+    pub fn with_kinds(mut self, kinds: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.kinds.extend(kinds.into_iter().map(Into::into));
+        self
+    }
+
+    /// Only accept events of these clans or players
+    #[must_use]
+    pub fn with_tags(mut self, tags: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        self.tags
+            .extend(tags.into_iter().map(|tag| normalize_tag(tag.as_ref())));
+        self
+    }
+
+    #[must_use]
+    pub fn matches(&self, event: &Event) -> bool {
+        let kind = event.kind();
+        let kind_matches = self.kinds.is_empty()
+            || self.kinds.iter().any(|pattern| {
+                pattern == "*"
+                    || pattern == kind
+                    || pattern.strip_suffix(".*").is_some_and(|group| {
+                        kind.strip_prefix(group)
+                            .is_some_and(|rest| rest.starts_with('.'))
+                    })
+            });
+        kind_matches && (self.tags.is_empty() || self.tags.contains(event.tag()))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Endpoint {
+    pub url: String,
+    /// Secret the deliveries are signed with, unsigned if `None`
+    pub secret: Option<String>,
+    pub filter: EventFilter,
+}
+
+impl Endpoint {
+    #[must_use]
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            secret: None,
+            filter: EventFilter::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_secret(mut self, secret: &str) -> Self {
+        self.secret = Some(secret.to_string());
+        self
+    }
+
+    #[must_use]
+    pub fn with_filter(mut self, filter: EventFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts per delivery, including the first one
+    pub max_attempts: u32,
+    /// Wait after the first failed attempt, doubled after every further one
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Wait after `attempt` failed attempts
+    #[must_use]
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// A delivery that failed every attempt
+#[derive(Debug, Clone)]
+pub struct DeadLetter {
+    pub url: String,
+    pub id: String,
+    pub kind: String,
+    pub body: String,
+    pub attempts: u32,
+    /// Why the last attempt failed
+    pub error: String,
+    pub time: SystemTime,
+    secret: Option<String>,
+}
+
+/// A request to send, the same for every attempt
+#[derive(Debug, Clone)]
+struct Delivery {
+    url: String,
+    secret: Option<String>,
+    id: String,
+    kind: String,
+    body: String,
+}
+
+/// Why an attempt failed, and whether it is worth another one
+struct Failure {
+    error: String,
+    retry: bool,
+}
+
+async fn attempt(client: &reqwest::Client, delivery: &Delivery) -> Result<(), Failure> {
+    let mut request = client
+        .post(&delivery.url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(EVENT_HEADER, &delivery.kind)
+        .header(DELIVERY_HEADER, &delivery.id)
+        .body(delivery.body.clone());
+    if let Some(secret) = &delivery.secret {
+        request = request.header(
+            SIGNATURE_HEADER,
+            sign(secret.as_bytes(), delivery.body.as_bytes()),
+        );
+    }
+
+    let response = request.send().await.map_err(|e| Failure {
+        error: e.to_string(),
+        retry: true,
+    })?;
+    let status = response.status();
+    if status.is_success() {
+        return Ok(());
+    }
+    // Other client errors will not go away by sending the same request again
+    let retry = status.is_server_error()
+        || status == reqwest::StatusCode::REQUEST_TIMEOUT
+        || status == reqwest::StatusCode::TOO_MANY_REQUESTS;
+    Err(Failure {
+        error: format!("Receiver responded with {status}"),
+        retry,
+    })
+}
+
+/// Send a delivery until it succeeds or the attempts run out
+async fn deliver(
+    client: reqwest::Client,
+    retry: RetryPolicy,
+    delivery: Delivery,
+) -> Result<(), DeadLetter> {
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        match attempt(&client, &delivery).await {
+            Ok(()) => return Ok(()),
+            Err(failure) if failure.retry && attempts < retry.max_attempts => {
+                tokio::time::sleep(retry.backoff(attempts)).await;
+            }
+            Err(failure) => {
+                return Err(DeadLetter {
+                    url: delivery.url,
+                    id: delivery.id,
+                    kind: delivery.kind,
+                    body: delivery.body,
+                    attempts,
+                    error: failure.error,
+                    time: SystemTime::now(),
+                    secret: delivery.secret,
+                })
+            }
+        }
+    }
+}
+
+pub struct Dispatcher {
+    client: reqwest::Client,
+    endpoints: Vec<Endpoint>,
+    retry: RetryPolicy,
+    /// Receiver every delivery goes to in test mode
+    test_url: Option<String>,
+    dead_letters: VecDeque<DeadLetter>,
+    dead_letter_capacity: usize,
+    /// Number of events dispatched, part of every delivery id
+    sequence: u64,
+}
+
+impl Default for Dispatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Dispatcher {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            endpoints: Vec::new(),
+            retry: RetryPolicy::default(),
+            test_url: None,
+            dead_letters: VecDeque::new(),
+            dead_letter_capacity: DEFAULT_DEAD_LETTER_CAPACITY,
+            sequence: 0,
+        }
+    }
+
+    #[must_use]
+    pub fn with_endpoint(mut self, endpoint: Endpoint) -> Self {
+        self.endpoints.push(endpoint);
+        self
+    }
+
+    #[must_use]
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Keep at most `capacity` dead letters, dropping the oldest
+    #[must_use]
+    pub fn with_dead_letter_capacity(mut self, capacity: usize) -> Self {
+        self.dead_letter_capacity = capacity;
+        self
+    }
+
+    /// Test mode: send every delivery to `url`, e.g. a [`TestReceiver`],
+    /// instead of the endpoint's own URL. Filters and secrets still apply.
+    #[must_use]
+    pub fn with_test_receiver(mut self, url: &str) -> Self {
+        self.test_url = Some(url.to_string());
+        self
+    }
+
+    pub fn endpoints(&self) -> impl Iterator<Item = &Endpoint> {
+        self.endpoints.iter()
+    }
+
+    /// Deliver an event to every endpoint that accepts it, returns how many
+    /// deliveries succeeded. Deliveries that fail every attempt are moved to
+    /// the dead-letter queue.
+    pub async fn dispatch(&mut self, event: &Event) -> usize {
+        let deliveries = self
+            .deliveries(event)
+            .into_iter()
+            .map(|(_, delivery)| delivery)
+            .collect();
+        self.send(deliveries).await
+    }
+
+    /// Deliver every event received until the channel closes, e.g. the
+    /// events of `Poller::subscribe`. Every endpoint has a queue of its own,
+    /// so an endpoint that is down and being retried does not hold up the
+    /// deliveries to the others. Returns the dispatcher once every queue is
+    /// empty, with the deliveries that failed in its dead-letter queue.
+    pub async fn run(mut self, mut events: mpsc::UnboundedReceiver<Event>) -> Self {
+        let (failed, mut letters) = mpsc::unbounded_channel();
+        let queues: Vec<_> = self
+            .endpoints
+            .iter()
+            .map(|_| {
+                let (queue, mut pending) = mpsc::unbounded_channel();
+                let (client, retry, failed) = (self.client.clone(), self.retry, failed.clone());
+                tokio::spawn(async move {
+                    while let Some(delivery) = pending.recv().await {
+                        if let Err(letter) = deliver(client.clone(), retry, delivery).await {
+                            // Only fails when run was cancelled
+                            let _ = failed.send(letter);
+                        }
+                    }
+                });
+                queue
+            })
+            .collect();
+        drop(failed);
+
+        loop {
+            tokio::select! {
+                event = events.recv() => {
+                    let Some(event) = event else { break };
+                    for (index, delivery) in self.deliveries(&event) {
+                        // The queue only closes when its task panicked
+                        let _ = queues[index].send(delivery);
+                    }
+                }
+                Some(letter) = letters.recv() => self.add_dead_letter(letter),
+            }
+        }
+
+        // Let the queues drain, every task ends once its queue is empty
+        drop(queues);
+        while let Some(letter) = letters.recv().await {
+            self.add_dead_letter(letter);
+        }
+        self
+    }
+
+    /// Try every dead letter again, returns how many were delivered. The ones
+    /// that fail again go back to the queue.
+    pub async fn retry_dead_letters(&mut self) -> usize {
+        let deliveries = self
+            .dead_letters
+            .drain(..)
+            .map(|letter| Delivery {
+                url: letter.url,
+                secret: letter.secret,
+                id: letter.id,
+                kind: letter.kind,
+                body: letter.body,
+            })
+            .collect();
+        self.send(deliveries).await
+    }
+
+    pub fn dead_letters(&self) -> impl Iterator<Item = &DeadLetter> {
+        self.dead_letters.iter()
+    }
+
+    /// Empty the dead-letter queue, e.g. to persist or inspect it
+    pub fn take_dead_letters(&mut self) -> Vec<DeadLetter> {
+        self.dead_letters.drain(..).collect()
+    }
+
+    async fn send(&mut self, deliveries: Vec<Delivery>) -> usize {
+        let mut tasks = JoinSet::new();
+        for delivery in deliveries {
+            tasks.spawn(deliver(self.client.clone(), self.retry, delivery));
+        }
+
+        let mut delivered = 0;
+        while let Some(result) = tasks.join_next().await {
+            match result {
+                Ok(Ok(())) => delivered += 1,
+                Ok(Err(letter)) => self.add_dead_letter(letter),
+                // The delivery task panicked, there is nothing left to retry
+                Err(_) => {}
+            }
+        }
+        delivered
+    }
+
+    fn add_dead_letter(&mut self, letter: DeadLetter) {
+        if self.dead_letters.len() >= self.dead_letter_capacity {
+            self.dead_letters.pop_front();
+        }
+        self.dead_letters.push_back(letter);
+    }
+
+    /// Deliveries of an event, paired with the index of their endpoint
+    fn deliveries(&mut self, event: &Event) -> Vec<(usize, Delivery)> {
+        let endpoints: Vec<(usize, &Endpoint)> = self
+            .endpoints
+            .iter()
+            .enumerate()
+            .filter(|(_, endpoint)| endpoint.filter.matches(event))
+            .collect();
+        if endpoints.is_empty() {
+            return Vec::new();
+        }
+
+        self.sequence += 1;
+        let now = SystemTime::now();
+        let (seconds, millis) = to_unix(now);
+        let id = format!("{}{:03}-{}", seconds, millis, self.sequence);
+        let body = serde_json::json!({
+            "id": id,
+            "kind": event.kind(),
+            "tag": event.tag(),
+            "time": Timestamp::from(now),
+            "event": event,
+        })
+        .to_string();
+
+        endpoints
+            .into_iter()
+            .map(|(index, endpoint)| {
+                let delivery = Delivery {
+                    url: self
+                        .test_url
+                        .clone()
+                        .unwrap_or_else(|| endpoint.url.clone()),
+                    secret: endpoint.secret.clone(),
+                    id: id.clone(),
+                    kind: event.kind().to_string(),
+                    body: body.clone(),
+                };
+                (index, delivery)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Dispatcher, Endpoint, EventFilter, RetryPolicy, TestReceiver};
+    use crate::events::{Event, MemberEvent, Target};
+    use crate::models::clan::Clan;
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+
+    fn joined() -> Event {
+        let clan: Clan =
+            serde_json::from_str(include_str!("../../tests/fixtures/clan.json")).unwrap();
+        let member = clan.member_list.unwrap().0.remove(0);
+        Event::Member {
+            clan_tag: clan.tag,
+            event: MemberEvent::Joined {
+                member: Box::new(member),
+            },
+        }
+    }
+
+    fn failed() -> Event {
+        Event::PollFailed {
+            target: Target::CurrentWar("#2PG".to_string()),
+            message: "Request throttled".to_string(),
+        }
+    }
+
+    fn retry() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(5),
+            max_backoff: Duration::from_millis(20),
+        }
+    }
+
+    #[test]
+    fn test_filter_and_backoff() {
+        let joined = joined();
+        assert_eq!(joined.kind(), "member.joined");
+        assert!(EventFilter::new().matches(&joined));
+        assert!(EventFilter::new().with_kinds(["member.*"]).matches(&joined));
+        assert!(EventFilter::new().with_kinds(["*"]).matches(&joined));
+        assert!(!EventFilter::new().with_kinds(["mem.*"]).matches(&joined));
+        assert!(!EventFilter::new().with_kinds(["war.*"]).matches(&joined));
+        assert!(EventFilter::new().with_tags(["2pp"]).matches(&joined));
+        assert!(!EventFilter::new().with_tags(["#2PG"]).matches(&joined));
+
+        let retry = RetryPolicy::default();
+        assert_eq!(retry.backoff(1), Duration::from_secs(1));
+        assert_eq!(retry.backoff(3), Duration::from_secs(4));
+        assert_eq!(retry.backoff(30), Duration::from_secs(60));
+    }
+
+    #[tokio::test]
+    async fn test_dispatch() {
+        let receiver = TestReceiver::start().await.unwrap();
+        let mut dispatcher = Dispatcher::new()
+            .with_endpoint(
+                Endpoint::new("https://example.com/members")
+                    .with_secret("secret")
+                    .with_filter(EventFilter::new().with_kinds(["member.*"])),
+            )
+            .with_endpoint(Endpoint::new("https://example.com/all"))
+            .with_retry_policy(retry())
+            .with_test_receiver(&format!("{}/hook", receiver.url()));
+
+        assert_eq!(dispatcher.dispatch(&joined()).await, 2);
+        assert_eq!(dispatcher.dispatch(&failed()).await, 1);
+
+        let received = receiver.received();
+        assert_eq!(received.len(), 3);
+        assert_eq!(received[0].path, "/hook");
+        assert_eq!(received[0].kind.as_deref(), Some("member.joined"));
+        let signed: Vec<_> = received.iter().filter(|r| r.verify("secret")).collect();
+        assert_eq!(signed.len(), 1);
+
+        let body = signed[0].json().unwrap();
+        assert_eq!(body["kind"], "member.joined");
+        assert_eq!(body["tag"], "#2PP");
+        assert_eq!(body["event"]["type"], "member");
+        assert_eq!(body["event"]["event"]["type"], "joined");
+        assert_eq!(body["event"]["event"]["member"]["name"], "Alpha");
+
+        receiver.stop().await;
+    }
+
+    #[tokio::test]
+    async fn test_retries_and_dead_letters() {
+        let receiver = TestReceiver::start().await.unwrap();
+        let mut dispatcher = Dispatcher::new()
+            .with_endpoint(Endpoint::new("https://example.com").with_secret("secret"))
+            .with_retry_policy(retry())
+            .with_test_receiver(receiver.url());
+
+        // Delivered on the last attempt
+        receiver.fail_next(2);
+        assert_eq!(dispatcher.dispatch(&joined()).await, 1);
+        assert_eq!(dispatcher.dead_letters().count(), 0);
+
+        receiver.fail_next(3);
+        assert_eq!(dispatcher.dispatch(&failed()).await, 0);
+        let letter = dispatcher.dead_letters().next().unwrap();
+        assert_eq!((letter.kind.as_str(), letter.attempts), ("poll.failed", 3));
+        assert_eq!(
+            letter.error,
+            "Receiver responded with 500 Internal Server Error"
+        );
+
+        assert_eq!(dispatcher.retry_dead_letters().await, 1);
+        assert_eq!(dispatcher.dead_letters().count(), 0);
+
+        let received = receiver.received();
+        assert_eq!(received.len(), 2);
+        assert!(received.iter().all(|r| r.verify("secret")));
+        assert_ne!(received[0].id, received[1].id);
+
+        receiver.stop().await;
+    }
+
+    #[tokio::test]
+    async fn test_failing_endpoint_does_not_block() {
+        let receiver = TestReceiver::start().await.unwrap();
+        // Nothing listens on port 1, every attempt is refused and retried
+        let dispatcher = Dispatcher::new()
+            .with_endpoint(Endpoint::new("http://127.0.0.1:1/hook"))
+            .with_endpoint(Endpoint::new(receiver.url()))
+            .with_retry_policy(RetryPolicy {
+                max_attempts: 3,
+                initial_backoff: Duration::from_millis(300),
+                max_backoff: Duration::from_millis(300),
+            });
+
+        let (events, pending) = mpsc::unbounded_channel();
+        let running = tokio::spawn(dispatcher.run(pending));
+        events.send(joined()).unwrap();
+        events.send(failed()).unwrap();
+
+        // Both reach the healthy endpoint while the first one is still retrying
+        tokio::time::timeout(Duration::from_millis(500), async {
+            while receiver.received().len() < 2 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("healthy endpoint was held up by the failing one");
+        assert!(!running.is_finished());
+
+        drop(events);
+        let dispatcher = running.await.unwrap();
+        let letters: Vec<_> = dispatcher.dead_letters().collect();
+        assert_eq!(letters.len(), 2);
+        assert!(letters
+            .iter()
+            .all(|letter| letter.url == "http://127.0.0.1:1/hook"));
+        assert_eq!(letters[0].kind, "member.joined");
+        assert_eq!(letters[1].kind, "poll.failed");
+
+        receiver.stop().await;
+    }
+}
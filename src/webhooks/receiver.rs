@@ -0,0 +1,132 @@
+//! A local webhook receiver for test mode. It accepts every POST on any path,
+//! records the deliveries and can be told to fail requests to exercise
+//! retries.
+
+use actix_web::dev::ServerHandle;
+use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+
+use super::signature::{verify, SIGNATURE_HEADER};
+use super::{DELIVERY_HEADER, EVENT_HEADER};
+
+/// A delivery as the receiver got it
+#[derive(Debug, Clone)]
+pub struct Received {
+    pub path: String,
+    pub kind: Option<String>,
+    pub id: Option<String>,
+    pub signature: Option<String>,
+    pub body: String,
+}
+
+impl Received {
+    /// Whether the delivery was signed with `secret`
+    #[must_use]
+    pub fn verify(&self, secret: &str) -> bool {
+        self.signature
+            .as_deref()
+            .is_some_and(|signature| verify(secret.as_bytes(), self.body.as_bytes(), signature))
+    }
+
+    pub fn json(&self) -> serde_json::Result<serde_json::Value> {
+        serde_json::from_str(&self.body)
+    }
+}
+
+#[derive(Default)]
+struct State {
+    received: Mutex<Vec<Received>>,
+    /// Requests left to answer with an error
+    failures: AtomicU32,
+}
+
+fn header(request: &HttpRequest, name: &str) -> Option<String> {
+    request
+        .headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+async fn receive(request: HttpRequest, body: String, state: web::Data<State>) -> HttpResponse {
+    let failing = state
+        .failures
+        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| {
+            left.checked_sub(1)
+        })
+        .is_ok();
+    if failing {
+        return HttpResponse::InternalServerError().finish();
+    }
+
+    state
+        .received
+        .lock()
+        .expect("receiver state poisoned")
+        .push(Received {
+            path: request.path().to_string(),
+            kind: header(&request, EVENT_HEADER),
+            id: header(&request, DELIVERY_HEADER),
+            signature: header(&request, SIGNATURE_HEADER),
+            body,
+        });
+    HttpResponse::NoContent().finish()
+}
+
+pub struct TestReceiver {
+    url: String,
+    state: web::Data<State>,
+    handle: ServerHandle,
+}
+
+impl TestReceiver {
+    /// Start a receiver on a free local port
+    pub async fn start() -> std::io::Result<Self> {
+        let state = web::Data::new(State::default());
+        let data = state.clone();
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(data.clone())
+                .default_service(web::post().to(receive))
+        })
+        .workers(1)
+        .disable_signals()
+        .bind(("127.0.0.1", 0))?;
+
+        let address = server.addrs()[0];
+        let server = server.run();
+        let handle = server.handle();
+        tokio::spawn(server);
+
+        Ok(Self {
+            url: format!("http://{address}"),
+            state,
+            handle,
+        })
+    }
+
+    #[must_use]
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Every delivery received so far, oldest first
+    #[must_use]
+    pub fn received(&self) -> Vec<Received> {
+        self.state
+            .received
+            .lock()
+            .expect("receiver state poisoned")
+            .clone()
+    }
+
+    /// Answer the next `count` requests with a server error
+    pub fn fail_next(&self, count: u32) {
+        self.state.failures.store(count, Ordering::SeqCst);
+    }
+
+    pub async fn stop(self) {
+        self.handle.stop(false).await;
+    }
+}